@@ -0,0 +1,190 @@
+//! FORCERENEW op vraag van de beheerder: `dhcp forcerenew <ip> [config]`
+//! stuurt het ip over de unix socket uit `[auth] socket` naar de draaiende
+//! server, want enkel die kent de sleutels van de leases. De server stuurt
+//! de getekende FORCERENEW unicast naar de client op poort 68 en antwoordt
+//! met `ok` of de fout.
+
+use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
+
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::{UdpSocket, UnixListener, UnixStream},
+};
+
+use crate::{error::DHCPError, standard::CLIENT_PORT, state::DHCPState};
+
+/// Antwoord van de server als de FORCERENEW verstuurd is
+const OK: &str = "ok";
+
+pub struct ForcerenewServer {
+    listener: UnixListener,
+    path: PathBuf,
+    state: DHCPState,
+    /// De poort van de clients, anders dan 68 in de tests
+    client_port: u16,
+}
+
+impl ForcerenewServer {
+    pub fn bind(path: &Path, state: DHCPState) -> Result<Self, DHCPError> {
+        // een socket van een vorige server blijft staan
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        Ok(ForcerenewServer {
+            listener: UnixListener::bind(path)?,
+            path: path.to_path_buf(),
+            state,
+            client_port: CLIENT_PORT,
+        })
+    }
+
+    pub async fn run(self) {
+        tracing::info!("Accepting FORCERENEW requests on {}", self.path.display());
+        loop {
+            let stream = match self.listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(error) => {
+                    tracing::error!("FORCERENEW socket error: {error}");
+                    continue;
+                }
+            };
+            if let Err(error) = self.handle(stream).await {
+                tracing::error!("FORCERENEW request failed: {error}");
+            }
+        }
+    }
+
+    async fn handle(&self, stream: UnixStream) -> Result<(), DHCPError> {
+        let mut stream = BufReader::new(stream);
+        let mut line = String::new();
+        stream.read_line(&mut line).await?;
+
+        let reply = match line.trim().parse() {
+            Ok(ip) => match self.send(ip).await {
+                Ok(()) => OK.to_string(),
+                Err(error) => error.to_string(),
+            },
+            Err(_) => format!("Invalid ip address {:?}", line.trim()),
+        };
+        stream.write_all(format!("{reply}\n").as_bytes()).await?;
+        Ok(())
+    }
+
+    async fn send(&self, ip: Ipv4Addr) -> Result<(), DHCPError> {
+        let packet = super::forcerenew(&self.state, ip)
+            .ok_or_else(|| DHCPError::Config(format!("No FORCERENEW key for {ip}")))?;
+        let mut buffer = [0; 4096];
+        let len = packet.write_to_bytes(&mut buffer);
+
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+        socket
+            .send_to(&buffer[..len], SocketAddr::from((ip, self.client_port)))
+            .await?;
+        tracing::info!("Sent FORCERENEW to {ip}");
+        Ok(())
+    }
+}
+
+/// Vraagt de server achter `path` een FORCERENEW naar `ip` te sturen.
+pub async fn request(path: &Path, ip: Ipv4Addr) -> Result<(), DHCPError> {
+    let mut stream = BufReader::new(UnixStream::connect(path).await?);
+    stream.write_all(format!("{ip}\n").as_bytes()).await?;
+
+    let mut reply = String::new();
+    stream.read_line(&mut reply).await?;
+    match reply.trim() {
+        OK => Ok(()),
+        error => Err(DHCPError::Config(error.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{net::Ipv4Addr, time::Duration};
+
+    use tokio::{net::UdpSocket, time::timeout};
+
+    use crate::{
+        config::Config,
+        handlers::{on_dhcp_discover, on_dhcp_request},
+        packet::{auth, DHCPMessageType, DHCPOption, Packet},
+        state::DHCPState,
+    };
+
+    use super::{request, ForcerenewServer};
+
+    const SERVER: Ipv4Addr = Ipv4Addr::new(127, 0, 0, 1);
+
+    fn state() -> DHCPState {
+        let config: Config = toml::from_str(
+            r#"
+            [[subnet]]
+            range_start = "127.0.0.10"
+            range_end = "127.0.0.20"
+            server_addr = "127.0.0.1"
+            subnet_mask = "255.0.0.0"
+
+            [auth]
+            forcerenew = true
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        DHCPState::from_config(config, None)
+    }
+
+    /// Een lease voor een client die FORCERENEW ondersteunt, met de
+    /// reconfigure key uit de ACK.
+    fn lease(state: &DHCPState) -> (Ipv4Addr, Vec<u8>) {
+        let mut discover = Packet::new_request(DHCPMessageType::DHCPDISCOVER);
+        discover.add_option(DHCPOption::ForcerenewNonceCapable(vec![1]));
+        let offer = on_dhcp_discover(discover, state.clone(), None)
+            .unwrap()
+            .unwrap();
+
+        let mut request = Packet::new_request(DHCPMessageType::DHCPREQUEST);
+        request.add_option(DHCPOption::ForcerenewNonceCapable(vec![1]));
+        request.add_option(DHCPOption::RequestedIp(offer.yiaddr));
+        request.add_option(DHCPOption::ServerIdentifier(SERVER));
+        let ack = on_dhcp_request(request, state.clone(), None)
+            .unwrap()
+            .unwrap();
+        (ack.yiaddr, ack.authentication().unwrap().info[1..].to_vec())
+    }
+
+    #[tokio::test]
+    async fn forcerenew_to_client() {
+        let state = state();
+        let (ip, key) = lease(&state);
+        let client = UdpSocket::bind((ip, 0)).await.unwrap();
+
+        let path = std::env::temp_dir().join(format!("dhcp-forcerenew-{}", std::process::id()));
+        let mut server = ForcerenewServer::bind(&path, state).unwrap();
+        server.client_port = client.local_addr().unwrap().port();
+        tokio::spawn(server.run());
+
+        request(&path, ip).await.unwrap();
+        let mut buffer = [0; 1024];
+        let len = timeout(Duration::from_secs(1), client.recv(&mut buffer))
+            .await
+            .unwrap()
+            .unwrap();
+        let renew = Packet::try_from(&buffer[..len]).unwrap();
+        assert_eq!(renew.dhcp_message_type, DHCPMessageType::DHCPFORCERENEW);
+        assert_eq!(renew.ciaddr, ip);
+        let mac = renew.authentication().and_then(|auth| auth.mac()).unwrap();
+        assert!(auth::verify(renew.auth_input().unwrap(), mac, &key));
+
+        // geen lease, geen sleutel
+        let error = request(&path, Ipv4Addr::new(127, 0, 0, 30))
+            .await
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("No FORCERENEW key for 127.0.0.30"));
+        let _ = std::fs::remove_file(&path);
+    }
+}
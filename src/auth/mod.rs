@@ -0,0 +1,458 @@
+//! Authenticatie van clients met option 90 (RFC 3118).
+//!
+//! Een client met een sleutel in zijn `[[host]]` gebruikt delayed
+//! authentication: in de DISCOVER vraagt hij erom, daarna heeft elk bericht
+//! een HMAC-MD5 met die sleutel en een replay detection die bij elk bericht
+//! stijgt. Berichten die daar niet aan voldoen worden genegeerd, zodat
+//! niemand anders bv. zijn lease kan vrijgeven. De server tekent zijn
+//! antwoorden met dezelfde sleutel.
+//!
+//! Een client zonder sleutel kan in de ACK een reconfigure key krijgen
+//! (RFC 6704), waarmee hij een FORCERENEW van deze server herkent. Een
+//! FORCERENEW wordt verstuurd met `dhcp forcerenew <ip>`, zie
+//! [`forcerenew::ForcerenewServer`].
+//!
+//! De replay detection van de clients staat enkel in het geheugen en wordt
+//! nooit opgeruimd. Na een herstart kan elk opgevangen bericht dus nog één
+//! keer opnieuw verstuurd worden. De sleutels voor een FORCERENEW gaan weg
+//! als de lease vrijgegeven wordt of verloopt.
+
+use std::{collections::HashMap, fs::File, io::Read, net::Ipv4Addr};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+
+use crate::{
+    config::AuthKeyConfig,
+    packet::{
+        auth::{
+            self, Authentication, ALGORITHM_HMAC_MD5, MAC_LEN, PROTOCOL_DELAYED, RDM_MONOTONIC,
+        },
+        DHCPMessageType, DHCPOption, Packet,
+    },
+    state::DHCPState,
+};
+
+pub mod forcerenew;
+
+/// Gedeelde sleutel van een client voor delayed authentication
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthKey {
+    pub secret_id: u32,
+    pub secret: Vec<u8>,
+}
+
+impl AuthKey {
+    pub fn from_config(config: &AuthKeyConfig) -> Result<Self, String> {
+        let secret = STANDARD
+            .decode(&config.secret)
+            .map_err(|error| format!("Invalid auth secret: {error}"))?;
+        if secret.is_empty() {
+            return Err("Auth secret is empty".into());
+        }
+        Ok(AuthKey {
+            secret_id: config.secret_id,
+            secret,
+        })
+    }
+}
+
+/// Waarom een bericht niet geauthenticeerd is
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthError {
+    /// De client heeft een sleutel maar het bericht heeft geen MAC
+    Missing,
+    /// De client heeft geen sleutel en `[auth] required` staat aan
+    NoKey,
+    /// Een ander protocol, algoritme of RDM dan delayed authentication met
+    /// HMAC-MD5 en een stijgende replay detection
+    Unsupported,
+    WrongSecret(u32),
+    InvalidMac,
+    /// De replay detection is niet gestegen sinds het vorige bericht
+    Replay {
+        last: u64,
+        received: u64,
+    },
+}
+
+/// Een sleutel om een FORCERENEW naar een lease te tekenen
+#[derive(Debug, Clone)]
+enum ForcerenewKey {
+    Delayed(AuthKey),
+    Reconfigure([u8; MAC_LEN]),
+}
+
+#[derive(Debug, Default)]
+pub struct AuthState {
+    /// De laatste replay detection van elke client, enkel van berichten
+    /// met een geldige MAC
+    received: HashMap<Vec<u8>, u64>,
+    /// De laatste replay detection die de server verstuurd heeft
+    sent: u64,
+    /// Hardware adres en sleutel per lease
+    forcerenew: HashMap<Ipv4Addr, (Vec<u8>, ForcerenewKey)>,
+}
+
+impl AuthState {
+    /// Een tijdstempel zoals NTP, met de seconden in de bovenste 32 bits,
+    /// maar altijd hoger dan de vorige.
+    fn next_replay_detection(&mut self) -> u64 {
+        let now = (Utc::now().timestamp() as u64) << 32;
+        self.sent = now.max(self.sent + 1);
+        self.sent
+    }
+}
+
+fn client_key(state: &DHCPState, packet: &Packet) -> Option<AuthKey> {
+    let host = state.config().host(packet.chaddr())?;
+    // gecontroleerd in Config::validate
+    AuthKey::from_config(host.auth.as_ref()?).ok()
+}
+
+/// Controleert option 90 van een bericht van de client. Geeft de sleutel
+/// terug waarmee het antwoord getekend moet worden, `None` als de client
+/// geen sleutel heeft.
+pub fn authenticate(state: &DHCPState, packet: &Packet) -> Result<Option<AuthKey>, AuthError> {
+    let Some(key) = client_key(state, packet) else {
+        return match state.config().auth.required {
+            true => Err(AuthError::NoKey),
+            false => Ok(None),
+        };
+    };
+    let auth = packet.authentication().ok_or(AuthError::Missing)?;
+    if auth.protocol != PROTOCOL_DELAYED
+        || auth.algorithm != ALGORITHM_HMAC_MD5
+        || auth.rdm != RDM_MONOTONIC
+    {
+        return Err(AuthError::Unsupported);
+    }
+    // in de DISCOVER heeft de client nog geen sleutel gekozen
+    if packet.dhcp_message_type == DHCPMessageType::DHCPDISCOVER {
+        return Ok(Some(key));
+    }
+
+    let secret_id = auth.secret_id().ok_or(AuthError::Missing)?;
+    if secret_id != key.secret_id {
+        return Err(AuthError::WrongSecret(secret_id));
+    }
+    let (Some(input), Some(mac)) = (packet.auth_input(), auth.mac()) else {
+        return Err(AuthError::Missing);
+    };
+    if !auth::verify(input, mac, &key.secret) {
+        return Err(AuthError::InvalidMac);
+    }
+
+    let mut auth_state = state.auth();
+    let received = auth.replay_detection;
+    if let Some(&last) = auth_state.received.get(packet.chaddr()) {
+        if received <= last {
+            return Err(AuthError::Replay { last, received });
+        }
+    }
+    auth_state
+        .received
+        .insert(packet.chaddr().to_vec(), received);
+    Ok(Some(key))
+}
+
+/// Tekent het antwoord op `request` met de sleutel van de client. Een
+/// client zonder sleutel krijgt een reconfigure key in de ACK als
+/// `[auth] forcerenew` aan staat en de client HMAC-MD5 ondersteunt.
+pub fn sign_response(
+    state: &DHCPState,
+    request: &Packet,
+    response: &mut Packet,
+    key: Option<AuthKey>,
+) {
+    let ack =
+        response.dhcp_message_type == DHCPMessageType::DHCPACK && !response.yiaddr.is_unspecified();
+    let capable = matches!(
+        request.get_option(145),
+        Some(DHCPOption::ForcerenewNonceCapable(algorithms)) if algorithms.contains(&ALGORITHM_HMAC_MD5)
+    );
+
+    let mut auth_state = state.auth();
+    let replay_detection = auth_state.next_replay_detection();
+    let (option, forcerenew_key) = match key {
+        Some(key) => (
+            Authentication::delayed(replay_detection, key.secret_id, &key.secret),
+            ForcerenewKey::Delayed(key),
+        ),
+        None if ack && capable && state.config().auth.forcerenew => {
+            let key = match random_key() {
+                Ok(key) => key,
+                Err(error) => {
+                    println!("Could not create a reconfigure key: {error}");
+                    return;
+                }
+            };
+            (
+                Authentication::reconfigure_key(replay_detection, &key),
+                ForcerenewKey::Reconfigure(key),
+            )
+        }
+        None => return,
+    };
+    response.override_option(DHCPOption::Authentication(option));
+    if ack {
+        auth_state
+            .forcerenew
+            .insert(response.yiaddr, (request.chaddr().to_vec(), forcerenew_key));
+    }
+}
+
+/// De lease van `ip` is vrijgegeven of verlopen, er kan geen FORCERENEW meer naartoe.
+pub fn forget_lease(state: &DHCPState, ip: Ipv4Addr) {
+    state.auth().forcerenew.remove(&ip);
+}
+
+/// Een getekende FORCERENEW (RFC 3203) voor de lease van `ip`, die unicast
+/// naar de client gestuurd wordt. `None` als de server geen sleutel voor de
+/// lease heeft: zonder authenticatie zou de client ze moeten negeren.
+pub fn forcerenew(state: &DHCPState, ip: Ipv4Addr) -> Option<Packet> {
+    let server_addr = state
+        .lock()
+        .iter()
+        .find(|range| range.contains(ip))?
+        .server_addr;
+
+    let mut auth_state = state.auth();
+    let (hardware_address, key) = auth_state.forcerenew.get(&ip)?.clone();
+    let replay_detection = auth_state.next_replay_detection();
+    let option = match key {
+        ForcerenewKey::Delayed(key) => {
+            Authentication::delayed(replay_detection, key.secret_id, &key.secret)
+        }
+        ForcerenewKey::Reconfigure(key) => Authentication::reconfigure_mac(replay_detection, &key),
+    };
+
+    let mut packet = Packet::new_forcerenew(ip, &hardware_address, replay_detection as u32);
+    packet.add_option(DHCPOption::ServerIdentifier(server_addr));
+    packet.add_option(DHCPOption::Authentication(option));
+    Some(packet)
+}
+
+fn random_key() -> std::io::Result<[u8; MAC_LEN]> {
+    let mut key = [0; MAC_LEN];
+    File::open("/dev/urandom")?.read_exact(&mut key)?;
+    Ok(key)
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use chrono::Utc;
+
+    use crate::{
+        config::Config,
+        handlers::{on_dhcp_discover, on_dhcp_release, on_dhcp_request},
+        packet::{
+            auth::{self, Authentication, PROTOCOL_RECONFIGURE_KEY, RECONFIGURE_KEY_VALUE},
+            DHCPMessageType, DHCPOption, Packet,
+        },
+        state::DHCPState,
+    };
+
+    use super::{authenticate, forcerenew, AuthError};
+
+    const KNOWN: [u8; 6] = [0xde, 0xad, 0xc0, 0xde, 0xca, 0xfe];
+    const OTHER: [u8; 6] = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+    const SECRET: &[u8] = b"secret";
+    const SERVER: Ipv4Addr = Ipv4Addr::new(192, 168, 56, 1);
+
+    fn state(auth: &str) -> DHCPState {
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [[subnet]]
+            range_start = "192.168.56.10"
+            range_end = "192.168.56.20"
+            server_addr = "192.168.56.1"
+            subnet_mask = "255.255.255.0"
+
+            [[host]]
+            hardware_address = "de:ad:c0:de:ca:fe"
+            [host.auth]
+            secret_id = 1
+            secret = "c2VjcmV0"
+
+            [auth]
+            {auth}
+            "#
+        ))
+        .unwrap();
+        assert!(config.validate().is_ok());
+        DHCPState::from_config(config, None)
+    }
+
+    fn packet(message_type: DHCPMessageType, chaddr: [u8; 6]) -> Packet {
+        let mut packet = Packet::new_request(message_type);
+        packet.set_chaddr(&chaddr);
+        packet
+    }
+
+    /// Schrijft het pakket weg zoals de client het verstuurt, met de MAC
+    /// ingevuld, en leest het terug in zoals de server het ontvangt.
+    fn sent(packet: &Packet) -> Packet {
+        let mut buffer = [0; 1024];
+        let len = packet.write_to_bytes(&mut buffer);
+        Packet::try_from(&buffer[..len]).unwrap()
+    }
+
+    fn signed(mut packet: Packet, replay_detection: u64, key: &[u8]) -> Packet {
+        packet.add_option(DHCPOption::Authentication(Authentication::delayed(
+            replay_detection,
+            1,
+            key,
+        )));
+        sent(&packet)
+    }
+
+    /// Controleert de MAC van een bericht van de server zoals de client dat
+    /// doet. `packet` moet van de server komen: een ontvangen pakket
+    /// opnieuw wegschrijven kan de volgorde van de opties veranderen.
+    fn verify(packet: &Packet, key: &[u8]) -> bool {
+        let packet = sent(packet);
+        let mac = packet.authentication().and_then(|auth| auth.mac()).unwrap();
+        auth::verify(packet.auth_input().unwrap(), mac, key)
+    }
+
+    fn request(ip: Ipv4Addr) -> Packet {
+        let mut request = packet(DHCPMessageType::DHCPREQUEST, KNOWN);
+        request.add_option(DHCPOption::RequestedIp(ip));
+        request.add_option(DHCPOption::ServerIdentifier(SERVER));
+        request
+    }
+
+    fn release(ip: Ipv4Addr) -> Packet {
+        let mut release = packet(DHCPMessageType::DHCPRELEASE, KNOWN);
+        release.ciaddr = ip;
+        release
+    }
+
+    fn leased(state: &DHCPState, ip: Ipv4Addr) -> bool {
+        state.lock()[0].data.contains_key(&ip)
+    }
+
+    #[test]
+    fn delayed_authentication() {
+        let state = state("");
+        // zonder option 90 geen antwoord voor een client met een sleutel
+        let discover = packet(DHCPMessageType::DHCPDISCOVER, KNOWN);
+        assert_eq!(authenticate(&state, &discover), Err(AuthError::Missing));
+        assert!(on_dhcp_discover(discover.clone(), state.clone(), None)
+            .unwrap()
+            .is_none());
+
+        // de DISCOVER vraagt om delayed authentication, zonder MAC
+        let mut discover = discover;
+        discover.add_option(DHCPOption::Authentication(Authentication {
+            info: Vec::new(),
+            ..Authentication::delayed(1, 0, SECRET)
+        }));
+        let offer = on_dhcp_discover(sent(&discover), state.clone(), None)
+            .unwrap()
+            .unwrap();
+        assert!(verify(&offer, SECRET));
+        assert_eq!(offer.authentication().unwrap().secret_id(), Some(1));
+
+        let ip = offer.yiaddr;
+        let ack = on_dhcp_request(signed(request(ip), 2, SECRET), state.clone(), None)
+            .unwrap()
+            .unwrap();
+        assert_eq!(ack.dhcp_message_type, DHCPMessageType::DHCPACK);
+        assert!(verify(&ack, SECRET));
+
+        // vervalste releases
+        assert_eq!(
+            authenticate(&state, &sent(&release(ip))),
+            Err(AuthError::Missing)
+        );
+        let spoofed = signed(release(ip), 3, b"guess");
+        assert_eq!(authenticate(&state, &spoofed), Err(AuthError::InvalidMac));
+        on_dhcp_release(spoofed, state.clone()).unwrap();
+        assert!(leased(&state, ip));
+
+        // een opnieuw verstuurd bericht van de client zelf
+        let replayed = signed(release(ip), 2, SECRET);
+        assert_eq!(
+            authenticate(&state, &replayed),
+            Err(AuthError::Replay {
+                last: 2,
+                received: 2
+            })
+        );
+        on_dhcp_release(replayed, state.clone()).unwrap();
+        assert!(leased(&state, ip));
+
+        // de FORCERENEW is getekend met de sleutel van de client
+        let renew = forcerenew(&state, ip).unwrap();
+        assert_eq!(renew.dhcp_message_type, DHCPMessageType::DHCPFORCERENEW);
+        assert_eq!(renew.ciaddr, ip);
+        assert_eq!(renew.chaddr(), KNOWN);
+        assert!(verify(&renew, SECRET));
+
+        on_dhcp_release(signed(release(ip), 3, SECRET), state.clone()).unwrap();
+        assert!(!leased(&state, ip));
+        assert!(forcerenew(&state, ip).is_none());
+    }
+
+    #[test]
+    fn reconfigure_key() {
+        let state = state("forcerenew = true");
+        let lease = |chaddr: [u8; 6], capable: bool| {
+            let mut discover = packet(DHCPMessageType::DHCPDISCOVER, chaddr);
+            let mut request = packet(DHCPMessageType::DHCPREQUEST, chaddr);
+            if capable {
+                discover.add_option(DHCPOption::ForcerenewNonceCapable(vec![1]));
+                request.add_option(DHCPOption::ForcerenewNonceCapable(vec![1]));
+            }
+            let offer = on_dhcp_discover(discover, state.clone(), None)
+                .unwrap()
+                .unwrap();
+            assert!(offer.authentication().is_none());
+            request.add_option(DHCPOption::RequestedIp(offer.yiaddr));
+            request.add_option(DHCPOption::ServerIdentifier(SERVER));
+            on_dhcp_request(request, state.clone(), None)
+                .unwrap()
+                .unwrap()
+        };
+
+        // zonder option 145 geen reconfigure key en geen FORCERENEW
+        let ack = lease([0x00, 0x11, 0x22, 0x33, 0x44, 0x56], false);
+        assert!(ack.authentication().is_none());
+        assert!(forcerenew(&state, ack.yiaddr).is_none());
+
+        let ack = lease(OTHER, true);
+        let auth = ack.authentication().unwrap();
+        assert_eq!(auth.protocol, PROTOCOL_RECONFIGURE_KEY);
+        assert_eq!(auth.info[0], RECONFIGURE_KEY_VALUE);
+        let key = auth.info[1..].to_vec();
+        assert_eq!(key.len(), 16);
+
+        let renew = forcerenew(&state, ack.yiaddr).unwrap();
+        let auth = renew.authentication().unwrap();
+        assert_eq!(auth.protocol, PROTOCOL_RECONFIGURE_KEY);
+        assert!(matches!(
+            renew.get_option(54),
+            Some(DHCPOption::ServerIdentifier(server)) if *server == SERVER
+        ));
+        assert!(verify(&renew, &key));
+        assert!(!verify(&renew, SECRET));
+
+        // na het verlopen van de lease is de sleutel weg
+        state.expire_leases(Utc::now() + chrono::Duration::days(2));
+        assert!(forcerenew(&state, ack.yiaddr).is_none());
+    }
+
+    #[test]
+    fn required() {
+        let state = state("required = true");
+        let discover = packet(DHCPMessageType::DHCPDISCOVER, OTHER);
+        assert_eq!(authenticate(&state, &discover), Err(AuthError::NoKey));
+        assert!(on_dhcp_discover(discover, state.clone(), None)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,240 @@
+//! Netboot: het bootbestand en de TFTP server kiezen voor PXE clients.
+
+use std::net::Ipv4Addr;
+
+use crate::{
+    config::BootRule,
+    packet::{DHCPOption, Packet},
+};
+
+pub mod proxy;
+
+/// Vendor class (60) waarmee PXE clients zich aanmelden
+pub const PXE_CLIENT_CLASS: &[u8] = b"PXEClient";
+
+/// User class (77) van clients die iPXE draaien
+pub const IPXE_USER_CLASS: &[u8] = b"iPXE";
+
+/// Lengte van het `file` veld zonder de 0 op het einde
+const FILE_FIELD_LEN: usize = 127;
+
+/// Wat een PXE client over zichzelf meestuurt, om een regel te kiezen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BootClient<'a> {
+    /// Option 93, leeg als de client die niet stuurt
+    pub architectures: &'a [u16],
+    /// Option 77
+    pub user_classes: &'a [Vec<u8>],
+    /// De client draait al iPXE: user class `iPXE` of option 175
+    pub ipxe: bool,
+}
+
+impl<'a> BootClient<'a> {
+    /// `None` als de client niet via PXE boot: geen option 93, geen vendor
+    /// class die met `PXEClient` begint en geen iPXE.
+    pub fn from_packet(packet: &'a Packet) -> Option<Self> {
+        let architectures = match packet.get_option(93) {
+            Some(DHCPOption::ClientArchitecture(types)) => types.as_slice(),
+            _ => &[],
+        };
+        let user_classes = match packet.get_option(77) {
+            Some(DHCPOption::UserClass(classes)) => classes.as_slice(),
+            _ => &[],
+        };
+        let ipxe = packet.get_option(175).is_some()
+            || user_classes.iter().any(|class| class == IPXE_USER_CLASS);
+        let pxe_class = matches!(
+            packet.get_option(60),
+            Some(DHCPOption::ClassIdentifier(class)) if class.starts_with(PXE_CLIENT_CLASS)
+        );
+
+        if !pxe_class && !ipxe && architectures.is_empty() {
+            return None;
+        }
+        Some(BootClient {
+            architectures,
+            user_classes,
+            ipxe,
+        })
+    }
+
+    /// Alle voorwaarden die in de regel staan moeten kloppen.
+    pub fn matches(&self, rule: &BootRule) -> bool {
+        let architecture = rule.architecture.is_none_or(|architecture| {
+            architecture
+                .codes()
+                .iter()
+                .any(|code| self.architectures.contains(code))
+        });
+        let user_class = rule.user_class.as_ref().is_none_or(|user_class| {
+            self.user_classes
+                .iter()
+                .any(|class| class == user_class.as_bytes())
+        });
+        let ipxe = rule.ipxe.is_none_or(|ipxe| ipxe == self.ipxe);
+        architecture && user_class && ipxe
+    }
+}
+
+/// Zoekt de eerste regel die past bij de client, `None` voor clients die
+/// niet via PXE booten.
+pub fn select_rule<'a>(rules: &'a [BootRule], packet: &Packet) -> Option<&'a BootRule> {
+    let client = BootClient::from_packet(packet)?;
+    rules.iter().find(|rule| client.matches(rule))
+}
+
+/// Vult `siaddr`, `sname` en `file` in. Options 66 en 67 worden enkel
+/// meegegeven als de client ze vraagt, PXE firmware leest meestal de velden.
+/// Een bootbestand dat niet in het `file` veld past (bv. een lange URL voor
+/// iPXE) gaat altijd mee in option 67.
+pub fn apply(packet: &mut Packet, rule: &BootRule, server_addr: Ipv4Addr, requested: &[u8]) {
+    packet.siaddr = rule.next_server.unwrap_or(server_addr);
+    packet.set_file(&rule.file);
+    if requested.contains(&67) || rule.file.len() > FILE_FIELD_LEN {
+        packet.override_option(DHCPOption::BootfileName(rule.file.clone()));
+    }
+    if let Some(server_name) = &rule.server_name {
+        packet.set_sname(server_name);
+        if requested.contains(&66) {
+            packet.override_option(DHCPOption::TftpServerName(server_name.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use crate::{
+        config::{Architecture, BootRule},
+        packet::{DHCPMessageType, DHCPOption, Packet},
+    };
+
+    use super::{apply, select_rule};
+
+    fn rule(architecture: Option<Architecture>, file: &str) -> BootRule {
+        BootRule {
+            architecture,
+            user_class: None,
+            ipxe: None,
+            file: file.into(),
+            next_server: None,
+            server_name: None,
+        }
+    }
+
+    fn pxe_client(architecture: u16) -> Packet {
+        let mut packet = Packet::new_request(DHCPMessageType::DHCPDISCOVER);
+        packet.add_option(DHCPOption::ClientArchitecture(vec![architecture]));
+        packet
+    }
+
+    #[test]
+    fn file_per_architecture() {
+        let rules = vec![
+            rule(Some(Architecture::Bios), "undionly.kpxe"),
+            rule(Some(Architecture::UefiX64), "ipxe.efi"),
+            rule(Some(Architecture::UefiArm64), "ipxe-arm64.efi"),
+        ];
+
+        let file = |packet: &Packet| select_rule(&rules, packet).map(|rule| rule.file.as_str());
+        assert_eq!(file(&pxe_client(0)), Some("undionly.kpxe"));
+        assert_eq!(file(&pxe_client(7)), Some("ipxe.efi"));
+        assert_eq!(file(&pxe_client(9)), Some("ipxe.efi"));
+        assert_eq!(file(&pxe_client(11)), Some("ipxe-arm64.efi"));
+        assert_eq!(file(&pxe_client(10)), None);
+        // geen PXE client
+        assert_eq!(
+            file(&Packet::new_request(DHCPMessageType::DHCPDISCOVER)),
+            None
+        );
+    }
+
+    #[test]
+    fn fallback_rule() {
+        let rules = vec![
+            rule(Some(Architecture::Bios), "pxelinux.0"),
+            rule(None, "grubx64.efi"),
+        ];
+        let mut packet = Packet::new_request(DHCPMessageType::DHCPDISCOVER);
+        packet.add_option(DHCPOption::ClassIdentifier(
+            b"PXEClient:Arch:00007:UNDI:003016".to_vec(),
+        ));
+        assert_eq!(select_rule(&rules, &packet).unwrap().file, "grubx64.efi");
+    }
+
+    #[test]
+    fn chainload_ipxe() {
+        let rules = vec![
+            BootRule {
+                ipxe: Some(true),
+                next_server: Some(Ipv4Addr::new(192, 168, 56, 2)),
+                ..rule(None, "http://192.168.56.2/boot.ipxe")
+            },
+            BootRule {
+                user_class: Some("lab".into()),
+                ..rule(None, "lab.kpxe")
+            },
+            rule(Some(Architecture::Bios), "undionly.kpxe"),
+            rule(Some(Architecture::UefiX64), "ipxe.efi"),
+        ];
+
+        // eerste stap: de PXE firmware krijgt iPXE
+        let firmware = pxe_client(7);
+        assert_eq!(select_rule(&rules, &firmware).unwrap().file, "ipxe.efi");
+
+        // tweede stap: iPXE krijgt het script
+        let mut ipxe = pxe_client(7);
+        ipxe.add_option(DHCPOption::UserClass(vec![b"iPXE".to_vec()]));
+        let rule = select_rule(&rules, &ipxe).unwrap();
+        assert_eq!(rule.file, "http://192.168.56.2/boot.ipxe");
+        assert_eq!(rule.next_server, Some(Ipv4Addr::new(192, 168, 56, 2)));
+
+        // iPXE herkend aan option 175
+        let mut ipxe = pxe_client(0);
+        ipxe.add_option(DHCPOption::IpxeEncapsulated(vec![177, 1, 1]));
+        assert_eq!(
+            select_rule(&rules, &ipxe).unwrap().file,
+            "http://192.168.56.2/boot.ipxe"
+        );
+
+        let mut lab = pxe_client(0);
+        lab.add_option(DHCPOption::UserClass(vec![b"lab".to_vec()]));
+        assert_eq!(select_rule(&rules, &lab).unwrap().file, "lab.kpxe");
+    }
+
+    #[test]
+    fn apply_boot_info() {
+        let server = Ipv4Addr::new(192, 168, 56, 1);
+        let mut packet = pxe_client(7);
+        packet.into_response(DHCPMessageType::DHCPOFFER);
+        apply(&mut packet, &rule(None, "ipxe.efi"), server, &[]);
+        assert_eq!(packet.siaddr, server);
+        assert_eq!(packet.file(), "ipxe.efi");
+        assert!(packet.get_option(67).is_none());
+
+        let tftp_rule = BootRule {
+            next_server: Some(Ipv4Addr::new(10, 0, 0, 5)),
+            server_name: Some("tftp.test".into()),
+            ..rule(None, "ipxe.efi")
+        };
+        apply(&mut packet, &tftp_rule, server, &[66, 67]);
+        assert_eq!(packet.siaddr, Ipv4Addr::new(10, 0, 0, 5));
+        assert_eq!(packet.sname(), "tftp.test");
+        assert!(
+            matches!(packet.get_option(66), Some(DHCPOption::TftpServerName(name)) if name == "tftp.test")
+        );
+        assert!(
+            matches!(packet.get_option(67), Some(DHCPOption::BootfileName(file)) if file == "ipxe.efi")
+        );
+
+        // te lang voor het file veld
+        let url = format!("http://boot.test/{}", "a".repeat(150));
+        let mut packet = pxe_client(7);
+        packet.into_response(DHCPMessageType::DHCPOFFER);
+        apply(&mut packet, &rule(None, &url), server, &[]);
+        assert!(
+            matches!(packet.get_option(67), Some(DHCPOption::BootfileName(file)) if *file == url)
+        );
+    }
+}
@@ -0,0 +1,205 @@
+//! ProxyDHCP (PXE spec 2.5): een andere server deelt de adressen uit, deze
+//! server geeft PXE clients enkel boot info. De client krijgt eerst een
+//! OFFER zonder adres met een boot server lijst en vraagt daarna het
+//! bootbestand aan de boot server op poort 4011.
+
+use std::net::Ipv4Addr;
+
+use crate::{
+    config::ProxyConfig,
+    packet::{DHCPMessageType, DHCPOption, Packet},
+    vendor::{decode_suboptions, encode_suboptions},
+};
+
+use super::{apply, select_rule, PXE_CLIENT_CLASS};
+
+/// PXE sub-opties in option 43 (PXE spec 2.4)
+const PXE_DISCOVERY_CONTROL: u8 = 6;
+const PXE_BOOT_SERVERS: u8 = 8;
+const PXE_BOOT_MENU: u8 = 9;
+const PXE_MENU_PROMPT: u8 = 10;
+const PXE_BOOT_ITEM: u8 = 71;
+const END_SUBOPTION: u8 = 255;
+
+/// Geen broadcast of multicast discovery, enkel de servers uit de lijst
+const DISCOVERY_SERVER_LIST_ONLY: u8 = 0b0111;
+/// Eigen boot server type, type 0 betekent voor veel firmware lokaal booten
+const BOOT_SERVER_TYPE: u16 = 0x8000;
+
+/// Option 43 met PXE sub-opties, afgesloten met 255.
+fn pxe_options(suboptions: &[(u8, Vec<u8>)]) -> DHCPOption {
+    let mut data = encode_suboptions(suboptions);
+    data.push(END_SUBOPTION);
+    DHCPOption::VendorSpecific(data)
+}
+
+/// Antwoord op een DISCOVER van een PXE client op poort 67: een OFFER
+/// zonder adres die de client naar deze boot server stuurt.
+pub fn on_proxy_discover(mut packet: Packet, proxy: &ProxyConfig) -> Option<Packet> {
+    select_rule(&proxy.boot_rules, &packet)?;
+    let uuid = packet.get_option(97).cloned();
+
+    packet.into_response(DHCPMessageType::DHCPOFFER);
+    packet.yiaddr = Ipv4Addr::UNSPECIFIED;
+    packet.siaddr = proxy.server_addr;
+    packet.set_broadcast();
+    add_pxe_options(&mut packet, proxy, uuid);
+
+    let mut boot_servers = BOOT_SERVER_TYPE.to_be_bytes().to_vec();
+    boot_servers.push(1);
+    boot_servers.extend_from_slice(&proxy.server_addr.octets());
+    let mut menu = BOOT_SERVER_TYPE.to_be_bytes().to_vec();
+    menu.push(proxy.menu.len() as u8);
+    menu.extend_from_slice(proxy.menu.as_bytes());
+    // timeout 0: meteen het eerste item uit het menu booten
+    let mut prompt = vec![0];
+    prompt.extend_from_slice(proxy.menu.as_bytes());
+
+    packet.override_option(pxe_options(&[
+        (PXE_DISCOVERY_CONTROL, vec![DISCOVERY_SERVER_LIST_ONLY]),
+        (PXE_BOOT_SERVERS, boot_servers),
+        (PXE_BOOT_MENU, menu),
+        (PXE_MENU_PROMPT, prompt),
+    ]));
+    Some(packet)
+}
+
+/// Antwoord op een REQUEST op poort 4011: een ACK met het bootbestand,
+/// het adres van de client blijft ongemoeid.
+pub fn on_boot_request(mut packet: Packet, proxy: &ProxyConfig) -> Option<Packet> {
+    let rule = select_rule(&proxy.boot_rules, &packet)?.clone();
+    let requested = packet.get_parameter_request();
+    let uuid = packet.get_option(97).cloned();
+    let boot_item = match packet.get_option(43) {
+        Some(DHCPOption::VendorSpecific(data)) => decode_suboptions(data)
+            .into_iter()
+            .find(|(tag, _)| *tag == PXE_BOOT_ITEM)
+            .map(|(_, item)| item.to_vec()),
+        _ => None,
+    };
+    if let Some(item) = &boot_item {
+        if item.get(..2) != Some(&BOOT_SERVER_TYPE.to_be_bytes()[..]) {
+            println!("Boot item {item:?} is not ours, ignoring");
+            return None;
+        }
+    }
+
+    packet.into_response(DHCPMessageType::DHCPACK);
+    packet.yiaddr = Ipv4Addr::UNSPECIFIED;
+    apply(&mut packet, &rule, proxy.server_addr, &requested);
+    add_pxe_options(&mut packet, proxy, uuid);
+    if let Some(item) = boot_item {
+        packet.override_option(pxe_options(&[(PXE_BOOT_ITEM, item)]));
+    }
+    Some(packet)
+}
+
+/// Opties die in elk antwoord aan een PXE client horen. De UUID (97) wordt
+/// teruggestuurd als de client die meegaf.
+fn add_pxe_options(packet: &mut Packet, proxy: &ProxyConfig, uuid: Option<DHCPOption>) {
+    packet.override_option(DHCPOption::ServerIdentifier(proxy.server_addr));
+    packet.override_option(DHCPOption::ClassIdentifier(PXE_CLIENT_CLASS.to_vec()));
+    if let Some(uuid) = uuid {
+        packet.override_option(uuid);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use crate::{
+        config::{Architecture, BootRule, ProxyConfig},
+        packet::{DHCPMessageType, DHCPOption, Packet},
+        vendor::decode_suboptions,
+    };
+
+    use super::{on_boot_request, on_proxy_discover, pxe_options, PXE_BOOT_ITEM, PXE_BOOT_SERVERS};
+
+    fn proxy() -> ProxyConfig {
+        ProxyConfig {
+            server_addr: Ipv4Addr::new(192, 168, 56, 2),
+            menu: "Network boot".into(),
+            boot_rules: vec![BootRule {
+                architecture: Some(Architecture::Bios),
+                user_class: None,
+                ipxe: None,
+                file: "undionly.kpxe".into(),
+                next_server: None,
+                server_name: None,
+            }],
+        }
+    }
+
+    fn pxe_client(message_type: DHCPMessageType) -> Packet {
+        let mut packet = Packet::new_request(message_type);
+        packet.add_option(DHCPOption::ClassIdentifier(
+            b"PXEClient:Arch:00000:UNDI:002001".to_vec(),
+        ));
+        packet.add_option(DHCPOption::ClientArchitecture(vec![0]));
+        packet.add_option(DHCPOption::ClientMachineId([7; 16]));
+        packet
+    }
+
+    fn vendor_options(packet: &Packet) -> Vec<(u8, Vec<u8>)> {
+        match packet.get_option(43) {
+            Some(DHCPOption::VendorSpecific(data)) => decode_suboptions(data)
+                .into_iter()
+                .map(|(tag, value)| (tag, value.to_vec()))
+                .collect(),
+            _ => panic!("No vendor options"),
+        }
+    }
+
+    #[test]
+    fn proxy_offer() {
+        let offer = on_proxy_discover(pxe_client(DHCPMessageType::DHCPDISCOVER), &proxy()).unwrap();
+        assert_eq!(offer.dhcp_message_type, DHCPMessageType::DHCPOFFER);
+        assert!(offer.yiaddr.is_unspecified());
+        assert_eq!(offer.siaddr, Ipv4Addr::new(192, 168, 56, 2));
+        assert!(offer.get_option(51).is_none());
+        assert!(
+            matches!(offer.get_option(60), Some(DHCPOption::ClassIdentifier(class)) if class == b"PXEClient")
+        );
+        assert!(
+            matches!(offer.get_option(97), Some(DHCPOption::ClientMachineId(uuid)) if *uuid == [7; 16])
+        );
+
+        let vendor = vendor_options(&offer);
+        let servers = vendor
+            .iter()
+            .find(|(tag, _)| *tag == PXE_BOOT_SERVERS)
+            .unwrap();
+        assert_eq!(servers.1, [0x80, 0, 1, 192, 168, 56, 2]);
+
+        // geen PXE client
+        let dhcp_client = Packet::new_request(DHCPMessageType::DHCPDISCOVER);
+        assert!(on_proxy_discover(dhcp_client, &proxy()).is_none());
+        // geen regel voor deze architectuur
+        let mut uefi = pxe_client(DHCPMessageType::DHCPDISCOVER);
+        uefi.override_option(DHCPOption::ClientArchitecture(vec![7]));
+        assert!(on_proxy_discover(uefi, &proxy()).is_none());
+    }
+
+    #[test]
+    fn boot_server_ack() {
+        let mut request = pxe_client(DHCPMessageType::DHCPREQUEST);
+        request.ciaddr = Ipv4Addr::new(192, 168, 56, 50);
+        request.add_option(pxe_options(&[(PXE_BOOT_ITEM, vec![0x80, 0, 0, 0])]));
+
+        let ack = on_boot_request(request.clone(), &proxy()).unwrap();
+        assert_eq!(ack.dhcp_message_type, DHCPMessageType::DHCPACK);
+        assert_eq!(ack.ciaddr, Ipv4Addr::new(192, 168, 56, 50));
+        assert!(ack.yiaddr.is_unspecified());
+        assert_eq!(ack.file(), "undionly.kpxe");
+        assert_eq!(ack.siaddr, Ipv4Addr::new(192, 168, 56, 2));
+        assert_eq!(
+            vendor_options(&ack),
+            vec![(PXE_BOOT_ITEM, vec![0x80, 0, 0, 0])]
+        );
+
+        // boot item van een andere server
+        request.override_option(pxe_options(&[(PXE_BOOT_ITEM, vec![0, 1, 0, 0])]));
+        assert!(on_boot_request(request, &proxy()).is_none());
+    }
+}
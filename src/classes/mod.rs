@@ -0,0 +1,211 @@
+//! Indeling van clients in classes op basis van de velden in hun request,
+//! voor er een adres gekozen wordt. Een client kan bij meerdere classes
+//! horen, die elk de leasetijd en opties kunnen aanpassen.
+
+use crate::{
+    config::{ClassConfig, ClassMatch},
+    leases::hostname,
+    packet::{DHCPOption, Packet},
+};
+
+/// Relay agent sub-opties (RFC 3046 2.0)
+pub const RELAY_CIRCUIT_ID: u8 = 1;
+pub const RELAY_REMOTE_ID: u8 = 2;
+
+/// De velden van een request waarop classes kunnen matchen.
+#[derive(Debug, Clone, Copy)]
+pub struct ClassContext<'a> {
+    pub packet: &'a Packet,
+    /// De interface waarop het pakket binnenkwam
+    pub interface: Option<&'a str>,
+}
+
+impl<'a> ClassContext<'a> {
+    pub fn new(packet: &'a Packet, interface: Option<&'a str>) -> Self {
+        ClassContext { packet, interface }
+    }
+
+    fn vendor_class(&self) -> Option<&'a [u8]> {
+        match self.packet.get_option(60) {
+            Some(DHCPOption::ClassIdentifier(class)) => Some(class),
+            _ => None,
+        }
+    }
+
+    fn user_classes(&self) -> &'a [Vec<u8>] {
+        match self.packet.get_option(77) {
+            Some(DHCPOption::UserClass(classes)) => classes,
+            _ => &[],
+        }
+    }
+
+    fn fingerprint(&self) -> Option<&'a [u8]> {
+        match self.packet.get_option(55) {
+            Some(DHCPOption::ParameterRequest(requested)) => Some(requested),
+            _ => None,
+        }
+    }
+}
+
+impl ClassMatch {
+    /// Alle voorwaarden die ingevuld zijn moeten kloppen.
+    pub fn matches(&self, context: &ClassContext) -> bool {
+        let bytes_eq = |expected: &Option<String>, actual: Option<&[u8]>| {
+            expected
+                .as_ref()
+                .is_none_or(|expected| actual == Some(expected.as_bytes()))
+        };
+        let bytes_prefix = |prefix: &Option<String>, actual: Option<&[u8]>| {
+            prefix.as_ref().is_none_or(|prefix| {
+                actual.is_some_and(|actual| actual.starts_with(prefix.as_bytes()))
+            })
+        };
+        let hostname = hostname::client_hostname(context.packet).map(str::as_bytes);
+
+        self.hardware_prefix
+            .as_ref()
+            .is_none_or(|prefix| context.packet.chaddr().starts_with(&prefix.0))
+            && bytes_eq(&self.vendor_class, context.vendor_class())
+            && bytes_prefix(&self.vendor_class_prefix, context.vendor_class())
+            && self.user_class.as_ref().is_none_or(|user_class| {
+                context
+                    .user_classes()
+                    .iter()
+                    .any(|class| class == user_class.as_bytes())
+            })
+            && bytes_eq(&self.circuit_id, context.packet.relay_suboption(RELAY_CIRCUIT_ID))
+            && bytes_eq(&self.remote_id, context.packet.relay_suboption(RELAY_REMOTE_ID))
+            && bytes_eq(&self.hostname, hostname)
+            && bytes_prefix(&self.hostname_prefix, hostname)
+            && self
+                .fingerprint
+                .as_ref()
+                .is_none_or(|fingerprint| context.fingerprint() == Some(fingerprint.as_slice()))
+            && self
+                .interface
+                .as_ref()
+                .is_none_or(|interface| context.interface == Some(interface.as_str()))
+    }
+}
+
+/// Geeft alle classes waar de client bij hoort, in de volgorde van de config.
+pub fn classify<'a>(classes: &'a [ClassConfig], context: &ClassContext) -> Vec<&'a ClassConfig> {
+    classes
+        .iter()
+        .filter(|class| class.conditions.matches(context))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        config::Config,
+        packet::{DHCPMessageType, DHCPOption, Packet},
+    };
+
+    use super::{classify, ClassContext};
+
+    fn config() -> Config {
+        let classes: Config = toml::from_str(
+            r#"
+            interfaces = ["eth0", "eth1"]
+
+            [[class]]
+            name = "printers"
+            default_lease_time = 86400
+            [class.match]
+            hardware_prefix = "00:1b:a9"
+
+            [[class]]
+            name = "phones"
+            [class.match]
+            vendor_class_prefix = "Cisco Systems, Inc. IP Phone"
+            circuit_id = "ge-0/0/1"
+
+            [[class]]
+            name = "ipxe"
+            [class.match]
+            user_class = "iPXE"
+
+            [[class]]
+            name = "guests"
+            routers = ["10.10.0.1"]
+            [class.match]
+            interface = "eth1"
+
+            [[class]]
+            name = "windows"
+            [class.match]
+            fingerprint = [1, 3, 6, 15, 31, 33, 43, 44, 46, 47, 119, 121, 249, 252]
+            hostname_prefix = "DESKTOP-"
+            "#,
+        )
+        .unwrap();
+        Config {
+            interfaces: classes.interfaces,
+            classes: classes.classes,
+            ..Config::default()
+        }
+    }
+
+    fn names(config: &Config, packet: &Packet, interface: Option<&str>) -> Vec<String> {
+        classify(&config.classes, &ClassContext::new(packet, interface))
+            .into_iter()
+            .map(|class| class.name.clone())
+            .collect()
+    }
+
+    fn packet(chaddr: [u8; 6]) -> Packet {
+        let mut packet = Packet::new_request(DHCPMessageType::DHCPDISCOVER);
+        packet.set_chaddr(&chaddr);
+        packet
+    }
+
+    #[test]
+    fn classify_requests() {
+        let config = config();
+        assert!(config.validate().is_ok());
+
+        let printer = packet([0x00, 0x1b, 0xa9, 1, 2, 3]);
+        assert_eq!(names(&config, &printer, None), ["printers"]);
+        assert_eq!(
+            names(&config, &printer, Some("eth1")),
+            ["printers", "guests"]
+        );
+
+        let mut phone = packet([0x00, 0x1e, 0x7a, 1, 2, 3]);
+        phone.add_option(DHCPOption::ClassIdentifier(
+            b"Cisco Systems, Inc. IP Phone CP-8845".to_vec(),
+        ));
+        // zonder circuit ID van de relay agent
+        assert!(names(&config, &phone, None).is_empty());
+        phone.add_option(DHCPOption::RelayAgentInfo(vec![
+            (1, b"ge-0/0/1".to_vec()),
+            (2, b"switch-1".to_vec()),
+        ]));
+        assert_eq!(names(&config, &phone, None), ["phones"]);
+
+        let mut ipxe = packet([0; 6]);
+        ipxe.add_option(DHCPOption::UserClass(vec![
+            b"lab".to_vec(),
+            b"iPXE".to_vec(),
+        ]));
+        assert_eq!(names(&config, &ipxe, Some("eth0")), ["ipxe"]);
+
+        let mut windows = packet([0; 6]);
+        windows.add_option(DHCPOption::ParameterRequest(vec![
+            1, 3, 6, 15, 31, 33, 43, 44, 46, 47, 119, 121, 249, 252,
+        ]));
+        assert!(names(&config, &windows, None).is_empty());
+        windows.add_option(DHCPOption::HostName("DESKTOP-1234".into()));
+        assert_eq!(names(&config, &windows, None), ["windows"]);
+    }
+
+    #[test]
+    fn interface_needs_interfaces() {
+        let mut config = config();
+        assert!(config.warnings().is_empty());
+        config.interfaces.clear();
+        assert_eq!(config.warnings().len(), 1);
+    }
+}
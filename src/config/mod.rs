@@ -0,0 +1,1371 @@
+use std::{
+    fmt::Display,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use serde::{Deserialize, Deserializer};
+
+use crate::{
+    auth::AuthKey,
+    ddns::tsig::TsigAlgorithm,
+    error::DHCPError,
+    leases::LeasePolicy,
+    options::custom,
+    packet::{custom::OptionType, dns, ClasslessRoute, DHCPOption, SipServers},
+    standard::MIN_V6ONLY_WAIT,
+    vendor,
+};
+
+pub const CONFIG_PATH: &str = "dhcp.toml";
+
+/// Configuratie van de server, ingelezen uit een toml bestand.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Config {
+    /// Opties voor alle clients, zie [`crate::options`] voor de volgorde
+    /// waarin opties van de verschillende scopes overgenomen worden
+    #[serde(default)]
+    pub options: OptionsConfig,
+    /// Eigen opties, waarden worden gezet in de `custom` tabel van een scope
+    #[serde(default, rename = "custom_option")]
+    pub custom_options: Vec<CustomOptionConfig>,
+    /// Groepen van netwerken op dezelfde fysieke link
+    #[serde(default, rename = "shared_network")]
+    pub shared_networks: Vec<SharedNetworkConfig>,
+    /// Opties per netwerk, voor alle pools in dat netwerk
+    #[serde(default, rename = "network")]
+    pub networks: Vec<NetworkConfig>,
+    /// Het eerste subnet is het netwerk waar de server rechtstreeks op
+    /// aangesloten is, de andere worden via een relay agent bereikt.
+    #[serde(default, rename = "subnet")]
+    pub subnets: Vec<SubnetConfig>,
+    #[serde(default, rename = "host")]
+    pub hosts: Vec<HostConfig>,
+    /// Enkel op deze interfaces luisteren, met een socket per interface.
+    /// Leeg: op alle interfaces, zonder te weten waar een pakket binnenkwam.
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    /// Classes waarin clients ingedeeld worden, zie [`ClassConfig`]
+    #[serde(default, rename = "class")]
+    pub classes: Vec<ClassConfig>,
+    /// DNS records bijhouden voor leases (RFC 2136, RFC 4703)
+    pub ddns: Option<DdnsConfig>,
+    /// ProxyDHCP: enkel boot info geven naast een andere DHCP server
+    pub proxy: Option<ProxyConfig>,
+    /// Ingebouwde TFTP server voor bootbestanden
+    pub tftp: Option<TftpConfig>,
+    /// Sub-opties die een vendor in option 43 verwacht
+    #[serde(default, rename = "vendor_space")]
+    pub vendor_spaces: Vec<VendorSpaceConfig>,
+    /// Option 43 per vendor class (60), de eerste die past wordt gebruikt
+    #[serde(default, rename = "vendor")]
+    pub vendors: Vec<VendorConfig>,
+    /// Option 125 per enterprise number (RFC 3925)
+    #[serde(default, rename = "enterprise")]
+    pub enterprises: Vec<EnterpriseConfig>,
+    /// Authenticatie van clients met option 90 (RFC 3118)
+    #[serde(default)]
+    pub auth: AuthConfig,
+}
+
+/// De sub-opties van een vendor voor option 43:
+/// ```toml
+/// [[vendor_space]]
+/// name = "aruba"
+/// suboptions = [{ code = 1, name = "controller", type = "ip" }]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct VendorSpaceConfig {
+    pub name: String,
+    pub suboptions: Vec<SuboptionConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SuboptionConfig {
+    pub code: u8,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: SuboptionType,
+}
+
+/// Hoe de waarde van een sub-optie geschreven wordt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuboptionType {
+    Ip,
+    /// Een lijst van ip adressen
+    Ips,
+    String,
+    U8,
+    U16,
+    U32,
+    Bool,
+    /// Bytes als hex, bv. `"01:02:ff"`
+    Hex,
+}
+
+/// Option 43 voor clients met een bepaalde vendor class. De inhoud komt uit
+/// een vendor space of staat als hex in `raw`:
+/// ```toml
+/// [[vendor]]
+/// class_prefix = "ArubaAP"
+/// space = "aruba"
+/// options = { controller = "192.168.56.10" }
+///
+/// [[vendor]]
+/// class = "MSFT 5.0"
+/// raw = "02:04:00:00:00:01"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct VendorConfig {
+    /// De vendor class moet exact gelijk zijn
+    pub class: Option<String>,
+    /// De vendor class moet hiermee beginnen
+    pub class_prefix: Option<String>,
+    /// Naam van een `[[vendor_space]]`
+    pub space: Option<String>,
+    /// Waarden per naam van een sub-optie uit de space
+    #[serde(default)]
+    pub options: toml::Table,
+    /// De volledige inhoud van option 43 als hex
+    pub raw: Option<String>,
+}
+
+/// Sub-opties in option 125 voor een IANA enterprise number, bv. de ACS
+/// voor TR-069 (TR-111) onder enterprise 3561:
+/// ```toml
+/// [[enterprise]]
+/// number = 3561
+/// space = "tr069"
+/// options = { acs_url = "http://acs.example.com/" }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct EnterpriseConfig {
+    pub number: u32,
+    /// Naam van een `[[vendor_space]]`
+    pub space: String,
+    /// Waarden per naam van een sub-optie uit de space
+    #[serde(default)]
+    pub options: toml::Table,
+}
+
+/// ProxyDHCP (PXE spec 2.5): de server deelt geen adressen uit maar geeft
+/// PXE clients enkel boot info, naast de gewone DHCP server van het netwerk.
+/// ```toml
+/// [proxy]
+/// server_addr = "192.168.56.2"
+///
+/// [[proxy.boot]]
+/// architecture = "bios"
+/// file = "undionly.kpxe"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProxyConfig {
+    /// Adres van deze server, ook de TFTP server tenzij een regel een
+    /// andere `next_server` heeft
+    pub server_addr: Ipv4Addr,
+    /// Tekst in het boot menu van de PXE firmware
+    #[serde(default = "ProxyConfig::default_menu")]
+    pub menu: String,
+    #[serde(default, rename = "boot")]
+    pub boot_rules: Vec<BootRule>,
+}
+
+impl ProxyConfig {
+    /// Het menu staat twee keer in option 43, naast 21 bytes aan andere
+    /// sub-opties. PXE firmware leest geen option 43 die over meerdere
+    /// opties gesplitst is (RFC 3396), alles moet dus in 255 bytes passen.
+    const MAX_MENU_LEN: usize = (255 - 21) / 2;
+
+    fn default_menu() -> String {
+        "Network boot".into()
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        if self.menu.len() > ProxyConfig::MAX_MENU_LEN {
+            return Err(format!(
+                "Menu is longer than {} bytes",
+                ProxyConfig::MAX_MENU_LEN
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Read-only TFTP server (RFC 1350) die een map aanbiedt:
+/// ```toml
+/// [tftp]
+/// root = "/srv/tftp"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct TftpConfig {
+    /// Map met de bootbestanden, bestanden erbuiten zijn niet bereikbaar
+    pub root: PathBuf,
+    #[serde(default = "TftpConfig::default_listen")]
+    pub listen: SocketAddr,
+    /// Seconden wachten op een ACK, een client kan een andere timeout
+    /// vragen (RFC 2349)
+    #[serde(default = "TftpConfig::default_timeout")]
+    pub timeout: u64,
+    /// Aantal keer dat een blok opnieuw gestuurd wordt
+    #[serde(default = "TftpConfig::default_retries")]
+    pub retries: u32,
+    /// Grootste blksize (RFC 2348) die we toestaan, standaard past een blok
+    /// in een ethernet frame
+    #[serde(default = "TftpConfig::default_max_block_size")]
+    pub max_block_size: u16,
+}
+
+impl TftpConfig {
+    fn default_listen() -> SocketAddr {
+        SocketAddr::from((Ipv4Addr::UNSPECIFIED, 69))
+    }
+
+    fn default_timeout() -> u64 {
+        3
+    }
+
+    fn default_retries() -> u32 {
+        5
+    }
+
+    fn default_max_block_size() -> u16 {
+        1468
+    }
+}
+
+/// Een pool van adressen. Meerdere `[[subnet]]` in hetzelfde netwerk worden
+/// na elkaar geprobeerd: als een pool de client weigert of vol is gaat het
+/// verder met de volgende.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubnetConfig {
+    pub range_start: Ipv4Addr,
+    pub range_end: Ipv4Addr,
+    pub server_addr: Ipv4Addr,
+    pub subnet_mask: Ipv4Addr,
+    #[serde(default)]
+    pub authoritative: bool,
+    /// Clients mogen met een DISCOVER met Rapid Commit (80) meteen een
+    /// lease krijgen (RFC 4039)
+    #[serde(default)]
+    pub rapid_commit: bool,
+    #[serde(flatten)]
+    pub lease_time: LeaseTimeConfig,
+    #[serde(flatten)]
+    pub options: OptionsConfig,
+    #[serde(default)]
+    pub hostname_policy: HostnamePolicy,
+    /// Bootbestanden voor PXE clients
+    #[serde(default, rename = "boot")]
+    pub boot_rules: Vec<BootRule>,
+    /// Als er regels zijn krijgen enkel clients die aan een ervan voldoen
+    /// een adres uit deze pool
+    #[serde(default)]
+    pub allow: Vec<PoolRule>,
+    /// Clients die aan een van deze regels voldoen krijgen geen adres uit
+    /// deze pool, ook als ze in `allow` staan
+    #[serde(default)]
+    pub deny: Vec<PoolRule>,
+    #[serde(default)]
+    pub on_deny: DenyAction,
+}
+
+/// Een regel voor `allow` of `deny` van een pool: `"known-clients"` (met een
+/// `[[host]]`), `"unknown-clients"` of `"class:<naam>"`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PoolRule {
+    KnownClients,
+    UnknownClients,
+    Class(String),
+}
+
+impl FromStr for PoolRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "known-clients" => Ok(PoolRule::KnownClients),
+            "unknown-clients" => Ok(PoolRule::UnknownClients),
+            _ => match s.strip_prefix("class:") {
+                Some(class) if !class.is_empty() => Ok(PoolRule::Class(class.to_string())),
+                _ => Err(format!("Invalid pool rule: {s}")),
+            },
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PoolRule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Wat er gebeurt met een REQUEST van een client die geen adres mag krijgen
+/// uit de pool van het gevraagde ip. Een DISCOVER krijgt gewoon geen OFFER,
+/// een NAK is daar geen geldig antwoord op (RFC 2131 4.3.1).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DenyAction {
+    /// Niet antwoorden
+    #[default]
+    Drop,
+    Nak,
+}
+
+/// Een optie zonder eigen variant, met de code, naam en het type:
+/// `ip`, `ip-list`, `u8`, `u16`, `u32`, `bool`, `string`, `hex`,
+/// `domain-list` of een lijst van deze types voor een record.
+/// ```toml
+/// [[custom_option]]
+/// code = 224
+/// name = "provisioning"
+/// type = ["u16", "ip", "string"]
+///
+/// [options.custom]
+/// provisioning = [8080, "10.0.0.1", "pbx"]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomOptionConfig {
+    pub code: u8,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: OptionType,
+}
+
+impl<'de> Deserialize<'de> for OptionType {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Type(String),
+            Record(Vec<String>),
+        }
+        match Raw::deserialize(deserializer)? {
+            Raw::Type(kind) => kind.parse().map_err(serde::de::Error::custom),
+            Raw::Record(fields) => fields
+                .iter()
+                .map(|field| field.parse())
+                .collect::<Result<_, _>>()
+                .map(OptionType::Record)
+                .map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// Netwerken die op dezelfde fysieke link liggen en opties delen.
+/// ```toml
+/// [[shared_network]]
+/// name = "office"
+/// domain_name = "office.example.com"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct SharedNetworkConfig {
+    pub name: String,
+    #[serde(flatten)]
+    pub options: OptionsConfig,
+}
+
+/// De opties van een netwerk, voor alle `[[subnet]]` pools erin.
+/// ```toml
+/// [[network]]
+/// subnet = "192.168.56.0/24"
+/// shared_network = "office"
+/// routers = ["192.168.56.1"]
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct NetworkConfig {
+    pub subnet: Cidr,
+    /// De naam van een `[[shared_network]]`
+    pub shared_network: Option<String>,
+    #[serde(flatten)]
+    pub options: OptionsConfig,
+}
+
+/// Opties die in elke scope gezet kunnen worden: globaal, in een shared
+/// network, netwerk, pool, class of host.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct OptionsConfig {
+    /// Router option (3)
+    #[serde(default)]
+    pub routers: Vec<Ipv4Addr>,
+    /// Classless static routes (121), worden ook als option 249 gestuurd
+    #[serde(default, rename = "classless_route")]
+    pub classless_routes: Vec<RouteConfig>,
+    /// Domain search list (119)
+    #[serde(default)]
+    pub domain_search: Vec<String>,
+    /// Domain name (15), wordt ook gebruikt voor de FQDN van clients
+    pub domain_name: Option<String>,
+    /// Captive portal API (114, RFC 8910), een `https` URI of
+    /// `urn:ietf:params:capport:unrestricted` als er geen portal is
+    pub captive_portal: Option<String>,
+    /// URL van het proxy auto-config bestand (252, WPAD)
+    pub wpad: Option<String>,
+    /// SIP servers (120, RFC 3361), enkel namen of enkel ip adressen
+    #[serde(default)]
+    pub sip_servers: Vec<String>,
+    /// Adressen van TFTP servers (150), bv. voor IP telefoons
+    #[serde(default)]
+    pub tftp_servers: Vec<Ipv4Addr>,
+    /// IPv6-only preferred (108, RFC 8925): V6ONLY_WAIT in seconden. Clients
+    /// die de optie vragen krijgen geen IPv4 adres.
+    pub ipv6_only_preferred: Option<u32>,
+    /// Waarden van `[[custom_option]]` opties, op naam
+    #[serde(default)]
+    pub custom: toml::Table,
+}
+
+/// RFC 8910 2: deze URI betekent dat het netwerk geen captive portal heeft.
+pub const CAPTIVE_PORTAL_UNRESTRICTED: &str = "urn:ietf:params:capport:unrestricted";
+
+/// Controleert een URL voor option 114 of 252: een van de `schemes` en een
+/// host, in ASCII zonder spaties en kort genoeg voor een optie. Geeft de host
+/// terug.
+fn validate_url<'a>(url: &'a str, schemes: &[&str]) -> Result<&'a str, String> {
+    if url.len() > u8::MAX as usize {
+        return Err("longer than 255 bytes".into());
+    }
+    if !url.bytes().all(|b| b.is_ascii_graphic()) {
+        return Err("contains spaces or non-ASCII characters".into());
+    }
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return Err("missing scheme".into());
+    };
+    if !schemes.contains(&scheme.to_ascii_lowercase().as_str()) {
+        return Err(format!("scheme must be {}", schemes.join(" or ")));
+    }
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host_port = authority.rsplit('@').next().unwrap_or_default();
+    let host = match host_port.strip_prefix('[') {
+        // IPv6 literal
+        Some(ipv6) => ipv6.split(']').next().unwrap_or_default(),
+        None => host_port.split(':').next().unwrap_or_default(),
+    };
+    if host.is_empty() {
+        return Err("missing host".into());
+    }
+    Ok(host)
+}
+
+impl OptionsConfig {
+    /// `custom_options` zijn de definities voor de waarden in `custom`.
+    pub fn to_options(&self, custom_options: &[CustomOptionConfig]) -> Vec<DHCPOption> {
+        let mut options = Vec::new();
+        if !self.routers.is_empty() {
+            options.push(DHCPOption::Router(self.routers.clone()));
+        }
+        if !self.classless_routes.is_empty() {
+            let routes: Vec<ClasslessRoute> = self
+                .classless_routes
+                .iter()
+                .map(|route| {
+                    ClasslessRoute::new(
+                        route.destination.addr,
+                        route.destination.prefix_len,
+                        route.router,
+                    )
+                })
+                .collect();
+            options.push(DHCPOption::MsClasslessStaticRoute(routes.clone()));
+            options.push(DHCPOption::ClasslessStaticRoute(routes));
+        }
+        if let Some(domain_name) = &self.domain_name {
+            options.push(DHCPOption::DomainName(domain_name.clone()));
+        }
+        if let Some(wait) = self.ipv6_only_preferred {
+            options.push(DHCPOption::Ipv6OnlyPreferred(wait));
+        }
+        if let Some(uri) = &self.captive_portal {
+            options.push(DHCPOption::CaptivePortal(uri.clone()));
+        }
+        if !self.domain_search.is_empty() {
+            options.push(DHCPOption::DomainSearch(self.domain_search.clone()));
+        }
+        if let Ok(Some(servers)) = self.sip_servers() {
+            options.push(DHCPOption::SipServers(servers));
+        }
+        if !self.tftp_servers.is_empty() {
+            options.push(DHCPOption::TftpServerAddresses(self.tftp_servers.clone()));
+        }
+        if let Some(url) = &self.wpad {
+            options.push(DHCPOption::WebProxyAutoDiscovery(url.clone()));
+        }
+        // fouten zijn al bij het laden van de config gemeld
+        options.extend(
+            self.custom
+                .iter()
+                .filter_map(|(name, value)| custom::custom_option(custom_options, name, value).ok()),
+        );
+        options
+    }
+
+    fn validate(&self, custom_options: &[CustomOptionConfig]) -> Result<(), DHCPError> {
+        for domain in self.domain_search.iter().chain(&self.domain_name) {
+            dns::validate_name(domain).map_err(|error| {
+                DHCPError::Config(format!("Invalid domain {domain}: {error:?}"))
+            })?;
+        }
+        if let Some(wait) = self.ipv6_only_preferred.filter(|wait| *wait < MIN_V6ONLY_WAIT) {
+            return Err(DHCPError::Config(format!(
+                "ipv6_only_preferred must be at least {MIN_V6ONLY_WAIT} seconds, got {wait}"
+            )));
+        }
+        if let Some(uri) = self
+            .captive_portal
+            .as_ref()
+            .filter(|uri| *uri != CAPTIVE_PORTAL_UNRESTRICTED)
+        {
+            // RFC 8908: de API is enkel via HTTPS bereikbaar, RFC 8910 2:
+            // een naam en geen ip adres zodat het certificaat klopt
+            let host = validate_url(uri, &["https"]).map_err(|error| {
+                DHCPError::Config(format!("Invalid captive_portal {uri}: {error}"))
+            })?;
+            if host.parse::<IpAddr>().is_ok() {
+                return Err(DHCPError::Config(format!(
+                    "Invalid captive_portal {uri}: the host must be a name, not an ip address"
+                )));
+            }
+        }
+        self.sip_servers()
+            .map_err(|error| DHCPError::Config(format!("Invalid sip_servers: {error}")))?;
+        if let Some(url) = &self.wpad {
+            validate_url(url, &["http", "https"])
+                .map_err(|error| DHCPError::Config(format!("Invalid wpad {url}: {error}")))?;
+        }
+        for (name, value) in &self.custom {
+            custom::custom_option(custom_options, name, value).map_err(|error| {
+                DHCPError::Config(format!("Invalid value for custom option {name}: {error}"))
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Option 120 kan namen of adressen bevatten maar niet beide, de
+    /// encoding volgt uit de servers in de config.
+    fn sip_servers(&self) -> Result<Option<SipServers>, String> {
+        if self.sip_servers.is_empty() {
+            return Ok(None);
+        }
+        let addresses: Vec<Ipv4Addr> = self
+            .sip_servers
+            .iter()
+            .filter_map(|server| server.parse().ok())
+            .collect();
+        if addresses.len() == self.sip_servers.len() {
+            return Ok(Some(SipServers::Addresses(addresses)));
+        }
+        if !addresses.is_empty() {
+            return Err("names and ip addresses can not be mixed".into());
+        }
+        for name in &self.sip_servers {
+            dns::validate_name(name).map_err(|error| format!("invalid domain {name}: {error:?}"))?;
+        }
+        Ok(Some(SipServers::Domains(self.sip_servers.clone())))
+    }
+
+    /// Een router (3) wordt genegeerd door clients die classless routes
+    /// krijgen (RFC 3442), de default route moet dan in 121 staan.
+    fn route_warnings(&self, scope: &str) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if !self.routers.is_empty() && !self.classless_routes.is_empty() {
+            warnings.push(format!(
+                "{scope}: clients that receive classless static routes (121) ignore the router option (3)"
+            ));
+            if !self
+                .classless_routes
+                .iter()
+                .any(|route| route.destination.prefix_len == 0)
+            {
+                warnings.push(format!(
+                    "{scope}: add a 0.0.0.0/0 classless route to keep a default gateway"
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+/// Hoe de hostname van een client bepaald wordt. Een hostname uit een
+/// `[[host]]` reservatie gaat altijd voor.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HostnamePolicy {
+    /// De naam van de client overnemen, of een naam maken op basis van het
+    /// ip als de client geen naam stuurt
+    #[default]
+    Accept,
+    /// Altijd een naam maken op basis van het ip, bv. `dhcp-192-168-56-10`
+    Generate,
+    /// Enkel namen uit een reservatie gebruiken
+    Reservation,
+}
+
+/// Bootbestand voor PXE clients, de eerste regel waarvan alle voorwaarden
+/// kloppen wordt gebruikt. Een regel zonder voorwaarden past voor alle PXE
+/// clients.
+/// ```toml
+/// [[subnet.boot]]
+/// ipxe = true
+/// file = "http://192.168.56.1/boot.ipxe"
+///
+/// [[subnet.boot]]
+/// architecture = "uefi-x64"
+/// file = "ipxe.efi"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct BootRule {
+    /// Enkel voor clients met deze architectuur (93)
+    pub architecture: Option<Architecture>,
+    /// Enkel voor clients die deze user class (77) sturen
+    pub user_class: Option<String>,
+    /// `true`: enkel clients die al iPXE draaien, `false`: enkel clients die
+    /// nog in de PXE firmware zitten
+    pub ipxe: Option<bool>,
+    /// Bootbestand (`file` en option 67)
+    pub file: String,
+    /// TFTP server (`siaddr`), standaard de server zelf
+    pub next_server: Option<Ipv4Addr>,
+    /// Naam van de TFTP server (`sname` en option 66)
+    pub server_name: Option<String>,
+}
+
+impl BootRule {
+    /// Een lang bootbestand gaat in option 67 in plaats van het `file` veld,
+    /// maar PXE firmware kan geen opties lezen die over meerdere stukken
+    /// verdeeld zijn (RFC 3396).
+    fn validate(&self) -> Result<(), String> {
+        if self.file.len() > u8::MAX as usize {
+            return Err("file is longer than 255 bytes".into());
+        }
+        if self
+            .server_name
+            .as_ref()
+            .is_some_and(|name| name.len() > u8::MAX as usize)
+        {
+            return Err("server_name is longer than 255 bytes".into());
+        }
+        Ok(())
+    }
+}
+
+/// Client system architecture (93), RFC 4578 2.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Architecture {
+    /// Intel x86 PC met BIOS
+    Bios,
+    UefiIa32,
+    UefiX64,
+    UefiArm32,
+    UefiArm64,
+}
+
+impl Architecture {
+    /// De waarden van option 93 voor deze architectuur. x64 UEFI firmware
+    /// stuurt meestal 7 (EFI BC) in plaats van 9 (EFI x86-64).
+    pub fn codes(self) -> &'static [u16] {
+        match self {
+            Architecture::Bios => &[0],
+            Architecture::UefiIa32 => &[6],
+            Architecture::UefiX64 => &[7, 9],
+            Architecture::UefiArm32 => &[10],
+            Architecture::UefiArm64 => &[11],
+        }
+    }
+}
+
+/// Een route in de vorm
+/// ```toml
+/// [[subnet.classless_route]]
+/// destination = "10.0.0.0/8"
+/// router = "192.168.56.254"
+/// ```
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RouteConfig {
+    pub destination: Cidr,
+    pub router: Ipv4Addr,
+}
+
+/// Een netwerk in de vorm `10.0.0.0/8`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    pub addr: Ipv4Addr,
+    pub prefix_len: u8,
+}
+
+impl Cidr {
+    /// Kijkt of `ip` in dit netwerk ligt.
+    pub fn contains(&self, ip: Ipv4Addr) -> bool {
+        let mask = u32::MAX.checked_shl(32 - u32::from(self.prefix_len)).unwrap_or(0);
+        u32::from(ip) & mask == u32::from(self.addr) & mask
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+/// Een vaste client, herkend aan zijn hardware adres.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HostConfig {
+    pub hardware_address: HardwareAddr,
+    pub hostname: Option<String>,
+    #[serde(flatten)]
+    pub lease_time: LeaseTimeConfig,
+    #[serde(flatten)]
+    pub options: OptionsConfig,
+    /// Sleutel voor delayed authentication, berichten van deze client
+    /// zonder geldige MAC worden genegeerd
+    pub auth: Option<AuthKeyConfig>,
+}
+
+/// Gedeelde sleutel van een client voor delayed authentication
+/// (RFC 3118 5):
+/// ```toml
+/// [host.auth]
+/// secret_id = 1
+/// secret = "base64..."
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct AuthKeyConfig {
+    pub secret_id: u32,
+    pub secret: String,
+}
+
+/// ```toml
+/// [auth]
+/// required = true
+/// forcerenew = true
+/// socket = "/run/dhcp-forcerenew.sock"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct AuthConfig {
+    /// Clients zonder sleutel in hun `[[host]]` worden genegeerd
+    #[serde(default)]
+    pub required: bool,
+    /// Clients zonder sleutel die het ondersteunen (option 145) krijgen een
+    /// reconfigure key in de ACK, om een FORCERENEW te kunnen tekenen
+    /// (RFC 6704)
+    #[serde(default)]
+    pub forcerenew: bool,
+    /// Unix socket waarop `dhcp forcerenew <ip>` de server vraagt een
+    /// FORCERENEW te sturen
+    pub socket: Option<PathBuf>,
+}
+
+/// Een class van clients. Een client hoort bij elke class waarvan alle
+/// voorwaarden in `[class.match]` kloppen. De leasetijd en opties van de
+/// class gaan voor die van het subnet, bij meerdere classes gaat de laatste
+/// class in de config voor.
+/// ```toml
+/// [[class]]
+/// name = "printers"
+/// default_lease_time = 86400
+///
+/// [class.match]
+/// hardware_prefix = "00:1b:a9"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ClassConfig {
+    pub name: String,
+    #[serde(default, rename = "match")]
+    pub conditions: ClassMatch,
+    #[serde(flatten)]
+    pub lease_time: LeaseTimeConfig,
+    #[serde(flatten)]
+    pub options: OptionsConfig,
+}
+
+/// Voorwaarden voor een class, een lege `[class.match]` past bij elke client.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClassMatch {
+    /// Begin van het hardware adres, bv. de OUI `00:1b:a9`
+    pub hardware_prefix: Option<HardwarePrefix>,
+    /// Vendor class (60), exact
+    pub vendor_class: Option<String>,
+    pub vendor_class_prefix: Option<String>,
+    /// Een van de user classes (77)
+    pub user_class: Option<String>,
+    /// Relay agent circuit ID (82.1)
+    pub circuit_id: Option<String>,
+    /// Relay agent remote ID (82.2)
+    pub remote_id: Option<String>,
+    /// Hostname van de client (12 of 81), exact
+    pub hostname: Option<String>,
+    pub hostname_prefix: Option<String>,
+    /// Parameter request list (55) in dezelfde volgorde, een fingerprint van
+    /// het besturingssysteem
+    pub fingerprint: Option<Vec<u8>>,
+    /// De interface waarop het pakket binnenkwam, zie `interfaces`
+    pub interface: Option<String>,
+}
+
+/// Begin van een hardware adres, bv. `00:1b:a9`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HardwarePrefix(pub Vec<u8>);
+
+impl FromStr for HardwarePrefix {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let prefix = s
+            .split([':', '-'])
+            .map(|part| u8::from_str_radix(part, 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| format!("Invalid hardware prefix: {s}"))?;
+        if prefix.len() > 16 {
+            return Err(format!("Hardware prefix too long: {s}"));
+        }
+        Ok(HardwarePrefix(prefix))
+    }
+}
+
+impl<'de> Deserialize<'de> for HardwarePrefix {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Dynamische DNS updates:
+/// ```toml
+/// [ddns]
+/// server = "127.0.0.1:53"
+/// forward_zone = "example.com."
+/// reverse_zone = "56.168.192.in-addr.arpa."
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct DdnsConfig {
+    pub server: SocketAddr,
+    /// Zone voor A records, ook gebruikt voor hostnames zonder domein
+    pub forward_zone: String,
+    /// Zone voor PTR records, zonder reverse zone worden er geen PTR
+    /// records bijgehouden
+    pub reverse_zone: Option<String>,
+    #[serde(default = "DdnsConfig::default_ttl")]
+    pub ttl: u32,
+    pub tsig: Option<TsigConfig>,
+    /// Aantal keer dat een mislukte update opnieuw geprobeerd wordt
+    #[serde(default = "DdnsConfig::default_max_retries")]
+    pub max_retries: u32,
+    /// Seconden voor de eerste nieuwe poging, verdubbelt bij elke poging
+    #[serde(default = "DdnsConfig::default_retry_interval")]
+    pub retry_interval: u64,
+    /// Seconden wachten op een antwoord van de DNS server
+    #[serde(default = "DdnsConfig::default_timeout")]
+    pub timeout: u64,
+}
+
+impl DdnsConfig {
+    fn default_ttl() -> u32 {
+        300
+    }
+
+    fn default_max_retries() -> u32 {
+        5
+    }
+
+    fn default_retry_interval() -> u64 {
+        10
+    }
+
+    fn default_timeout() -> u64 {
+        3
+    }
+}
+
+/// TSIG sleutel om updates te tekenen:
+/// ```toml
+/// [ddns.tsig]
+/// name = "dhcp-key"
+/// algorithm = "hmac-sha256"
+/// secret = "base64..."
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct TsigConfig {
+    pub name: String,
+    pub algorithm: TsigAlgorithm,
+    pub secret: String,
+}
+
+/// Leasetijden in seconden, `4294967295` is een oneindige lease.
+/// Velden die niet ingevuld zijn worden overgenomen van de bovenliggende scope.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct LeaseTimeConfig {
+    pub default_lease_time: Option<u32>,
+    pub min_lease_time: Option<u32>,
+    pub max_lease_time: Option<u32>,
+}
+
+impl Config {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, DHCPError> {
+        let content = std::fs::read_to_string(path)?;
+        let config: Config =
+            toml::from_str(&content).map_err(|error| DHCPError::Config(error.to_string()))?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Controleert de waarden die niet door het type zelf afgedwongen worden.
+    pub fn validate(&self) -> Result<(), DHCPError> {
+        if self.subnets.is_empty() && self.proxy.is_none() {
+            return Err(DHCPError::Config("No [[subnet]] or [proxy] configured".into()));
+        }
+        for definition in &self.custom_options {
+            custom::validate_definition(definition, &self.custom_options).map_err(|error| {
+                DHCPError::Config(format!("[[custom_option]] {}: {error}", definition.name))
+            })?;
+        }
+        self.options.validate(&self.custom_options)?;
+        for shared_network in &self.shared_networks {
+            shared_network.options.validate(&self.custom_options)?;
+        }
+        for network in &self.networks {
+            network.options.validate(&self.custom_options)?;
+            if let Some(name) = &network.shared_network {
+                if self.shared_network(name).is_none() {
+                    return Err(DHCPError::Config(format!(
+                        "Network {}: unknown shared network {name}",
+                        network.subnet
+                    )));
+                }
+            }
+        }
+        for subnet in &self.subnets {
+            subnet.options.validate(&self.custom_options)?;
+            for rule in subnet.allow.iter().chain(&subnet.deny) {
+                if let PoolRule::Class(name) = rule {
+                    if !self.classes.iter().any(|class| class.name == *name) {
+                        return Err(DHCPError::Config(format!(
+                            "Subnet {}: unknown class {name}",
+                            subnet.range_start
+                        )));
+                    }
+                }
+            }
+        }
+        for class in &self.classes {
+            class.options.validate(&self.custom_options)?;
+        }
+        for host in &self.hosts {
+            host.options.validate(&self.custom_options)?;
+            if let Some(key) = &host.auth {
+                AuthKey::from_config(key).map_err(|error| {
+                    DHCPError::Config(format!("[[host]] {}: {error}", host.hardware_address))
+                })?;
+            }
+        }
+        for vendor in &self.vendors {
+            vendor::encode_vendor(vendor, &self.vendor_spaces).map_err(|error| {
+                DHCPError::Config(format!("Invalid [[vendor]] {vendor:?}: {error}"))
+            })?;
+        }
+        for enterprise in &self.enterprises {
+            vendor::encode_enterprise(enterprise, &self.vendor_spaces).map_err(|error| {
+                DHCPError::Config(format!("Invalid [[enterprise]] {}: {error}", enterprise.number))
+            })?;
+        }
+        if let Some(ddns) = &self.ddns {
+            for zone in std::iter::once(&ddns.forward_zone).chain(&ddns.reverse_zone) {
+                dns::validate_name(zone).map_err(|error| {
+                    DHCPError::Config(format!("Invalid zone {zone}: {error:?}"))
+                })?;
+            }
+        }
+        self.validate_lease_times()?;
+        if let Some(proxy) = &self.proxy {
+            proxy
+                .validate()
+                .map_err(|error| DHCPError::Config(format!("[proxy]: {error}")))?;
+        }
+        let boot_rules = self
+            .subnets
+            .iter()
+            .flat_map(|subnet| &subnet.boot_rules)
+            .chain(self.proxy.iter().flat_map(|proxy| &proxy.boot_rules));
+        for rule in boot_rules {
+            rule.validate()
+                .map_err(|error| DHCPError::Config(format!("Boot rule {}: {error}", rule.file)))?;
+        }
+        Ok(())
+    }
+
+    /// Controleert de leasetijden van elk subnet, met elke class en elke
+    /// host erbovenop zoals bij het bepalen van de lease policy.
+    fn validate_lease_times(&self) -> Result<(), DHCPError> {
+        let classes = std::iter::once(None).chain(self.classes.iter().map(Some));
+        for subnet in &self.subnets {
+            for class in classes.clone() {
+                for host in std::iter::once(None).chain(self.hosts.iter().map(Some)) {
+                    let scopes: Vec<&LeaseTimeConfig> = std::iter::once(&subnet.lease_time)
+                        .chain(class.map(|class: &ClassConfig| &class.lease_time))
+                        .chain(host.map(|host: &HostConfig| &host.lease_time))
+                        .collect();
+                    LeasePolicy::check(&scopes).map_err(|error| {
+                        let mut scope = format!("Subnet {}", subnet.range_start);
+                        if let Some(class) = class {
+                            scope += &format!(", class {}", class.name);
+                        }
+                        if let Some(host) = host {
+                            scope += &format!(", host {}", host.hardware_address);
+                        }
+                        DHCPError::Config(format!("{scope}: {error}"))
+                    })?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Geeft waarschuwingen voor instellingen die wel geldig zijn maar
+    /// waarschijnlijk niet doen wat de gebruiker verwacht.
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        if self.proxy.is_some() && !self.subnets.is_empty() {
+            warnings.push("ProxyDHCP mode does not hand out addresses, [[subnet]] is ignored".into());
+        }
+        warnings.extend(self.options.route_warnings("[options]"));
+        for network in &self.networks {
+            warnings.extend(
+                network
+                    .options
+                    .route_warnings(&format!("Network {}", network.subnet)),
+            );
+        }
+        for subnet in &self.subnets {
+            warnings.extend(
+                subnet
+                    .options
+                    .route_warnings(&format!("Subnet {}", subnet.range_start)),
+            );
+        }
+        for class in &self.classes {
+            warnings.extend(class.options.route_warnings(&format!("Class {}", class.name)));
+            if class.conditions.interface.is_some() && self.interfaces.is_empty() {
+                warnings.push(format!(
+                    "Class {}: matching on the interface needs `interfaces` to be set",
+                    class.name
+                ));
+            }
+        }
+        warnings
+    }
+
+    /// Het `[[network]]` waarin `ip` ligt.
+    pub fn network(&self, ip: Ipv4Addr) -> Option<&NetworkConfig> {
+        self.networks.iter().find(|network| network.subnet.contains(ip))
+    }
+
+    pub fn shared_network(&self, name: &str) -> Option<&SharedNetworkConfig> {
+        self.shared_networks.iter().find(|shared| shared.name == name)
+    }
+
+    pub fn host(&self, hardware_address: &[u8]) -> Option<&HostConfig> {
+        self.hosts
+            .iter()
+            .find(|host| host.hardware_address.0 == hardware_address)
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            options: OptionsConfig::default(),
+            custom_options: Vec::new(),
+            shared_networks: Vec::new(),
+            networks: Vec::new(),
+            subnets: vec![SubnetConfig {
+                range_start: Ipv4Addr::new(192, 168, 56, 3),
+                range_end: Ipv4Addr::new(192, 168, 56, 255),
+                server_addr: Ipv4Addr::new(192, 168, 56, 1),
+                subnet_mask: Ipv4Addr::new(255, 255, 255, 0),
+                authoritative: false,
+                rapid_commit: false,
+                lease_time: LeaseTimeConfig::default(),
+                options: OptionsConfig::default(),
+                hostname_policy: HostnamePolicy::default(),
+                boot_rules: Vec::new(),
+                allow: Vec::new(),
+                deny: Vec::new(),
+                on_deny: DenyAction::default(),
+            }],
+            hosts: Vec::new(),
+            interfaces: Vec::new(),
+            classes: Vec::new(),
+            ddns: None,
+            proxy: None,
+            tftp: None,
+            vendor_spaces: Vec::new(),
+            vendors: Vec::new(),
+            enterprises: Vec::new(),
+            auth: AuthConfig::default(),
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = s
+            .split_once('/')
+            .ok_or(format!("Missing prefix length: {s}"))?;
+        let addr = addr.parse().map_err(|_| format!("Invalid address: {s}"))?;
+        let prefix_len = prefix_len
+            .parse()
+            .ok()
+            .filter(|len| *len <= 32)
+            .ok_or(format!("Invalid prefix length: {s}"))?;
+        Ok(Cidr { addr, prefix_len })
+    }
+}
+
+impl<'de> Deserialize<'de> for Cidr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Mac adres in de vorm `de:ad:c0:de:ca:fe`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HardwareAddr(pub [u8; 6]);
+
+impl FromStr for HardwareAddr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut addr = [0; 6];
+        let mut parts = s.split([':', '-']);
+        for byte in addr.iter_mut() {
+            let part = parts.next().ok_or(format!("Hardware address too short: {s}"))?;
+            *byte = u8::from_str_radix(part, 16)
+                .map_err(|_| format!("Invalid hardware address: {s}"))?;
+        }
+        if parts.next().is_some() {
+            return Err(format!("Hardware address too long: {s}"));
+        }
+        Ok(HardwareAddr(addr))
+    }
+}
+
+impl Display for HardwareAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+impl<'de> Deserialize<'de> for HardwareAddr {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use super::{
+        Cidr, Config, HardwareAddr, OptionsConfig, CAPTIVE_PORTAL_UNRESTRICTED, CONFIG_PATH,
+    };
+    use crate::packet::SipServers;
+
+    #[test]
+    fn example_config_parses() {
+        let config = Config::load(CONFIG_PATH).unwrap();
+        assert!(!config.subnets.is_empty());
+    }
+
+    #[test]
+    fn hardware_address() {
+        let addr: HardwareAddr = "de:ad:c0:de:ca:fe".parse().unwrap();
+        assert_eq!(addr.0, [0xde, 0xad, 0xc0, 0xde, 0xca, 0xfe]);
+        assert_eq!(addr.to_string(), "de:ad:c0:de:ca:fe");
+        assert!("de:ad:c0:de:ca".parse::<HardwareAddr>().is_err());
+        assert!("de:ad:c0:de:ca:fe:00".parse::<HardwareAddr>().is_err());
+    }
+
+    #[test]
+    fn lease_times() {
+        let config = |subnet: &str, host: &str| {
+            let config: Config = toml::from_str(&format!(
+                r#"
+                [[subnet]]
+                range_start = "192.168.56.10"
+                range_end = "192.168.56.20"
+                server_addr = "192.168.56.1"
+                subnet_mask = "255.255.255.0"
+                {subnet}
+
+                [[host]]
+                hardware_address = "de:ad:c0:de:ca:fe"
+                {host}
+                "#
+            ))
+            .unwrap();
+            config.validate()
+        };
+        assert!(config("max_lease_time = 600", "").is_ok());
+        assert!(config("min_lease_time = 3600\nmax_lease_time = 600", "").is_err());
+        // pas fout na het overnemen van de host
+        assert!(config("max_lease_time = 600", "min_lease_time = 3600").is_err());
+        assert!(config("max_lease_time = 600", "default_lease_time = 7200").is_err());
+    }
+
+    #[test]
+    fn cidr() {
+        let cidr: Cidr = "10.0.0.0/8".parse().unwrap();
+        assert_eq!(cidr.addr, Ipv4Addr::new(10, 0, 0, 0));
+        assert_eq!(cidr.prefix_len, 8);
+        assert!("10.0.0.0".parse::<Cidr>().is_err());
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn boot_file_len() {
+        let config = |file: &str| {
+            let config: Config = toml::from_str(&format!(
+                r#"
+                [[subnet]]
+                range_start = "192.168.56.10"
+                range_end = "192.168.56.20"
+                server_addr = "192.168.56.1"
+                subnet_mask = "255.255.255.0"
+                [[subnet.boot]]
+                file = "{file}"
+                "#
+            ))
+            .unwrap();
+            config.validate()
+        };
+        let url = |len: usize| format!("http://boot.example.com/{}", "a".repeat(len - 24));
+        assert!(config(&url(255)).is_ok());
+        assert!(config(&url(256)).is_err());
+    }
+
+    #[test]
+    fn router_ignored_with_classless_routes() {
+        let config: Config = toml::from_str(
+            r#"
+            [[subnet]]
+            range_start = "192.168.56.3"
+            range_end = "192.168.56.255"
+            server_addr = "192.168.56.1"
+            subnet_mask = "255.255.255.0"
+            routers = ["192.168.56.1"]
+
+            [[subnet.classless_route]]
+            destination = "10.0.0.0/8"
+            router = "192.168.56.254"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.warnings().len(), 2);
+    }
+
+    #[test]
+    fn proxy_without_subnets() {
+        let config: Config = toml::from_str(
+            r#"
+            [proxy]
+            server_addr = "192.168.56.2"
+
+            [[proxy.boot]]
+            architecture = "bios"
+            file = "undionly.kpxe"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        assert_eq!(config.proxy.unwrap().boot_rules.len(), 1);
+
+        let empty: Config = toml::from_str("").unwrap();
+        assert!(empty.validate().is_err());
+    }
+
+    #[test]
+    fn proxy_menu_len() {
+        let config = |len: usize| {
+            let config: Config = toml::from_str(&format!(
+                "[proxy]\nserver_addr = \"192.168.56.1\"\nmenu = \"{}\"",
+                "a".repeat(len)
+            ))
+            .unwrap();
+            config.validate()
+        };
+        assert!(config(117).is_ok());
+        assert!(config(118).is_err());
+        assert!(config(300).is_err());
+    }
+
+    #[test]
+    fn url_options() {
+        let options = |captive_portal: &str, wpad: &str| OptionsConfig {
+            captive_portal: Some(captive_portal.into()),
+            wpad: Some(wpad.into()),
+            ..OptionsConfig::default()
+        };
+        let valid = |captive_portal, wpad| options(captive_portal, wpad).validate(&[]).is_ok();
+
+        assert!(valid("https://portal.example.com/api", "http://wpad.example.com/wpad.dat"));
+        assert!(valid(CAPTIVE_PORTAL_UNRESTRICTED, "https://proxy:8443/proxy.pac"));
+        // de portal API moet HTTPS zijn en een naam hebben
+        assert!(!valid("http://portal.example.com/", "http://wpad/wpad.dat"));
+        assert!(!valid("https://192.168.56.1/api", "http://wpad/wpad.dat"));
+        assert!(!valid("https://[fe80::1]/api", "http://wpad/wpad.dat"));
+        assert!(!valid("portal.example.com", "http://wpad/wpad.dat"));
+        assert!(!valid("https://portal.example.com/", "ftp://wpad/wpad.dat"));
+        assert!(!valid("https://portal.example.com/", "http:///wpad.dat"));
+        assert!(!valid("https://portal.example.com/a b", "http://wpad/wpad.dat"));
+    }
+
+    #[test]
+    fn auth_secret() {
+        let config = |secret: &str| {
+            let config: Config = toml::from_str(&format!(
+                "[[host]]\nhardware_address = \"de:ad:c0:de:ca:fe\"\n\
+                 [host.auth]\nsecret_id = 1\nsecret = \"{secret}\""
+            ))
+            .unwrap();
+            Config {
+                hosts: config.hosts,
+                ..Config::default()
+            }
+            .validate()
+        };
+        assert!(config("c2VjcmV0").is_ok());
+        assert!(config("not base64!").is_err());
+        assert!(config("").is_err());
+    }
+
+    #[test]
+    fn sip_servers() {
+        let sip_servers = |servers: &[&str]| {
+            OptionsConfig {
+                sip_servers: servers.iter().map(|server| server.to_string()).collect(),
+                ..OptionsConfig::default()
+            }
+            .sip_servers()
+        };
+        assert_eq!(sip_servers(&[]), Ok(None));
+        assert_eq!(
+            sip_servers(&["10.0.0.1", "10.0.0.2"]),
+            Ok(Some(SipServers::Addresses(vec![
+                Ipv4Addr::new(10, 0, 0, 1),
+                Ipv4Addr::new(10, 0, 0, 2)
+            ])))
+        );
+        assert_eq!(
+            sip_servers(&["sip.example.com"]),
+            Ok(Some(SipServers::Domains(vec!["sip.example.com".into()])))
+        );
+        assert!(sip_servers(&["sip.example.com", "10.0.0.1"]).is_err());
+        assert!(sip_servers(&["sip..example.com"]).is_err());
+    }
+}
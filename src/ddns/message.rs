@@ -0,0 +1,310 @@
+use std::net::Ipv4Addr;
+
+use crate::packet::dns::{self, DnsNameErr};
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_SOA: u16 = 6;
+pub const TYPE_PTR: u16 = 12;
+pub const TYPE_DHCID: u16 = 49;
+pub const TYPE_TSIG: u16 = 250;
+pub const TYPE_ANY: u16 = 255;
+
+pub const CLASS_IN: u16 = 1;
+pub const CLASS_NONE: u16 = 254;
+pub const CLASS_ANY: u16 = 255;
+
+/// UPDATE opcode (RFC 2136 1.3)
+const OPCODE_UPDATE: u16 = 5;
+const HEADER_LEN: usize = 12;
+
+/// Response codes die we in een antwoord op een UPDATE verwachten
+/// (RFC 2136 2.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rcode {
+    NoError,
+    FormErr,
+    ServFail,
+    NxDomain,
+    NotImp,
+    Refused,
+    /// een naam bestaat die niet mocht bestaan
+    YxDomain,
+    /// een RRset bestaat die niet mocht bestaan
+    YxRrset,
+    /// een RRset bestaat niet die wel moest bestaan
+    NxRrset,
+    NotAuth,
+    NotZone,
+    Other(u8),
+}
+
+impl From<u8> for Rcode {
+    fn from(value: u8) -> Self {
+        match value {
+            0 => Rcode::NoError,
+            1 => Rcode::FormErr,
+            2 => Rcode::ServFail,
+            3 => Rcode::NxDomain,
+            4 => Rcode::NotImp,
+            5 => Rcode::Refused,
+            6 => Rcode::YxDomain,
+            7 => Rcode::YxRrset,
+            8 => Rcode::NxRrset,
+            9 => Rcode::NotAuth,
+            10 => Rcode::NotZone,
+            other => Rcode::Other(other),
+        }
+    }
+}
+
+impl From<Rcode> for u8 {
+    fn from(value: Rcode) -> Self {
+        match value {
+            Rcode::NoError => 0,
+            Rcode::FormErr => 1,
+            Rcode::ServFail => 2,
+            Rcode::NxDomain => 3,
+            Rcode::NotImp => 4,
+            Rcode::Refused => 5,
+            Rcode::YxDomain => 6,
+            Rcode::YxRrset => 7,
+            Rcode::NxRrset => 8,
+            Rcode::NotAuth => 9,
+            Rcode::NotZone => 10,
+            Rcode::Other(other) => other,
+        }
+    }
+}
+
+/// Een resource record zoals het in een UPDATE staat.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    pub name: String,
+    pub rtype: u16,
+    pub class: u16,
+    pub ttl: u32,
+    pub rdata: Vec<u8>,
+}
+
+impl Record {
+    /// Prerequisite: de naam heeft geen enkel record (RFC 2136 2.4.5)
+    pub fn name_not_in_use(name: &str) -> Self {
+        Record::new(name, TYPE_ANY, CLASS_NONE, 0, Vec::new())
+    }
+
+    /// Prerequisite: de RRset bestaat met deze waarde (RFC 2136 2.4.2)
+    pub fn rrset_exists(name: &str, rtype: u16, rdata: Vec<u8>) -> Self {
+        Record::new(name, rtype, CLASS_IN, 0, rdata)
+    }
+
+    /// Update: voeg een record toe (RFC 2136 2.5.1)
+    pub fn add(name: &str, rtype: u16, ttl: u32, rdata: Vec<u8>) -> Self {
+        Record::new(name, rtype, CLASS_IN, ttl, rdata)
+    }
+
+    /// Update: verwijder de hele RRset (RFC 2136 2.5.2)
+    pub fn delete_rrset(name: &str, rtype: u16) -> Self {
+        Record::new(name, rtype, CLASS_ANY, 0, Vec::new())
+    }
+
+    /// Update: verwijder een record uit een RRset (RFC 2136 2.5.4)
+    pub fn delete(name: &str, rtype: u16, rdata: Vec<u8>) -> Self {
+        Record::new(name, rtype, CLASS_NONE, 0, rdata)
+    }
+
+    pub fn new(name: &str, rtype: u16, class: u16, ttl: u32, rdata: Vec<u8>) -> Self {
+        Record {
+            name: name.to_string(),
+            rtype,
+            class,
+            ttl,
+            rdata,
+        }
+    }
+
+    pub fn write(&self, buffer: &mut Vec<u8>) {
+        dns::encode_name(&self.name, buffer);
+        buffer.extend_from_slice(&self.rtype.to_be_bytes());
+        buffer.extend_from_slice(&self.class.to_be_bytes());
+        buffer.extend_from_slice(&self.ttl.to_be_bytes());
+        buffer.extend_from_slice(&(self.rdata.len() as u16).to_be_bytes());
+        buffer.extend_from_slice(&self.rdata);
+    }
+}
+
+/// Een DNS UPDATE bericht (RFC 2136 2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateMessage {
+    pub id: u16,
+    pub zone: String,
+    pub prerequisites: Vec<Record>,
+    pub updates: Vec<Record>,
+}
+
+impl UpdateMessage {
+    pub fn new(id: u16, zone: &str) -> Self {
+        UpdateMessage {
+            id,
+            zone: zone.to_string(),
+            prerequisites: Vec::new(),
+            updates: Vec::new(),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(&self.id.to_be_bytes());
+        buffer.extend_from_slice(&(OPCODE_UPDATE << 11).to_be_bytes());
+        // ZOCOUNT, PRCOUNT, UPCOUNT, ADCOUNT
+        buffer.extend_from_slice(&1u16.to_be_bytes());
+        buffer.extend_from_slice(&(self.prerequisites.len() as u16).to_be_bytes());
+        buffer.extend_from_slice(&(self.updates.len() as u16).to_be_bytes());
+        buffer.extend_from_slice(&0u16.to_be_bytes());
+
+        dns::encode_name(&self.zone, &mut buffer);
+        buffer.extend_from_slice(&TYPE_SOA.to_be_bytes());
+        buffer.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+        for record in self.prerequisites.iter().chain(&self.updates) {
+            record.write(&mut buffer);
+        }
+        buffer
+    }
+}
+
+/// Een ingelezen DNS bericht, enkel de delen die we nodig hebben.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub id: u16,
+    pub opcode: u16,
+    pub rcode: Rcode,
+    /// ZONE/QUESTION sectie: naam, type en class
+    pub zone: Vec<(String, u16, u16)>,
+    /// PREREQUISITE/ANSWER, UPDATE/AUTHORITY en ADDITIONAL sectie
+    pub sections: [Vec<Record>; 3],
+    /// Offset van het laatste record in de additional sectie, daar staat
+    /// de TSIG als die er is.
+    pub last_additional: Option<usize>,
+}
+
+impl Message {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DnsNameErr> {
+        let header = bytes.get(..HEADER_LEN).ok_or(DnsNameErr::Truncated)?;
+        let read_u16 = |i: usize| u16::from_be_bytes([header[i], header[i + 1]]);
+        let id = read_u16(0);
+        let flags = read_u16(2);
+        let counts = [read_u16(4), read_u16(6), read_u16(8), read_u16(10)];
+
+        let mut offset = HEADER_LEN;
+        let mut zone = Vec::new();
+        for _ in 0..counts[0] {
+            let (name, next) = dns::decode_name(bytes, offset)?;
+            let fixed = bytes.get(next..(next + 4)).ok_or(DnsNameErr::Truncated)?;
+            zone.push((
+                name,
+                u16::from_be_bytes([fixed[0], fixed[1]]),
+                u16::from_be_bytes([fixed[2], fixed[3]]),
+            ));
+            offset = next + 4;
+        }
+
+        let mut sections: [Vec<Record>; 3] = Default::default();
+        let mut last_additional = None;
+        for (section, count) in sections.iter_mut().zip(&counts[1..]) {
+            for _ in 0..*count {
+                last_additional = Some(offset);
+                let (name, next) = dns::decode_name(bytes, offset)?;
+                let fixed = bytes.get(next..(next + 10)).ok_or(DnsNameErr::Truncated)?;
+                let rdlength = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+                let rdata = bytes
+                    .get((next + 10)..(next + 10 + rdlength))
+                    .ok_or(DnsNameErr::Truncated)?;
+                section.push(Record {
+                    name,
+                    rtype: u16::from_be_bytes([fixed[0], fixed[1]]),
+                    class: u16::from_be_bytes([fixed[2], fixed[3]]),
+                    ttl: u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]),
+                    rdata: rdata.to_vec(),
+                });
+                offset = next + 10 + rdlength;
+            }
+        }
+        if counts[3] == 0 {
+            last_additional = None;
+        }
+
+        Ok(Message {
+            id,
+            opcode: (flags >> 11) & 0xf,
+            rcode: Rcode::from((flags & 0xf) as u8),
+            zone,
+            sections,
+            last_additional,
+        })
+    }
+}
+
+/// Maakt een antwoord op `request` met `rcode`, zoals een DNS server doet.
+#[cfg(test)]
+pub fn response(request: &[u8], rcode: Rcode) -> Vec<u8> {
+    let mut response = request[..HEADER_LEN].to_vec();
+    // QR bit en opcode overnemen, rcode invullen
+    response[2] |= 0x80;
+    response[3] = u8::from(rcode) & 0xf;
+    // enkel de header, alle secties leeg
+    response[4..HEADER_LEN].fill(0);
+    response
+}
+
+/// Naam voor het PTR record, bv. `10.56.168.192.in-addr.arpa.`
+pub fn reverse_name(ip: Ipv4Addr) -> String {
+    let [a, b, c, d] = ip.octets();
+    format!("{d}.{c}.{b}.{a}.in-addr.arpa.")
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use super::{
+        response, reverse_name, Message, Rcode, Record, UpdateMessage, CLASS_IN, CLASS_NONE,
+        TYPE_A, TYPE_ANY,
+    };
+
+    #[test]
+    fn update_message() {
+        let mut message = UpdateMessage::new(0x1234, "example.com.");
+        message
+            .prerequisites
+            .push(Record::name_not_in_use("host.example.com."));
+        message.updates.push(Record::add(
+            "host.example.com.",
+            TYPE_A,
+            300,
+            vec![192, 168, 56, 10],
+        ));
+        let bytes = message.to_bytes();
+
+        assert_eq!(&bytes[..12], &[0x12, 0x34, 0x28, 0, 0, 1, 0, 1, 0, 1, 0, 0]);
+
+        let parsed = Message::from_bytes(&bytes).unwrap();
+        assert_eq!(parsed.opcode, 5);
+        assert_eq!(parsed.zone, vec![("example.com".to_string(), 6, CLASS_IN)]);
+        assert_eq!(parsed.sections[0][0].class, CLASS_NONE);
+        assert_eq!(parsed.sections[0][0].rtype, TYPE_ANY);
+        assert_eq!(parsed.sections[1][0].rdata, vec![192, 168, 56, 10]);
+        assert_eq!(parsed.last_additional, None);
+
+        let parsed = Message::from_bytes(&response(&bytes, Rcode::YxDomain)).unwrap();
+        assert_eq!(parsed.id, 0x1234);
+        assert_eq!(parsed.rcode, Rcode::YxDomain);
+    }
+
+    #[test]
+    fn reverse() {
+        assert_eq!(
+            reverse_name(Ipv4Addr::new(192, 168, 56, 10)),
+            "10.56.168.192.in-addr.arpa."
+        );
+    }
+}
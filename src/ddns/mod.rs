@@ -0,0 +1,493 @@
+use std::{
+    net::Ipv4Addr,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use sha2::{Digest, Sha256};
+use tokio::{
+    net::UdpSocket,
+    sync::mpsc::UnboundedReceiver,
+    time::{sleep_until, timeout, Instant},
+};
+
+use crate::{
+    config::DdnsConfig,
+    error::DHCPError,
+    leases::{hostname::LeaseName, LeaseEvent},
+    packet::{dns, DHCPOption, Packet},
+};
+
+use self::{
+    message::{reverse_name, Message, Rcode, Record, UpdateMessage, TYPE_A, TYPE_DHCID, TYPE_PTR},
+    tsig::TsigKey,
+};
+
+pub mod message;
+pub mod tsig;
+
+/// DHCID identifier types (RFC 4701 3.3)
+const DHCID_HTYPE_CHADDR: u16 = 0x0000;
+const DHCID_CLIENT_ID: u16 = 0x0001;
+/// SHA-256, het enige digest type (RFC 4701 3.4)
+const DHCID_SHA256: u8 = 1;
+
+const DNS_BUFFER_LEN: usize = 512;
+
+#[derive(Debug)]
+pub enum DnsUpdateErr {
+    Io(std::io::Error),
+    Timeout,
+    InvalidResponse,
+    Rcode(Rcode),
+    /// De naam is in gebruik door een andere client (RFC 4703 5.3.1)
+    Conflict(String),
+}
+
+impl From<std::io::Error> for DnsUpdateErr {
+    fn from(value: std::io::Error) -> Self {
+        DnsUpdateErr::Io(value)
+    }
+}
+
+/// Identificeert de client voor het DHCID record: het identifier type
+/// gevolgd door de client identifier (61) of het htype en chaddr.
+pub fn dhcid_identifier(packet: &Packet) -> Vec<u8> {
+    match packet.get_option(61) {
+        Some(DHCPOption::ClientIdentifier(id)) => {
+            let mut identifier = DHCID_CLIENT_ID.to_be_bytes().to_vec();
+            identifier.extend_from_slice(id);
+            identifier
+        }
+        _ => {
+            let mut identifier = DHCID_HTYPE_CHADDR.to_be_bytes().to_vec();
+            identifier.push(packet.htype());
+            identifier.extend_from_slice(packet.chaddr());
+            identifier
+        }
+    }
+}
+
+/// RDATA van het DHCID record (RFC 4701 3.3), `identifier` komt van
+/// [`dhcid_identifier`].
+pub fn dhcid(identifier: &[u8], fqdn: &str) -> Vec<u8> {
+    let mut name = Vec::new();
+    dns::encode_name(&fqdn.to_ascii_lowercase(), &mut name);
+
+    let mut digest = Sha256::new();
+    digest.update(&identifier[2..]);
+    digest.update(&name);
+
+    let mut rdata = identifier[..2].to_vec();
+    rdata.push(DHCID_SHA256);
+    rdata.extend_from_slice(&digest.finalize());
+    rdata
+}
+
+struct Retry {
+    event: LeaseEvent,
+    attempt: u32,
+    at: Instant,
+}
+
+/// Houdt de DNS records van leases bij (RFC 2136). Mislukte updates worden
+/// opnieuw geprobeerd met een exponentiële backoff.
+pub struct DnsUpdater {
+    config: DdnsConfig,
+    key: Option<TsigKey>,
+    timeout: Duration,
+    retry_interval: Duration,
+    retries: Vec<Retry>,
+    next_id: u16,
+}
+
+impl DnsUpdater {
+    pub fn new(config: &DdnsConfig) -> Result<Self, DHCPError> {
+        let key = config.tsig.as_ref().map(TsigKey::from_config).transpose()?;
+        Ok(DnsUpdater {
+            config: config.clone(),
+            key,
+            timeout: Duration::from_secs(config.timeout),
+            retry_interval: Duration::from_secs(config.retry_interval),
+            retries: Vec::new(),
+            next_id: std::process::id() as u16,
+        })
+    }
+
+    pub async fn run(mut self, mut events: UnboundedReceiver<LeaseEvent>) {
+        loop {
+            let next_retry = self
+                .retries
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, retry)| retry.at)
+                .map(|(i, retry)| (i, retry.at));
+
+            tokio::select! {
+                event = events.recv() => {
+                    let Some(event) = event else {
+                        tracing::info!("Lease events closed, stopping DNS updates");
+                        break;
+                    };
+                    // een oude update mag niet na een nieuwere komen
+                    self.retries.retain(|retry| !event.supersedes(&retry.event));
+                    self.process(event, 0).await;
+                },
+                _ = sleep_until(next_retry.map_or_else(Instant::now, |(_, at)| at)), if next_retry.is_some() => {
+                    let (i, _) = next_retry.unwrap();
+                    let retry = self.retries.swap_remove(i);
+                    self.process(retry.event, retry.attempt).await;
+                }
+            }
+        }
+    }
+
+    async fn process(&mut self, event: LeaseEvent, attempt: u32) {
+        match self.apply(&event).await {
+            Ok(()) => tracing::info!("DNS updated: {event:?}"),
+            Err(DnsUpdateErr::Conflict(name)) => {
+                tracing::warn!("DNS name {name} belongs to another client, not updating");
+            }
+            Err(error) if attempt < self.config.max_retries => {
+                let delay = self.retry_interval * 2u32.saturating_pow(attempt);
+                tracing::warn!("DNS update failed: {error:?}, retrying in {delay:?}");
+                self.retries.push(Retry {
+                    event,
+                    attempt: attempt + 1,
+                    at: Instant::now() + delay,
+                });
+            }
+            Err(error) => tracing::error!("DNS update failed: {error:?}, giving up on {event:?}"),
+        }
+    }
+
+    async fn apply(&mut self, event: &LeaseEvent) -> Result<(), DnsUpdateErr> {
+        match event {
+            LeaseEvent::Bound { ip, name } => {
+                let fqdn = self.fqdn(name);
+                self.add_forward(&fqdn, *ip, dhcid(&name.dhcid_identifier, &fqdn))
+                    .await?;
+                self.add_reverse(&fqdn, *ip).await
+            }
+            LeaseEvent::Released { ip, name } => {
+                let fqdn = self.fqdn(name);
+                self.remove_forward(&fqdn, *ip, dhcid(&name.dhcid_identifier, &fqdn))
+                    .await?;
+                self.remove_reverse(*ip).await
+            }
+        }
+    }
+
+    /// Namen zonder punt op het einde komen in de forward zone.
+    fn fqdn(&self, name: &LeaseName) -> String {
+        if name.fqdn.ends_with('.') {
+            name.fqdn.clone()
+        } else {
+            format!(
+                "{}.{}.",
+                name.hostname,
+                self.config.forward_zone.trim_end_matches('.')
+            )
+        }
+    }
+
+    /// RFC 4703 5.3.1: eerst proberen met de voorwaarde dat de naam niet
+    /// bestaat, bestaat ze wel dan enkel overschrijven als het DHCID record
+    /// van deze client is.
+    async fn add_forward(
+        &mut self,
+        fqdn: &str,
+        ip: Ipv4Addr,
+        dhcid: Vec<u8>,
+    ) -> Result<(), DnsUpdateErr> {
+        let ttl = self.config.ttl;
+        let mut message = UpdateMessage::new(0, &self.config.forward_zone);
+        message.prerequisites.push(Record::name_not_in_use(fqdn));
+        message
+            .updates
+            .push(Record::add(fqdn, TYPE_A, ttl, ip.octets().to_vec()));
+        message
+            .updates
+            .push(Record::add(fqdn, TYPE_DHCID, ttl, dhcid.clone()));
+
+        match self.send(message).await? {
+            Rcode::NoError => return Ok(()),
+            Rcode::YxDomain => {}
+            rcode => return Err(DnsUpdateErr::Rcode(rcode)),
+        }
+
+        let mut message = UpdateMessage::new(0, &self.config.forward_zone);
+        message
+            .prerequisites
+            .push(Record::rrset_exists(fqdn, TYPE_DHCID, dhcid));
+        message.updates.push(Record::delete_rrset(fqdn, TYPE_A));
+        message
+            .updates
+            .push(Record::add(fqdn, TYPE_A, ttl, ip.octets().to_vec()));
+
+        match self.send(message).await? {
+            Rcode::NoError => Ok(()),
+            Rcode::NxRrset => Err(DnsUpdateErr::Conflict(fqdn.to_string())),
+            rcode => Err(DnsUpdateErr::Rcode(rcode)),
+        }
+    }
+
+    async fn add_reverse(&mut self, fqdn: &str, ip: Ipv4Addr) -> Result<(), DnsUpdateErr> {
+        let Some(zone) = self.config.reverse_zone.clone() else {
+            return Ok(());
+        };
+        let name = reverse_name(ip);
+        let mut ptr = Vec::new();
+        dns::encode_name(fqdn, &mut ptr);
+
+        let mut message = UpdateMessage::new(0, &zone);
+        message.updates.push(Record::delete_rrset(&name, TYPE_PTR));
+        message
+            .updates
+            .push(Record::add(&name, TYPE_PTR, self.config.ttl, ptr));
+        self.expect_success(message).await
+    }
+
+    /// RFC 4703 5.5: enkel verwijderen als het DHCID record van deze client is.
+    async fn remove_forward(
+        &mut self,
+        fqdn: &str,
+        ip: Ipv4Addr,
+        dhcid: Vec<u8>,
+    ) -> Result<(), DnsUpdateErr> {
+        let mut message = UpdateMessage::new(0, &self.config.forward_zone);
+        message
+            .prerequisites
+            .push(Record::rrset_exists(fqdn, TYPE_DHCID, dhcid));
+        message
+            .updates
+            .push(Record::delete(fqdn, TYPE_A, ip.octets().to_vec()));
+        message.updates.push(Record::delete_rrset(fqdn, TYPE_DHCID));
+
+        match self.send(message).await? {
+            Rcode::NoError => Ok(()),
+            Rcode::NxRrset | Rcode::NxDomain => Err(DnsUpdateErr::Conflict(fqdn.to_string())),
+            rcode => Err(DnsUpdateErr::Rcode(rcode)),
+        }
+    }
+
+    async fn remove_reverse(&mut self, ip: Ipv4Addr) -> Result<(), DnsUpdateErr> {
+        let Some(zone) = self.config.reverse_zone.clone() else {
+            return Ok(());
+        };
+        let mut message = UpdateMessage::new(0, &zone);
+        message
+            .updates
+            .push(Record::delete_rrset(&reverse_name(ip), TYPE_PTR));
+        self.expect_success(message).await
+    }
+
+    async fn expect_success(&mut self, message: UpdateMessage) -> Result<(), DnsUpdateErr> {
+        match self.send(message).await? {
+            Rcode::NoError => Ok(()),
+            rcode => Err(DnsUpdateErr::Rcode(rcode)),
+        }
+    }
+
+    /// Stuurt een UPDATE, getekend met TSIG als er een sleutel is, en geeft
+    /// de rcode van het antwoord terug.
+    async fn send(&mut self, mut message: UpdateMessage) -> Result<Rcode, DnsUpdateErr> {
+        self.next_id = self.next_id.wrapping_add(1);
+        message.id = self.next_id;
+
+        let mut bytes = message.to_bytes();
+        if let Some(key) = &self.key {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            key.sign(&mut bytes, now.as_secs());
+        }
+
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).await?;
+        socket.connect(self.config.server).await?;
+        socket.send(&bytes).await?;
+
+        let mut buffer = [0; DNS_BUFFER_LEN];
+        loop {
+            let len = timeout(self.timeout, socket.recv(&mut buffer))
+                .await
+                .map_err(|_| DnsUpdateErr::Timeout)??;
+            let response =
+                Message::from_bytes(&buffer[..len]).map_err(|_| DnsUpdateErr::InvalidResponse)?;
+            // antwoorden op oudere berichten negeren. De TSIG van het
+            // antwoord wordt niet gecontroleerd: die gaat ook over de MAC van
+            // de vraag (RFC 8945 5.3), wat `TsigKey` niet ondersteunt. Een
+            // vervalst antwoord moet de poort en het id raden en kan enkel een
+            // update als gelukt of mislukt laten doorgaan, de records zelf
+            // veranderen enkel met een getekende UPDATE.
+            if response.id == message.id {
+                return Ok(response.rcode);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{net::Ipv4Addr, time::Duration};
+
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use tokio::{net::UdpSocket, sync::mpsc};
+
+    use crate::{
+        config::DdnsConfig,
+        leases::{hostname::LeaseName, LeaseEvent},
+    };
+
+    use super::{
+        dhcid,
+        message::{response, Message, Rcode, CLASS_IN, CLASS_NONE, TYPE_A, TYPE_DHCID, TYPE_PTR},
+        tsig::{TsigAlgorithm, TsigKey},
+        DnsUpdater,
+    };
+
+    #[test]
+    fn dhcid_rfc_examples() {
+        // RFC 4701 3.6.2
+        let mut identifier = vec![0, 1];
+        identifier.extend_from_slice(&[0x01, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c]);
+        assert_eq!(
+            STANDARD.encode(dhcid(&identifier, "chi.example.com.")),
+            "AAEBOSD+XR3Os/0LozeXVqcNc7FwCfQdWL3b/NaiUDlW2No="
+        );
+
+        // RFC 4701 3.6.3
+        let identifier = [0, 0, 1, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06];
+        assert_eq!(
+            STANDARD.encode(dhcid(&identifier, "client.example.com.")),
+            "AAABxLmlskllE0MVjd57zHcWmEH3pCQ6VytcKD//7es/deY="
+        );
+    }
+
+    async fn stub_server() -> (UdpSocket, DdnsConfig) {
+        let socket = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let config = DdnsConfig {
+            server: socket.local_addr().unwrap(),
+            forward_zone: "example.com.".into(),
+            reverse_zone: Some("56.168.192.in-addr.arpa.".into()),
+            ttl: 300,
+            tsig: None,
+            max_retries: 3,
+            retry_interval: 0,
+            timeout: 1,
+        };
+        (socket, config)
+    }
+
+    /// Wacht op een UPDATE en beantwoordt die met `rcode`.
+    async fn answer(socket: &UdpSocket, rcode: Rcode) -> (Message, Vec<u8>) {
+        let mut buffer = [0; 512];
+        let (len, src) = socket.recv_from(&mut buffer).await.unwrap();
+        socket
+            .send_to(&response(&buffer[..len], rcode), src)
+            .await
+            .unwrap();
+        (
+            Message::from_bytes(&buffer[..len]).unwrap(),
+            buffer[..len].to_vec(),
+        )
+    }
+
+    fn name() -> LeaseName {
+        LeaseName {
+            hostname: "laptop".into(),
+            fqdn: "laptop".into(),
+            dhcid_identifier: vec![0, 0, 1, 0xde, 0xad, 0xc0, 0xde, 0xca, 0xfe],
+        }
+    }
+
+    fn event() -> LeaseEvent {
+        LeaseEvent::Bound {
+            ip: Ipv4Addr::new(192, 168, 56, 10),
+            name: name(),
+        }
+    }
+
+    #[tokio::test]
+    async fn add_with_conflict_resolution() {
+        let (socket, mut config) = stub_server().await;
+        config.tsig = Some(crate::config::TsigConfig {
+            name: "dhcp-key".into(),
+            algorithm: TsigAlgorithm::HmacSha256,
+            secret: STANDARD.encode(b"secret"),
+        });
+        let key = TsigKey::from_config(config.tsig.as_ref().unwrap()).unwrap();
+        let updater = DnsUpdater::new(&config).unwrap();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(updater.run(receiver));
+        sender.send(event()).unwrap();
+
+        // de naam bestaat al
+        let (first, bytes) = answer(&socket, Rcode::YxDomain).await;
+        assert!(key.verify(&bytes));
+        assert_eq!(first.zone[0].0, "example.com");
+        assert_eq!(first.sections[0][0].class, CLASS_NONE);
+        assert_eq!(first.sections[1][0].name, "laptop.example.com");
+        assert_eq!(first.sections[1][0].rtype, TYPE_A);
+        assert_eq!(first.sections[1][1].rtype, TYPE_DHCID);
+
+        // overschrijven als het DHCID van deze client is
+        let (second, bytes) = answer(&socket, Rcode::NoError).await;
+        assert!(key.verify(&bytes));
+        assert_eq!(second.sections[0][0].rtype, TYPE_DHCID);
+        assert_eq!(second.sections[0][0].class, CLASS_IN);
+        assert_eq!(second.sections[0][0].rdata, first.sections[1][1].rdata);
+
+        let (reverse, _) = answer(&socket, Rcode::NoError).await;
+        assert_eq!(reverse.zone[0].0, "56.168.192.in-addr.arpa");
+        assert_eq!(reverse.sections[1][1].name, "10.56.168.192.in-addr.arpa");
+        assert_eq!(reverse.sections[1][1].rtype, TYPE_PTR);
+    }
+
+    #[tokio::test]
+    async fn retry_after_failure() {
+        let (socket, config) = stub_server().await;
+        let updater = DnsUpdater::new(&config).unwrap();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(updater.run(receiver));
+        sender.send(event()).unwrap();
+
+        let (first, _) = answer(&socket, Rcode::ServFail).await;
+        // dezelfde update komt opnieuw
+        let (retry, _) =
+            tokio::time::timeout(Duration::from_secs(5), answer(&socket, Rcode::NoError))
+                .await
+                .unwrap();
+        assert_ne!(first.id, retry.id);
+        assert_eq!(first.sections, retry.sections);
+    }
+
+    #[tokio::test]
+    async fn no_retry_after_release() {
+        let (socket, mut config) = stub_server().await;
+        config.retry_interval = 1;
+        let updater = DnsUpdater::new(&config).unwrap();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(updater.run(receiver));
+        sender.send(event()).unwrap();
+        answer(&socket, Rcode::ServFail).await;
+
+        // de lease is vrijgegeven voor de retry
+        sender
+            .send(LeaseEvent::Released {
+                ip: Ipv4Addr::new(192, 168, 56, 10),
+                name: name(),
+            })
+            .unwrap();
+        let (forward, _) = answer(&socket, Rcode::NoError).await;
+        assert_eq!(forward.sections[1][0].class, CLASS_NONE);
+        answer(&socket, Rcode::NoError).await;
+
+        // de mislukte toevoeging komt niet meer
+        let mut buffer = [0; 512];
+        assert!(
+            tokio::time::timeout(Duration::from_secs(2), socket.recv_from(&mut buffer))
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,169 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hmac::{digest::KeyInit, Hmac, Mac};
+use md5::Md5;
+use serde::Deserialize;
+use sha2::Sha256;
+
+use crate::{config::TsigConfig, error::DHCPError, packet::dns};
+
+use super::message::{Message, Record, CLASS_ANY, TYPE_TSIG};
+
+/// Tijdsverschil dat de DNS server toelaat, in seconden
+const FUDGE: u16 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum TsigAlgorithm {
+    #[serde(rename = "hmac-md5")]
+    HmacMd5,
+    #[serde(rename = "hmac-sha256")]
+    HmacSha256,
+}
+
+impl TsigAlgorithm {
+    /// Naam van het algoritme in de TSIG RR (RFC 8945 6)
+    pub fn name(self) -> &'static str {
+        match self {
+            TsigAlgorithm::HmacMd5 => "hmac-md5.sig-alg.reg.int.",
+            TsigAlgorithm::HmacSha256 => "hmac-sha256.",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct TsigKey {
+    pub name: String,
+    pub algorithm: TsigAlgorithm,
+    pub secret: Vec<u8>,
+}
+
+impl TsigKey {
+    pub fn from_config(config: &TsigConfig) -> Result<Self, DHCPError> {
+        let secret = STANDARD
+            .decode(&config.secret)
+            .map_err(|error| DHCPError::Config(format!("Invalid TSIG secret: {error}")))?;
+        Ok(TsigKey {
+            name: config.name.clone(),
+            algorithm: config.algorithm,
+            secret,
+        })
+    }
+
+    /// Tekent `message` door er een TSIG record aan toe te voegen
+    /// (RFC 8945 5.1). `time_signed` is de tijd in seconden sinds 1970.
+    pub fn sign(&self, message: &mut Vec<u8>, time_signed: u64) {
+        let mac = self.mac(message, time_signed);
+        let id = [message[0], message[1]];
+
+        let mut rdata = Vec::new();
+        dns::encode_name(self.algorithm.name(), &mut rdata);
+        rdata.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+        rdata.extend_from_slice(&FUDGE.to_be_bytes());
+        rdata.extend_from_slice(&(mac.len() as u16).to_be_bytes());
+        rdata.extend_from_slice(&mac);
+        rdata.extend_from_slice(&id);
+        // error en other len
+        rdata.extend_from_slice(&[0, 0, 0, 0]);
+
+        Record::new(&self.name, TYPE_TSIG, CLASS_ANY, 0, rdata).write(message);
+        let arcount = u16::from_be_bytes([message[10], message[11]]) + 1;
+        message[10..12].copy_from_slice(&arcount.to_be_bytes());
+    }
+
+    /// Controleert de TSIG van een getekend bericht, zoals de DNS server
+    /// dat doet.
+    pub fn verify(&self, message: &[u8]) -> bool {
+        let Ok(parsed) = Message::from_bytes(message) else {
+            return false;
+        };
+        let (Some(tsig), Some(offset)) = (parsed.sections[2].last(), parsed.last_additional) else {
+            return false;
+        };
+        if tsig.rtype != TYPE_TSIG
+            || !tsig
+                .name
+                .eq_ignore_ascii_case(self.name.trim_end_matches('.'))
+        {
+            return false;
+        }
+        let Ok((_, next)) = dns::decode_name(&tsig.rdata, 0) else {
+            return false;
+        };
+        let rdata = &tsig.rdata[next..];
+        let Some(fixed) = rdata.get(..10) else {
+            return false;
+        };
+        let mut time = [0; 8];
+        time[2..].copy_from_slice(&fixed[..6]);
+        let mac_len = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+        let Some(mac) = rdata.get(10..(10 + mac_len)) else {
+            return false;
+        };
+
+        // het bericht zoals het was voor de TSIG toegevoegd werd
+        let mut unsigned = message[..offset].to_vec();
+        let arcount = u16::from_be_bytes([unsigned[10], unsigned[11]]) - 1;
+        unsigned[10..12].copy_from_slice(&arcount.to_be_bytes());
+
+        self.mac(&unsigned, u64::from_be_bytes(time)) == mac
+    }
+
+    fn mac(&self, message: &[u8], time_signed: u64) -> Vec<u8> {
+        // TSIG variables (RFC 8945 4.3.3)
+        let mut variables = Vec::new();
+        dns::encode_name(&self.name.to_ascii_lowercase(), &mut variables);
+        variables.extend_from_slice(&CLASS_ANY.to_be_bytes());
+        variables.extend_from_slice(&0u32.to_be_bytes());
+        dns::encode_name(self.algorithm.name(), &mut variables);
+        variables.extend_from_slice(&time_signed.to_be_bytes()[2..]);
+        variables.extend_from_slice(&FUDGE.to_be_bytes());
+        // error en other len
+        variables.extend_from_slice(&[0, 0, 0, 0]);
+
+        match self.algorithm {
+            TsigAlgorithm::HmacMd5 => hmac::<Hmac<Md5>>(&self.secret, &[message, &variables]),
+            TsigAlgorithm::HmacSha256 => hmac::<Hmac<Sha256>>(&self.secret, &[message, &variables]),
+        }
+    }
+}
+
+fn hmac<M: Mac + KeyInit>(key: &[u8], data: &[&[u8]]) -> Vec<u8> {
+    let mut mac = <M as KeyInit>::new_from_slice(key).expect("HMAC accepts any key length");
+    for part in data {
+        mac.update(part);
+    }
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ddns::message::UpdateMessage;
+
+    use super::{TsigAlgorithm, TsigKey};
+
+    #[test]
+    fn sign_and_verify() {
+        for algorithm in [TsigAlgorithm::HmacMd5, TsigAlgorithm::HmacSha256] {
+            let key = TsigKey {
+                name: "dhcp-key".into(),
+                algorithm,
+                secret: b"secret".to_vec(),
+            };
+            let mut message = UpdateMessage::new(7, "example.com.").to_bytes();
+            key.sign(&mut message, 1_700_000_000);
+
+            assert_eq!(&message[10..12], &[0, 1]);
+            assert!(key.verify(&message));
+
+            let other = TsigKey {
+                secret: b"other".to_vec(),
+                ..key.clone()
+            };
+            assert!(!other.verify(&message));
+
+            // bericht aangepast na het tekenen
+            let mut tampered = message.clone();
+            tampered[0] = 8;
+            assert!(!key.verify(&tampered));
+        }
+    }
+}
@@ -9,5 +9,7 @@ pub enum DHCPError {
     #[error("Protocol error: {0}")]
     Protocol(String),
     #[error("Parse error {0}")]
-    OptionsParseError(#[from] OptionParseErr)
+    OptionsParseError(#[from] OptionParseErr),
+    #[error("Config error: {0}")]
+    Config(String),
 }
@@ -1,40 +1,439 @@
-use crate::{error::DHCPError, packet::{DHCPMessageType, Packet}, state::DHCPState};
+use std::net::Ipv4Addr;
+
+use crate::{
+    auth, boot,
+    classes::{self, ClassContext},
+    config::{BootRule, ClassConfig, DenyAction},
+    ddns,
+    error::DHCPError,
+    leases::{
+        self,
+        hostname::{self, LeaseName},
+        LeaseRange, LeaseReserveError, PoolClient,
+    },
+    options::{self, ScopedOption},
+    packet::{ClientFqdn, DHCPMessageType, DHCPOption, LeaseTime, Packet},
+    state::DHCPState,
+    vendor,
+};
 
 #[derive(Debug)]
 pub enum DiscoverResult {
     NoLeases,
 }
 
-pub fn on_dhcp_discover( mut packet: Packet, state: DHCPState) -> Result<Packet, DHCPError> {
-    let mut lease_range = state.lock();
+pub fn on_dhcp_discover(
+    packet: Packet,
+    state: DHCPState,
+    interface: Option<&str>,
+) -> Result<Option<Packet>, DHCPError> {
+    authenticated(packet, state, |packet, state| {
+        discover_response(packet, state, interface)
+    })
+}
+
+fn discover_response(
+    mut packet: Packet,
+    state: DHCPState,
+    interface: Option<&str>,
+) -> Result<Option<Packet>, DHCPError> {
+    let mut lease_ranges = state.lock();
     println!("Got discover");
+    let link = leases::link_address(&packet);
+    let pools = LeaseRange::network_pools(&mut lease_ranges, link);
+    if pools.is_empty() {
+        println!("No subnet for link {link}");
+        return Ok(None);
+    }
 
     println!("Client");
     packet.print();
-    let ip = lease_range.get_available_ip(packet.xid).unwrap();
+    let classes = classes::classify(&state.config().classes, &ClassContext::new(&packet, interface));
+    let client = pool_client(&state, &packet, &classes);
+    let client_options = client_options(&state, &packet, &classes);
+    let requested = packet.get_parameter_request();
+
+    let ipv6_only = pools
+        .iter()
+        .find(|pool| pool.permits(&client))
+        .and_then(|pool| Some((ipv6_only_wait(pool, &client_options, &requested)?, pool)));
+    if let Some((wait, pool)) = ipv6_only {
+        println!("Client prefers IPv6-only, not offering an address");
+        let response = ipv6_only_response(packet, pool, DHCPMessageType::DHCPOFFER, wait);
+        println!("\nResponse");
+        response.print();
+        return Ok(Some(response));
+    }
+
+    // de eerste pool die de client toelaat en nog een vrij ip heeft
+    let allocation = pools
+        .into_iter()
+        .filter(|pool| pool.permits(&client))
+        .find_map(|pool| {
+            let policy = state.lease_policy(pool, packet.chaddr(), &classes);
+            let lease_time = policy.lease_time(packet.get_leasetime());
+            let ip = pool.get_available_ip(packet.xid, lease_time)?;
+            Some((pool, policy, lease_time, ip))
+        });
+    let Some((lease_range, policy, lease_time, ip)) = allocation else {
+        println!("No pool with a free address for {client:?}");
+        return Ok(None);
+    };
+
+    // Rapid Commit: de offer meteen vastleggen en een ACK terugsturen
+    if lease_range.rapid_commit && packet.get_option(80).is_some() {
+        let lease_time = lease_range.reserve_ip(&packet, ip, &policy).map_err(|error| {
+            DHCPError::Protocol(format!("Rapid commit of {ip} failed: {error:?}"))
+        })?;
+        let mut response = ack(packet, &state, lease_range, &classes, ip, lease_time);
+        response.override_option(DHCPOption::RapidCommit);
 
+        println!("\nResponse");
+        response.print();
+        return Ok(Some(response));
+    }
+
+    let boot_rule = boot::select_rule(&lease_range.boot_rules, &packet).cloned();
+    let vendor_options = vendor::vendor_options(state.config(), &packet);
     packet.yiaddr = ip;
     packet.into_response(DHCPMessageType::DHCPOFFER);
-    packet.override_option(lease_range.get_subnet_option());
-    packet.override_option(lease_range.get_leasetime_option());
-    packet.override_option(lease_range.get_server_id_option());
+    add_lease_options(
+        &mut packet,
+        lease_range,
+        client_options,
+        lease_time,
+        &requested,
+        boot_rule.as_ref(),
+    );
+    for option in vendor_options {
+        packet.override_option(option);
+    }
 
     println!("\nResponse");
     packet.print();
-    Ok(packet)
+    Ok(Some(packet))
+}
+
+pub fn on_dhcp_request(
+    packet: Packet,
+    state: DHCPState,
+    interface: Option<&str>,
+) -> Result<Option<Packet>, DHCPError> {
+    authenticated(packet, state, |packet, state| {
+        request_response(packet, state, interface)
+    })
 }
 
-pub fn on_dhcp_request( packet: Packet , state: DHCPState) -> Result<Packet, DHCPError> {
-    let mut lease_range = state.lock();
+fn request_response(
+    packet: Packet,
+    state: DHCPState,
+    interface: Option<&str>,
+) -> Result<Option<Packet>, DHCPError> {
+    let mut lease_ranges = state.lock();
     println!("Got request");
     println!("Client");
     packet.print();
 
-    let ip = packet.get_requested_ip().unwrap();
+    // de client heeft een offer van een andere server gekozen
+    if let Some(server_id) = packet.get_server_id() {
+        if !lease_ranges.iter().any(|range| range.server_addr == server_id) {
+            println!("Request for server {server_id}, releasing offer");
+            lease_ranges
+                .iter_mut()
+                .for_each(|range| range.release_offer(packet.xid));
+            return Ok(None);
+        }
+    }
+
+    // de pool van het ip dat de client wil, of de eerste pool van het
+    // netwerk als het ip in geen enkele pool ligt. Een client die verlengt
+    // stuurt unicast naar de server zonder relay agent, dan is het netwerk
+    // dat van ciaddr.
+    let link = match leases::link_address(&packet) {
+        link if link.is_unspecified() => packet.ciaddr,
+        link => link,
+    };
+    let mut pools = LeaseRange::network_pools(&mut lease_ranges, link);
+    let ip = packet.get_requested_ip().unwrap_or(packet.ciaddr);
+    let index = pools.iter().position(|pool| pool.in_pool(ip)).unwrap_or(0);
+    if index >= pools.len() {
+        println!("No subnet for link {link}");
+        return Ok(None);
+    }
+    let lease_range = pools.swap_remove(index);
+
+    let classes = classes::classify(&state.config().classes, &ClassContext::new(&packet, interface));
+    let client = pool_client(&state, &packet, &classes);
+    if !lease_range.permits(&client) {
+        println!("Pool of {ip} denies {client:?}");
+        return Ok(match lease_range.on_deny {
+            DenyAction::Drop => None,
+            DenyAction::Nak => Some(nak(packet, lease_range)),
+        });
+    }
+
+    let requested = packet.get_parameter_request();
+    let client_options = client_options(&state, &packet, &classes);
+    if let Some(wait) = ipv6_only_wait(lease_range, &client_options, &requested) {
+        println!("Client prefers IPv6-only, not assigning an address");
+        let response = ipv6_only_response(packet, lease_range, DHCPMessageType::DHCPACK, wait);
+        println!("\nResponse");
+        response.print();
+        return Ok(Some(response));
+    }
+    let policy = state.lease_policy(lease_range, packet.chaddr(), &classes);
+
+    // RFC 2131 4.3.2: de state van de client afleiden uit de request
+    let response = match (packet.get_server_id(), packet.get_requested_ip()) {
+        // SELECTING
+        (Some(_), Some(ip)) => match lease_range.reserve_ip(&packet, ip, &policy) {
+            Ok(lease_time) => ack(packet, &state, lease_range, &classes, ip, lease_time),
+            Err(error) => {
+                println!("Could not reserve {ip}: {error:?}");
+                nak(packet, lease_range)
+            }
+        },
+        // INIT-REBOOT: de client wil zijn vorig ip terug
+        (None, Some(ip)) if packet.ciaddr.is_unspecified() => {
+            match lease_range.verify_ip(&packet, ip, &policy) {
+                Ok(lease_time) => ack(packet, &state, lease_range, &classes, ip, lease_time),
+                Err(LeaseReserveError::NotRequested) if !lease_range.authoritative => {
+                    println!("No record of {ip}, not authoritative");
+                    return Ok(None);
+                }
+                Err(error) => {
+                    println!("Rejecting {ip}: {error:?}");
+                    nak(packet, lease_range)
+                }
+            }
+        }
+        // RENEWING (unicast) of REBINDING (broadcast)
+        (None, None) if !packet.ciaddr.is_unspecified() => {
+            let ip = packet.ciaddr;
+            match lease_range.renew_ip(&packet, ip, &policy) {
+                Ok(lease_time) => ack(packet, &state, lease_range, &classes, ip, lease_time),
+                Err(LeaseReserveError::NotRequested) => {
+                    println!("No lease found for {ip}, ignoring renewal");
+                    return Ok(None);
+                }
+                Err(error) => {
+                    println!("Rejecting renewal of {ip}: {error:?}");
+                    nak(packet, lease_range)
+                }
+            }
+        }
+        _ => {
+            return Err(DHCPError::Protocol(
+                "Unsupported DHCPREQUEST".into(),
+            ))
+        }
+    };
+
+    println!("\nResponse");
+    response.print();
+    Ok(Some(response))
+}
+
+/// De client geeft zijn ip terug (RFC 2131 4.4.6), hier komt geen antwoord op.
+pub fn on_dhcp_release(packet: Packet, state: DHCPState) -> Result<Option<Packet>, DHCPError> {
+    let ip = packet.ciaddr;
+    println!("Got release for {ip}");
+    if let Err(error) = auth::authenticate(&state, &packet) {
+        println!("Ignoring release of {ip}: {error:?}");
+        return Ok(None);
+    }
+
+    let mut lease_ranges = state.lock();
+    let Some(lease_range) = lease_ranges.iter_mut().find(|range| range.in_pool(ip)) else {
+        println!("No pool for {ip}");
+        return Ok(None);
+    };
+    match lease_range.release_ip(&packet, ip) {
+        Ok(()) => auth::forget_lease(&state, ip),
+        Err(error) => println!("Could not release {ip}: {error:?}"),
+    }
+    Ok(None)
+}
 
-    let result = lease_range.reserve_ip(&packet, ip);
-    println!("Result: {result:?}");
-    Ok(packet)
+/// Controleert de authenticatie (option 90) van de client voor `handler`
+/// het bericht krijgt en tekent het antwoord, zie [`auth`].
+fn authenticated(
+    packet: Packet,
+    state: DHCPState,
+    handler: impl FnOnce(Packet, DHCPState) -> Result<Option<Packet>, DHCPError>,
+) -> Result<Option<Packet>, DHCPError> {
+    let key = match auth::authenticate(&state, &packet) {
+        Ok(key) => key,
+        Err(error) => {
+            println!("Ignoring {:?}: {error:?}", packet.dhcp_message_type);
+            return Ok(None);
+        }
+    };
+    let request = packet.clone();
+    let mut response = handler(packet, state.clone())?;
+    if let Some(response) = &mut response {
+        auth::sign_response(&state, &request, response, key);
+    }
+    Ok(response)
+}
+
+/// Wat de allow en deny regels van de pools over de client moeten weten.
+fn pool_client<'a>(state: &DHCPState, packet: &Packet, classes: &[&'a ClassConfig]) -> PoolClient<'a> {
+    PoolClient {
+        known: state.config().host(packet.chaddr()).is_some(),
+        classes: classes.iter().map(|class| class.name.as_str()).collect(),
+    }
+}
+
+/// De opties uit de classes en de `[[host]]` van de client.
+fn client_options(state: &DHCPState, packet: &Packet, classes: &[&ClassConfig]) -> Vec<ScopedOption> {
+    let host = state.config().host(packet.chaddr());
+    options::merge(
+        &options::client_scopes(classes, host),
+        &state.config().custom_options,
+    )
+}
+
+/// De V6ONLY_WAIT als de client IPv6-only preferred (108) vraagt en de optie
+/// in een van zijn scopes staat (RFC 8925 3.3).
+fn ipv6_only_wait(
+    lease_range: &LeaseRange,
+    client_options: &[ScopedOption],
+    requested: &[u8],
+) -> Option<u32> {
+    if !requested.contains(&108) {
+        return None;
+    }
+    client_options
+        .iter()
+        .map(|scoped| &scoped.option)
+        .chain(&lease_range.options)
+        .find_map(|option| match option {
+            DHCPOption::Ipv6OnlyPreferred(wait) => Some(*wait),
+            _ => None,
+        })
+}
+
+/// Een OFFER of ACK zonder adres (yiaddr 0.0.0.0) met enkel de V6ONLY_WAIT,
+/// de client zet IPv4 dan uit voor die tijd.
+fn ipv6_only_response(
+    mut packet: Packet,
+    lease_range: &LeaseRange,
+    message_type: DHCPMessageType,
+    wait: u32,
+) -> Packet {
+    packet.yiaddr = Ipv4Addr::UNSPECIFIED;
+    packet.into_response(message_type);
+    packet.override_option(lease_range.get_server_id_option());
+    packet.override_option(DHCPOption::Ipv6OnlyPreferred(wait));
+    packet
+}
+
+/// Maakt een DHCPACK voor `ip` met de opties van de lease range.
+/// De hostname van de client wordt bepaald en bij de lease bewaard.
+fn ack(
+    mut packet: Packet,
+    state: &DHCPState,
+    lease_range: &mut LeaseRange,
+    classes: &[&ClassConfig],
+    ip: Ipv4Addr,
+    lease_time: LeaseTime,
+) -> Packet {
+    let requested = packet.get_parameter_request();
+    let client_fqdn = match packet.get_option(81) {
+        Some(DHCPOption::ClientFqdn(fqdn)) => Some(fqdn.flags),
+        _ => None,
+    };
+    let dhcid_identifier = ddns::dhcid_identifier(&packet);
+    let boot_rule = boot::select_rule(&lease_range.boot_rules, &packet).cloned();
+    let vendor_options = vendor::vendor_options(state.config(), &packet);
+    let client_options = client_options(state, &packet, classes);
+    let reservation = state
+        .config()
+        .host(packet.chaddr())
+        .and_then(|host| host.hostname.as_deref());
+    let hostname = hostname::assign_hostname(
+        lease_range.hostname_policy,
+        reservation,
+        hostname::client_hostname(&packet),
+        ip,
+    );
+    let name = hostname.clone().map(|hostname| LeaseName {
+        fqdn: lease_range.fqdn(&hostname),
+        hostname,
+        dhcid_identifier,
+    });
+    lease_range.set_hostname(ip, name);
+
+    packet.yiaddr = ip;
+    packet.into_response(DHCPMessageType::DHCPACK);
+    add_lease_options(
+        &mut packet,
+        lease_range,
+        client_options,
+        lease_time,
+        &requested,
+        boot_rule.as_ref(),
+    );
+    for option in vendor_options {
+        packet.override_option(option);
+    }
+
+    // RFC 4702: een client die option 81 stuurt krijgt ook 81 terug, met de
+    // S bit als de server de DNS records bijhoudt
+    match (client_fqdn, hostname) {
+        (Some(flags), Some(hostname)) => {
+            let server_updates = state.config().ddns.is_some();
+            let fqdn = ClientFqdn::reply(flags, server_updates, lease_range.fqdn(&hostname));
+            packet.override_option(DHCPOption::ClientFqdn(fqdn));
+        }
+        (None, Some(hostname)) => {
+            packet.override_option(DHCPOption::HostName(hostname));
+        }
+        _ => {}
+    }
+    packet
+}
+
+/// Voegt de opties toe die in een OFFER en ACK horen. `requested` is de
+/// parameter request list (55) van de client, `boot_rule` de boot info voor
+/// PXE clients. `client_options` zijn de opties van de classes en de host van
+/// de client, die gaan voor de opties die de lease range overgenomen heeft.
+fn add_lease_options(
+    packet: &mut Packet,
+    lease_range: &LeaseRange,
+    client_options: Vec<ScopedOption>,
+    lease_time: LeaseTime,
+    requested: &[u8],
+    boot_rule: Option<&BootRule>,
+) {
+    packet.override_option(lease_range.get_subnet_option());
+    for option in lease_range.get_leasetime_options(lease_time) {
+        packet.override_option(option);
+    }
+    packet.override_option(lease_range.get_server_id_option());
+    for option in lease_range.get_options(requested) {
+        packet.override_option(option.clone());
+    }
+    for scoped in client_options {
+        if leases::include_option(&scoped.option, requested) {
+            packet.override_option(scoped.option);
+        }
+    }
+    if let Some(rule) = boot_rule {
+        boot::apply(packet, rule, lease_range.server_addr, requested);
+    }
+}
+
+/// Maakt een DHCPNAK, deze wordt altijd als broadcast teruggestuurd omdat
+/// de client zijn ip niet meer mag gebruiken.
+fn nak(mut packet: Packet, lease_range: &LeaseRange) -> Packet {
+    packet.ciaddr = Ipv4Addr::UNSPECIFIED;
+    packet.yiaddr = Ipv4Addr::UNSPECIFIED;
+    packet.set_broadcast();
+    packet.into_response(DHCPMessageType::DHCPNAK);
+    packet.override_option(lease_range.get_server_id_option());
+    packet
 }
 
 // pub fn on_dhcp_discover( packet: Packet,data: &mut LeaseRange ) -> Result<(), ()> {
@@ -46,3 +445,334 @@ pub fn on_dhcp_request( packet: Packet , state: DHCPState) -> Result<Packet, DHC
 
 //     Ok(())
 // }
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use crate::{
+        config::Config,
+        packet::{DHCPMessageType, DHCPOption, Packet},
+        state::DHCPState,
+    };
+
+    use super::{on_dhcp_discover, on_dhcp_release, on_dhcp_request};
+
+    const KNOWN: [u8; 6] = [0xde, 0xad, 0xc0, 0xde, 0xca, 0xfe];
+    const KNOWN2: [u8; 6] = [0xde, 0xad, 0xc0, 0xde, 0xca, 0xff];
+    const GUEST: [u8; 6] = [0x02, 0x00, 0x00, 0x00, 0x00, 0x01];
+    const OTHER: [u8; 6] = [0x00, 0x11, 0x22, 0x33, 0x44, 0x55];
+
+    /// Drie pools in hetzelfde netwerk: een voor vaste hosts, een voor
+    /// gasten en een voor de rest.
+    fn state() -> DHCPState {
+        let config: Config = toml::from_str(
+            r#"
+            [[subnet]]
+            range_start = "192.168.56.10"
+            range_end = "192.168.56.11"
+            server_addr = "192.168.56.1"
+            subnet_mask = "255.255.255.0"
+            allow = ["known-clients"]
+
+            [[subnet]]
+            range_start = "192.168.56.100"
+            range_end = "192.168.56.109"
+            server_addr = "192.168.56.1"
+            subnet_mask = "255.255.255.0"
+            allow = ["class:guests"]
+            on_deny = "nak"
+
+            [[subnet]]
+            range_start = "192.168.56.200"
+            range_end = "192.168.56.209"
+            server_addr = "192.168.56.1"
+            subnet_mask = "255.255.255.0"
+            deny = ["class:guests"]
+            ipv6_only_preferred = 1800
+
+            [[host]]
+            hardware_address = "de:ad:c0:de:ca:fe"
+
+            [[host]]
+            hardware_address = "de:ad:c0:de:ca:ff"
+
+            [[class]]
+            name = "guests"
+            captive_portal = "https://portal.example.com/api"
+            [class.match]
+            hardware_prefix = "02:00:00"
+            "#,
+        )
+        .unwrap();
+        assert!(config.validate().is_ok());
+        DHCPState::from_config(config, None)
+    }
+
+    fn packet(message_type: DHCPMessageType, chaddr: [u8; 6], xid: u32) -> Packet {
+        let mut packet = Packet::new_request(message_type);
+        packet.set_chaddr(&chaddr);
+        packet.xid = xid;
+        packet
+    }
+
+    fn offer(state: &DHCPState, chaddr: [u8; 6], xid: u32) -> Option<Ipv4Addr> {
+        discover(state, chaddr, xid).map(|offer| offer.yiaddr)
+    }
+
+    fn discover(state: &DHCPState, chaddr: [u8; 6], xid: u32) -> Option<Packet> {
+        on_dhcp_discover(packet(DHCPMessageType::DHCPDISCOVER, chaddr, xid), state.clone(), None)
+            .unwrap()
+    }
+
+    fn request(state: &DHCPState, chaddr: [u8; 6], xid: u32, ip: Ipv4Addr) -> Option<Packet> {
+        let mut packet = packet(DHCPMessageType::DHCPREQUEST, chaddr, xid);
+        packet.add_option(DHCPOption::RequestedIp(ip));
+        packet.add_option(DHCPOption::ServerIdentifier(Ipv4Addr::new(192, 168, 56, 1)));
+        on_dhcp_request(packet, state.clone(), None).unwrap()
+    }
+
+    #[test]
+    fn pool_fallthrough() {
+        let state = state();
+        let ip = Ipv4Addr::new(192, 168, 56, 10);
+        assert_eq!(offer(&state, KNOWN, 1), Some(ip));
+        let ack = request(&state, KNOWN, 1, ip).unwrap();
+        assert_eq!(ack.dhcp_message_type, DHCPMessageType::DHCPACK);
+        // de pool voor vaste hosts is vol, de guest pool laat geen vaste
+        // hosts toe
+        assert_eq!(
+            offer(&state, KNOWN2, 2),
+            Some(Ipv4Addr::new(192, 168, 56, 200))
+        );
+        assert_eq!(
+            offer(&state, GUEST, 3),
+            Some(Ipv4Addr::new(192, 168, 56, 100))
+        );
+        // een adres dat enkel geoffered is wordt ook aan andere clients
+        // geoffered
+        assert_eq!(
+            offer(&state, OTHER, 4),
+            Some(Ipv4Addr::new(192, 168, 56, 200))
+        );
+    }
+
+    #[test]
+    fn denied_request() {
+        let state = state();
+        // de guest pool stuurt een NAK
+        let nak = request(&state, OTHER, 5, Ipv4Addr::new(192, 168, 56, 100)).unwrap();
+        assert_eq!(nak.dhcp_message_type, DHCPMessageType::DHCPNAK);
+        // de pool voor vaste hosts antwoordt niet
+        assert!(request(&state, OTHER, 5, Ipv4Addr::new(192, 168, 56, 10)).is_none());
+        // een gast mag niet in de laatste pool, ook niet zonder regels in
+        // allow
+        assert!(request(&state, GUEST, 6, Ipv4Addr::new(192, 168, 56, 200)).is_none());
+    }
+
+    #[test]
+    fn release_from_second_pool() {
+        let state = state();
+        let ip = Ipv4Addr::new(192, 168, 56, 100);
+        assert_eq!(offer(&state, GUEST, 1), Some(ip));
+        let ack = request(&state, GUEST, 1, ip).unwrap();
+        assert_eq!(ack.dhcp_message_type, DHCPMessageType::DHCPACK);
+        assert!(state.lock()[1].data.contains_key(&ip));
+
+        let mut release = packet(DHCPMessageType::DHCPRELEASE, GUEST, 2);
+        release.ciaddr = ip;
+        assert!(on_dhcp_release(release, state.clone()).unwrap().is_none());
+        assert!(!state.lock()[1].data.contains_key(&ip));
+    }
+
+    #[test]
+    fn class_options() {
+        let state = state();
+        // enkel gasten krijgen de captive portal
+        let guest = discover(&state, GUEST, 1).unwrap();
+        assert!(matches!(
+            guest.get_option(114),
+            Some(DHCPOption::CaptivePortal(uri)) if uri == "https://portal.example.com/api"
+        ));
+        let other = discover(&state, OTHER, 2).unwrap();
+        assert!(other.get_option(114).is_none());
+    }
+
+    #[test]
+    fn ipv6_only_preferred() {
+        let state = state();
+        let mut request = packet(DHCPMessageType::DHCPDISCOVER, OTHER, 1);
+        request.add_option(DHCPOption::ParameterRequest(vec![1, 3, 6, 108]));
+        let offer = on_dhcp_discover(request, state.clone(), None).unwrap().unwrap();
+        assert_eq!(offer.dhcp_message_type, DHCPMessageType::DHCPOFFER);
+        assert_eq!(offer.yiaddr, Ipv4Addr::UNSPECIFIED);
+        assert!(matches!(
+            offer.get_option(108),
+            Some(DHCPOption::Ipv6OnlyPreferred(1800))
+        ));
+        // er is geen adres geoffered
+        assert!(state.lock().iter().all(|range| range.data.is_empty()));
+
+        // zonder 108 in de parameter request list een gewoon adres
+        let offer = discover(&state, OTHER, 2).unwrap();
+        assert_eq!(offer.yiaddr, Ipv4Addr::new(192, 168, 56, 200));
+        assert!(offer.get_option(108).is_none());
+
+        let mut request = packet(DHCPMessageType::DHCPREQUEST, OTHER, 3);
+        request.ciaddr = Ipv4Addr::new(192, 168, 56, 201);
+        request.add_option(DHCPOption::ParameterRequest(vec![1, 3, 108]));
+        let ack = on_dhcp_request(request, state.clone(), None).unwrap().unwrap();
+        assert_eq!(ack.dhcp_message_type, DHCPMessageType::DHCPACK);
+        assert_eq!(ack.yiaddr, Ipv4Addr::UNSPECIFIED);
+        assert!(ack.get_option(108).is_some());
+    }
+
+    /// Het lokale netwerk en drie netwerken achter relay agents.
+    fn relayed_state() -> DHCPState {
+        let mut config = String::new();
+        for network in ["192.168.56", "10.0.1", "10.0.2", "10.0.3"] {
+            config += &format!(
+                r#"
+                [[subnet]]
+                range_start = "{network}.100"
+                range_end = "{network}.109"
+                server_addr = "192.168.56.1"
+                subnet_mask = "255.255.255.0"
+                "#
+            );
+        }
+        let config: Config = toml::from_str(&config).unwrap();
+        assert!(config.validate().is_ok());
+        DHCPState::from_config(config, None)
+    }
+
+    fn relayed(message_type: DHCPMessageType, xid: u32) -> Packet {
+        let mut packet = packet(message_type, OTHER, xid);
+        packet.giaddr = Ipv4Addr::new(10, 0, 1, 1);
+        packet
+    }
+
+    #[test]
+    fn link_selection() {
+        let state = relayed_state();
+        let allocate = |packet: Packet| {
+            on_dhcp_discover(packet, state.clone(), None)
+                .unwrap()
+                .unwrap()
+        };
+
+        // enkel giaddr
+        let offer = allocate(relayed(DHCPMessageType::DHCPDISCOVER, 1));
+        assert_eq!(offer.yiaddr, Ipv4Addr::new(10, 0, 1, 100));
+
+        // subnet selection gaat voor giaddr en komt terug in het antwoord
+        let mut packet = relayed(DHCPMessageType::DHCPDISCOVER, 2);
+        packet.add_option(DHCPOption::SubnetSelection(Ipv4Addr::new(10, 0, 2, 0)));
+        let offer = allocate(packet);
+        assert_eq!(offer.yiaddr, Ipv4Addr::new(10, 0, 2, 100));
+        assert!(matches!(
+            offer.get_option(118),
+            Some(DHCPOption::SubnetSelection(subnet)) if *subnet == Ipv4Addr::new(10, 0, 2, 0)
+        ));
+
+        // link selection gaat voor subnet selection
+        let mut packet = relayed(DHCPMessageType::DHCPDISCOVER, 3);
+        packet.add_option(DHCPOption::SubnetSelection(Ipv4Addr::new(10, 0, 2, 0)));
+        packet.add_option(DHCPOption::RelayAgentInfo(vec![(5, vec![10, 0, 3, 0])]));
+        let offer = allocate(packet);
+        assert_eq!(offer.yiaddr, Ipv4Addr::new(10, 0, 3, 100));
+        assert!(offer.get_option(82).is_some());
+
+        // een link selection met een verkeerde lengte telt niet
+        let mut packet = relayed(DHCPMessageType::DHCPDISCOVER, 4);
+        packet.add_option(DHCPOption::RelayAgentInfo(vec![(5, vec![10, 0, 3])]));
+        assert_eq!(allocate(packet).yiaddr, Ipv4Addr::new(10, 0, 1, 100));
+
+        // een link zonder subnet krijgt geen antwoord
+        let mut packet = relayed(DHCPMessageType::DHCPDISCOVER, 5);
+        packet.add_option(DHCPOption::RelayAgentInfo(vec![(5, vec![10, 0, 9, 0])]));
+        assert!(on_dhcp_discover(packet, state.clone(), None)
+            .unwrap()
+            .is_none());
+
+        // een REQUEST kiest de pool op dezelfde manier
+        let mut packet = relayed(DHCPMessageType::DHCPREQUEST, 2);
+        packet.add_option(DHCPOption::SubnetSelection(Ipv4Addr::new(10, 0, 2, 0)));
+        packet.add_option(DHCPOption::RequestedIp(Ipv4Addr::new(10, 0, 2, 100)));
+        packet.add_option(DHCPOption::ServerIdentifier(Ipv4Addr::new(192, 168, 56, 1)));
+        let ack = on_dhcp_request(packet, state.clone(), None).unwrap().unwrap();
+        assert_eq!(ack.dhcp_message_type, DHCPMessageType::DHCPACK);
+        assert_eq!(ack.yiaddr, Ipv4Addr::new(10, 0, 2, 100));
+    }
+
+    #[test]
+    fn unicast_renewal() {
+        let state = relayed_state();
+        let mut discover = relayed(DHCPMessageType::DHCPDISCOVER, 1);
+        discover.giaddr = Ipv4Addr::new(10, 0, 2, 1);
+        let offer = on_dhcp_discover(discover, state.clone(), None)
+            .unwrap()
+            .unwrap();
+        let ip = offer.yiaddr;
+        assert_eq!(ip, Ipv4Addr::new(10, 0, 2, 100));
+
+        let mut request = relayed(DHCPMessageType::DHCPREQUEST, 1);
+        request.giaddr = Ipv4Addr::new(10, 0, 2, 1);
+        request.add_option(DHCPOption::RequestedIp(ip));
+        request.add_option(DHCPOption::ServerIdentifier(Ipv4Addr::new(192, 168, 56, 1)));
+        let ack = on_dhcp_request(request, state.clone(), None).unwrap().unwrap();
+        assert_eq!(ack.dhcp_message_type, DHCPMessageType::DHCPACK);
+
+        // RENEWING: unicast naar de server, zonder relay agent
+        let mut renew = packet(DHCPMessageType::DHCPREQUEST, OTHER, 2);
+        renew.ciaddr = ip;
+        let ack = on_dhcp_request(renew, state.clone(), None).unwrap().unwrap();
+        assert_eq!(ack.dhcp_message_type, DHCPMessageType::DHCPACK);
+        assert_eq!(ack.yiaddr, ip);
+    }
+
+    fn rapid_commit_state(rapid_commit: bool) -> DHCPState {
+        let config: Config = toml::from_str(&format!(
+            r#"
+            [[subnet]]
+            range_start = "192.168.56.10"
+            range_end = "192.168.56.20"
+            server_addr = "192.168.56.1"
+            subnet_mask = "255.255.255.0"
+            rapid_commit = {rapid_commit}
+            "#
+        ))
+        .unwrap();
+        assert!(config.validate().is_ok());
+        DHCPState::from_config(config, None)
+    }
+
+    #[test]
+    fn rapid_commit() {
+        let state = rapid_commit_state(true);
+        let mut request = packet(DHCPMessageType::DHCPDISCOVER, OTHER, 1);
+        request.add_option(DHCPOption::RapidCommit);
+        let ack = on_dhcp_discover(request, state.clone(), None).unwrap().unwrap();
+        assert_eq!(ack.dhcp_message_type, DHCPMessageType::DHCPACK);
+        assert!(matches!(ack.get_option(80), Some(DHCPOption::RapidCommit)));
+
+        // zonder option 80 een gewone OFFER, het adres van de eerste client
+        // is in gebruik
+        let offer = discover(&state, KNOWN, 2).unwrap();
+        assert_eq!(offer.dhcp_message_type, DHCPMessageType::DHCPOFFER);
+        assert!(offer.get_option(80).is_none());
+        assert_ne!(offer.yiaddr, ack.yiaddr);
+    }
+
+    #[test]
+    fn rapid_commit_disabled() {
+        let state = rapid_commit_state(false);
+        let mut request = packet(DHCPMessageType::DHCPDISCOVER, OTHER, 1);
+        request.add_option(DHCPOption::RapidCommit);
+        let response = on_dhcp_discover(request, state.clone(), None).unwrap().unwrap();
+        assert_eq!(response.dhcp_message_type, DHCPMessageType::DHCPOFFER);
+        assert!(response.get_option(80).is_none());
+        // enkel geoffered, een andere client krijgt hetzelfde adres
+        assert_eq!(offer(&state, KNOWN, 2), Some(response.yiaddr));
+    }
+}
@@ -0,0 +1,119 @@
+use std::net::Ipv4Addr;
+
+use crate::{
+    config::HostnamePolicy,
+    packet::{dns::MAX_LABEL_LEN, DHCPOption, Packet},
+};
+
+/// De naam van een lease zoals die in DNS komt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaseName {
+    pub hostname: String,
+    /// `host.domain.` als het subnet een domain name heeft, anders de hostname
+    pub fqdn: String,
+    /// Identificeert de client in het DHCID record, zie
+    /// [`crate::ddns::dhcid_identifier`]
+    pub dhcid_identifier: Vec<u8>,
+}
+
+/// De naam die de client zelf meestuurt, uit de Client FQDN (81) of
+/// anders de Host Name (12) option.
+pub fn client_hostname(packet: &Packet) -> Option<&str> {
+    match (packet.get_option(81), packet.get_option(12)) {
+        (Some(DHCPOption::ClientFqdn(fqdn)), _) => fqdn.hostname(),
+        (_, Some(DHCPOption::HostName(hostname))) => Some(hostname.as_str()),
+        _ => None,
+    }
+}
+
+/// Bepaalt de hostname van een lease. Een naam uit de reservatie gaat altijd
+/// voor, anders beslist `policy`.
+pub fn assign_hostname(
+    policy: HostnamePolicy,
+    reservation: Option<&str>,
+    client: Option<&str>,
+    ip: Ipv4Addr,
+) -> Option<String> {
+    if let Some(hostname) = reservation.and_then(sanitize_hostname) {
+        return Some(hostname);
+    }
+    match policy {
+        HostnamePolicy::Accept => client
+            .and_then(sanitize_hostname)
+            .or_else(|| Some(generate_hostname(ip))),
+        HostnamePolicy::Generate => Some(generate_hostname(ip)),
+        HostnamePolicy::Reservation => None,
+    }
+}
+
+/// Maakt een geldig DNS label (RFC 1123) van een naam: enkel het eerste
+/// label, kleine letters, cijfers en `-`. Ongeldige tekens worden `-`.
+pub fn sanitize_hostname(name: &str) -> Option<String> {
+    let label = name.split('.').next()?;
+    let label: String = label
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | '0'..='9' | '-' => c,
+            'A'..='Z' => c.to_ascii_lowercase(),
+            _ => '-',
+        })
+        .take(MAX_LABEL_LEN)
+        .collect();
+    let label = label.trim_matches('-');
+    if label.is_empty() {
+        None
+    } else {
+        Some(label.to_string())
+    }
+}
+
+/// Naam op basis van het ip, bv. `dhcp-192-168-56-10`
+pub fn generate_hostname(ip: Ipv4Addr) -> String {
+    let [a, b, c, d] = ip.octets();
+    format!("dhcp-{a}-{b}-{c}-{d}")
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use crate::config::HostnamePolicy;
+
+    use super::{assign_hostname, generate_hostname, sanitize_hostname};
+
+    #[test]
+    fn sanitize() {
+        assert_eq!(sanitize_hostname("Joeys-Laptop"), Some("joeys-laptop".into()));
+        assert_eq!(sanitize_hostname("my laptop_2.local"), Some("my-laptop-2".into()));
+        assert_eq!(sanitize_hostname("-printer-"), Some("printer".into()));
+        assert_eq!(sanitize_hostname("___"), None);
+        assert_eq!(sanitize_hostname(&"a".repeat(70)).unwrap().len(), 63);
+    }
+
+    #[test]
+    fn policy() {
+        let ip = Ipv4Addr::new(192, 168, 56, 10);
+        assert_eq!(generate_hostname(ip), "dhcp-192-168-56-10");
+
+        assert_eq!(
+            assign_hostname(HostnamePolicy::Accept, None, Some("laptop"), ip),
+            Some("laptop".into())
+        );
+        assert_eq!(
+            assign_hostname(HostnamePolicy::Accept, None, None, ip),
+            Some("dhcp-192-168-56-10".into())
+        );
+        assert_eq!(
+            assign_hostname(HostnamePolicy::Generate, None, Some("laptop"), ip),
+            Some("dhcp-192-168-56-10".into())
+        );
+        assert_eq!(
+            assign_hostname(HostnamePolicy::Reservation, None, Some("laptop"), ip),
+            None
+        );
+        assert_eq!(
+            assign_hostname(HostnamePolicy::Generate, Some("printer"), Some("laptop"), ip),
+            Some("printer".into())
+        );
+    }
+}
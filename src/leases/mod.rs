@@ -1,13 +1,21 @@
-use self::statemachine::DHCPStates;
+use self::{hostname::LeaseName, statemachine::DHCPStates};
 use crate::{
+    config::{BootRule, DenyAction, HostnamePolicy, PoolRule, SubnetConfig},
     leases::statemachine::DHCPOffer,
     packet::{DHCPOption, LeaseTime, Packet},
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use std::{collections::HashMap, net::Ipv4Addr, time::Duration};
+use tokio::sync::mpsc::UnboundedSender;
+
+pub use policy::LeasePolicy;
+pub use statemachine::LeaseEvent;
+
+pub mod hostname;
 
 pub const DEFAULT_LEASE_TIME: LeaseTime = LeaseTime::Finite(Duration::from_secs(86600));
 
+mod policy;
 mod statemachine;
 
 #[derive(Debug, Clone)]
@@ -16,9 +24,96 @@ pub struct LeaseRange {
     pub end_lease: Ipv4Addr,
     pub server_addr: Ipv4Addr,
     pub subnet: Ipv4Addr,
+    pub policy: LeasePolicy,
+    /// Als de server authoritative is voor dit subnet worden requests voor
+    /// onbekende adressen met een NAK beantwoord in plaats van genegeerd.
+    pub authoritative: bool,
+    /// DISCOVER met Rapid Commit meteen beantwoorden met een ACK
+    pub rapid_commit: bool,
+    /// Extra opties voor clients in dit subnet
+    pub options: Vec<DHCPOption>,
+    pub domain_name: Option<String>,
+    pub hostname_policy: HostnamePolicy,
+    pub boot_rules: Vec<BootRule>,
+    pub allow: Vec<PoolRule>,
+    pub deny: Vec<PoolRule>,
+    pub on_deny: DenyAction,
+    /// Hier worden veranderingen aan de namen van leases naartoe gestuurd
+    pub lease_events: Option<UnboundedSender<LeaseEvent>>,
     pub data: HashMap<Ipv4Addr, DHCPStates>,
 }
 
+/// Wat de allow en deny regels van een pool over de client weten.
+#[derive(Debug, Clone, Default)]
+pub struct PoolClient<'a> {
+    /// De client heeft een `[[host]]`
+    pub known: bool,
+    /// Namen van de classes van de client
+    pub classes: Vec<&'a str>,
+}
+
+impl PoolRule {
+    pub fn matches(&self, client: &PoolClient) -> bool {
+        match self {
+            PoolRule::KnownClients => client.known,
+            PoolRule::UnknownClients => !client.known,
+            PoolRule::Class(name) => client.classes.contains(&name.as_str()),
+        }
+    }
+}
+
+/// Kijkt of `ip` een van de adressen is die een pool van `start` tot `end`
+/// uitdeelt, net als bij [`LeaseRange::get_available_ip`] hoort `end` daar
+/// zelf niet bij.
+pub fn in_pool(ip: Ipv4Addr, start: Ipv4Addr, end: Ipv4Addr) -> bool {
+    (start..end).contains(&ip)
+}
+
+/// Kijkt of `ip` in het netwerk van `address` met `subnet_mask` ligt.
+pub fn in_network(ip: Ipv4Addr, address: Ipv4Addr, subnet_mask: Ipv4Addr) -> bool {
+    let mask = u32::from(subnet_mask);
+    u32::from(ip) & mask == u32::from(address) & mask
+}
+
+/// Link selection sub-optie van de relay agent information (RFC 3527)
+pub const RELAY_LINK_SELECTION: u8 = 5;
+
+/// Een adres in het netwerk waar de client een adres uit moet krijgen, of
+/// `0.0.0.0` als de client op het lokale netwerk zit. Van hoog naar laag:
+///
+/// 1. link selection (82.5, RFC 3527): de relay agent zegt op welke link de
+///    client zit. De server stuurt het antwoord nog steeds naar giaddr.
+///    Zoals bij ISC dhcpd gaat deze voor 118, de relay agent weet beter
+///    waar de client zit dan de client zelf.
+/// 2. subnet selection (118, RFC 3011): gezet door de client of door een
+///    relay agent zonder option 82.
+/// 3. giaddr van de relay agent.
+///
+/// Een link selection met een verkeerde lengte wordt genegeerd.
+pub fn link_address(packet: &Packet) -> Ipv4Addr {
+    let link_selection = packet
+        .relay_suboption(RELAY_LINK_SELECTION)
+        .and_then(|link| <[u8; 4]>::try_from(link).ok())
+        .map(Ipv4Addr::from);
+    let subnet_selection = match packet.get_option(118) {
+        Some(DHCPOption::SubnetSelection(subnet)) => Some(*subnet),
+        _ => None,
+    };
+    link_selection
+        .or(subnet_selection)
+        .unwrap_or(packet.giaddr)
+}
+
+/// Classless static routes (121 en 249, RFC 3442) en IPv6-only preferred
+/// (108, RFC 8925) worden enkel meegegeven als de client ze gevraagd heeft,
+/// andere opties altijd.
+pub fn include_option(option: &DHCPOption, requested: &[u8]) -> bool {
+    match option.get_option_id() {
+        108 | 121 | 249 => requested.contains(&option.get_option_id()),
+        _ => true,
+    }
+}
+
 impl LeaseRange {
     pub fn new(
         start_lease: Ipv4Addr,
@@ -31,16 +126,99 @@ impl LeaseRange {
             end_lease,
             subnet,
             server_addr,
+            policy: LeasePolicy::default(),
+            authoritative: false,
+            rapid_commit: false,
+            options: Vec::new(),
+            domain_name: None,
+            hostname_policy: HostnamePolicy::default(),
+            boot_rules: Vec::new(),
+            allow: Vec::new(),
+            deny: Vec::new(),
+            on_deny: DenyAction::default(),
+            lease_events: None,
             data: HashMap::new(),
         }
     }
 
+    /// De opties en domain name komen uit alle scopes boven de pool en
+    /// worden door `DHCPState::from_config` ingevuld.
+    pub fn from_config(config: &SubnetConfig) -> Self {
+        LeaseRange {
+            policy: LeasePolicy::default().merge(&config.lease_time),
+            authoritative: config.authoritative,
+            rapid_commit: config.rapid_commit,
+            hostname_policy: config.hostname_policy,
+            boot_rules: config.boot_rules.clone(),
+            allow: config.allow.clone(),
+            deny: config.deny.clone(),
+            on_deny: config.on_deny,
+            ..LeaseRange::new(
+                config.range_start,
+                config.range_end,
+                config.server_addr,
+                config.subnet_mask,
+            )
+        }
+    }
+
+    /// Zoekt de range waar een client thuishoort: het subnet van `link` (zie
+    /// [`link_address`]) of, als de client rechtstreeks verbonden is, het
+    /// eerste subnet.
+    pub fn select(ranges: &mut [LeaseRange], link: Ipv4Addr) -> Option<&mut LeaseRange> {
+        LeaseRange::network_pools(ranges, link).into_iter().next()
+    }
+
+    /// Alle pools in het netwerk van de client, in de volgorde van de
+    /// config. Het netwerk is dat van `link` (zie [`link_address`]) of, als
+    /// de client rechtstreeks verbonden is, dat van het eerste subnet.
+    pub fn network_pools(ranges: &mut [LeaseRange], link: Ipv4Addr) -> Vec<&mut LeaseRange> {
+        let network = match ranges.first() {
+            _ if !link.is_unspecified() => link,
+            Some(first) => first.start_lease,
+            None => return Vec::new(),
+        };
+        ranges
+            .iter_mut()
+            .filter(|range| range.contains(network))
+            .collect()
+    }
+
+    /// Of de client een adres uit deze pool mag krijgen. Zonder `allow`
+    /// regels mag iedereen die niet in `deny` staat.
+    pub fn permits(&self, client: &PoolClient) -> bool {
+        let allowed = self.allow.is_empty() || self.allow.iter().any(|rule| rule.matches(client));
+        allowed && !self.deny.iter().any(|rule| rule.matches(client))
+    }
+
+    /// Kijkt of `ip` een van de adressen is die deze pool uitdeelt.
+    pub fn in_pool(&self, ip: Ipv4Addr) -> bool {
+        in_pool(ip, self.start_lease, self.end_lease)
+    }
+
+    /// Kijkt of `ip` in het netwerk van deze range ligt.
+    pub fn contains(&self, ip: Ipv4Addr) -> bool {
+        in_network(ip, self.start_lease, self.subnet)
+    }
+
     pub fn get_subnet_option(&self) -> DHCPOption {
         DHCPOption::Subnet(self.subnet)
     }
 
-    pub fn get_leasetime_option(&self) -> DHCPOption {
-        DHCPOption::IpLeasetime(LeaseTime::Infinite)
+    /// Geeft de leasetijd (51), T1 (58) en T2 (59) opties.
+    pub fn get_leasetime_options(&self, lease_time: LeaseTime) -> [DHCPOption; 3] {
+        [
+            DHCPOption::IpLeasetime(lease_time),
+            DHCPOption::RenewalTime(lease_time.renewal_time()),
+            DHCPOption::RebindingTime(lease_time.rebinding_time()),
+        ]
+    }
+
+    /// De extra opties van dit subnet, zie [`include_option`].
+    pub fn get_options<'a>(&'a self, requested: &'a [u8]) -> impl Iterator<Item = &'a DHCPOption> {
+        self.options
+            .iter()
+            .filter(|option| include_option(option, requested))
     }
 
     pub fn get_server_id_option(&self) -> DHCPOption {
@@ -49,7 +227,7 @@ impl LeaseRange {
 
     /// Zoekt naar een beschikbaar ip in de range.
     /// Geeft `None` terug als er geen beschikbaar is
-    pub fn get_available_ip(&mut self, xid: u32) -> Option<Ipv4Addr> {
+    pub fn get_available_ip(&mut self, xid: u32, lease_time: LeaseTime) -> Option<Ipv4Addr> {
         for opt_leasable_ip in self.start_lease..self.end_lease {
             // kijken of er een state is opgeslagen + kijken of deze al geoffered is
             if let Some(state) = self.data.get_mut(&opt_leasable_ip) {
@@ -57,17 +235,14 @@ impl LeaseRange {
                     DHCPStates::Offered(clients) => {
                         // de xid van huidige client aan de reeds geofferde ip toevoegen
                         println!("IP is al geoffered, nog eens offeren");
-                        clients.insert(DHCPOffer {
-                            xid,
-                            lease_time: DEFAULT_LEASE_TIME,
-                        });
+                        clients.replace(DHCPOffer { xid, lease_time });
                         return Some(opt_leasable_ip);
                     }
                     _ => continue,
                 }
             } else {
                 self.data
-                    .insert(opt_leasable_ip, DHCPStates::new_offered(xid));
+                    .insert(opt_leasable_ip, DHCPStates::new_offered(xid, lease_time));
                 println!("IP nog niet uitgeleased");
                 return Some(opt_leasable_ip);
             }
@@ -75,32 +250,314 @@ impl LeaseRange {
         None
     }
 
-    pub fn reserve_ip(&mut self, packet: &Packet, ip: Ipv4Addr) -> Result<(), LeaseReserveError> {
+    /// De volledige naam van een client in dit subnet
+    pub fn fqdn(&self, hostname: &str) -> String {
+        match &self.domain_name {
+            Some(domain) => format!("{hostname}.{}.", domain.trim_end_matches('.')),
+            None => hostname.to_string(),
+        }
+    }
+
+    /// Geeft de lease van `ip` een naam. Als de naam verandert wordt de oude
+    /// naam vrijgegeven en de nieuwe gemeld.
+    pub fn set_hostname(&mut self, ip: Ipv4Addr, hostname: Option<LeaseName>) {
+        let Some(state) = self.data.get_mut(&ip) else {
+            return;
+        };
+        let previous = state.set_hostname(hostname.clone());
+        if previous == hostname {
+            return;
+        }
+        if let Some(name) = previous {
+            self.send_event(LeaseEvent::Released { ip, name });
+        }
+        if let Some(name) = hostname {
+            self.send_event(LeaseEvent::Bound { ip, name });
+        }
+    }
+
+    /// Geeft de lease van `ip` vrij als die van de client van `packet` is
+    /// (DHCPRELEASE).
+    pub fn release_ip(&mut self, packet: &Packet, ip: Ipv4Addr) -> Result<(), LeaseReserveError> {
+        match self.data.get(&ip) {
+            Some(state) if state.is_owned_by(&packet.client_id()) => {
+                self.remove_lease(ip);
+                Ok(())
+            }
+            Some(DHCPStates::Used { .. }) => Err(LeaseReserveError::InUse),
+            _ => Err(LeaseReserveError::NotRequested),
+        }
+    }
+
+    /// Geeft alle leases vrij waarvan de leasetijd voorbij is en geeft hun
+    /// ips terug.
+    pub fn expire(&mut self, now: DateTime<Utc>) -> Vec<Ipv4Addr> {
+        let expired: Vec<Ipv4Addr> = self
+            .data
+            .iter()
+            .filter(|(_, state)| state.is_expired(now))
+            .map(|(ip, _)| *ip)
+            .collect();
+        for ip in &expired {
+            tracing::info!("Lease for {ip} expired");
+            self.remove_lease(*ip);
+        }
+        expired
+    }
+
+    fn remove_lease(&mut self, ip: Ipv4Addr) {
+        if let Some(name) = self.data.remove(&ip).as_ref().and_then(DHCPStates::hostname) {
+            self.send_event(LeaseEvent::Released {
+                ip,
+                name: name.clone(),
+            });
+        }
+    }
+
+    fn send_event(&self, event: LeaseEvent) {
+        if let Some(sender) = &self.lease_events {
+            // zonder ontvanger is er niemand geïnteresseerd
+            let _ = sender.send(event);
+        }
+    }
+
+    /// Verwijdert de offers die naar `xid` gestuurd zijn, bv. omdat de client
+    /// een andere server gekozen heeft. Ips die niemand meer geoffered
+    /// hebben komen terug vrij.
+    pub fn release_offer(&mut self, xid: u32) {
+        self.data.retain(|_, state| match state {
+            DHCPStates::Offered(clients) => {
+                clients.remove(&xid.into());
+                !clients.is_empty()
+            }
+            DHCPStates::Used { .. } => true,
+        });
+    }
+
+    /// Zet een geofferd ip in gebruik. Als de client in de request een
+    /// leasetijd vraagt wordt die opnieuw afgetoetst aan `policy`, anders
+    /// blijft de geofferde leasetijd staan.
+    /// Als de client het ip al in gebruik heeft (bv. een herhaalde request)
+    /// wordt de lease verlengd.
+    pub fn reserve_ip(
+        &mut self,
+        packet: &Packet,
+        ip: Ipv4Addr,
+        policy: &LeasePolicy,
+    ) -> Result<LeaseTime, LeaseReserveError> {
+        let client_id = packet.client_id();
         let Some(state) = self.data.get_mut(&ip) else {
             return Err(LeaseReserveError::NotRequested);
         };
         let lease_time: LeaseTime = match state {
             DHCPStates::Offered(clients) => {
                 if let Some(offer) = clients.get(&packet.xid.into()) {
-                    offer.lease_time
+                    packet
+                        .get_leasetime()
+                        .map_or(offer.lease_time, |requested| policy.lease_time(Some(requested)))
                 } else {
                     return Err(LeaseReserveError::NotRequested);
                 }
             }
-            _ => return Err(LeaseReserveError::InUse),
+            DHCPStates::Used { .. } => {
+                let lease_time = policy.lease_time(packet.get_leasetime());
+                state.extend(&client_id, lease_time)?;
+                return Ok(lease_time);
+            }
         };
-        *state = DHCPStates::Used {
-            client_id: (),
-            lease_time,
-            start_time: Utc::now(),
-            options: packet.options_cloned(),
+        *state = DHCPStates::new_used(client_id, lease_time, packet.options_cloned());
+        Ok(lease_time)
+    }
+
+    /// Verlengt de lease van `ip` voor een client in RENEWING of REBINDING
+    /// state. De leasetijd wordt opnieuw bepaald met `policy`.
+    pub fn renew_ip(
+        &mut self,
+        packet: &Packet,
+        ip: Ipv4Addr,
+        policy: &LeasePolicy,
+    ) -> Result<LeaseTime, LeaseReserveError> {
+        let Some(state) = self.data.get_mut(&ip) else {
+            return Err(LeaseReserveError::NotRequested);
         };
-        Ok(())
+        let lease_time = policy.lease_time(packet.get_leasetime());
+        state.extend(&packet.client_id(), lease_time)?;
+        Ok(lease_time)
+    }
+
+    /// Controleert het ip dat een client in INIT-REBOOT state terug wil.
+    /// Als het ip nog aan de client toegewezen is wordt de lease verlengd.
+    pub fn verify_ip(
+        &mut self,
+        packet: &Packet,
+        ip: Ipv4Addr,
+        policy: &LeasePolicy,
+    ) -> Result<LeaseTime, LeaseReserveError> {
+        if !self.contains(ip) {
+            return Err(LeaseReserveError::WrongNetwork);
+        }
+        self.renew_ip(packet, ip, policy)
     }
 }
 
 #[derive(Debug)]
 pub enum LeaseReserveError {
+    /// Er is geen offer of lease voor deze client
     NotRequested,
+    /// Het ip is in gebruik door een andere client
     InUse,
+    /// Het ip hoort niet bij het netwerk waar de client op zit
+    WrongNetwork,
+}
+
+#[cfg(test)]
+mod test {
+    use std::{net::Ipv4Addr, time::Duration};
+
+    use chrono::Utc;
+    use tokio::sync::mpsc;
+
+    use crate::packet::{DHCPMessageType, DHCPOption, LeaseTime, Packet};
+
+    use super::{
+        hostname::LeaseName, LeaseEvent, LeasePolicy, LeaseRange, LeaseReserveError,
+        DEFAULT_LEASE_TIME,
+    };
+
+    fn lease_range() -> LeaseRange {
+        LeaseRange::new(
+            Ipv4Addr::new(192, 168, 56, 3),
+            Ipv4Addr::new(192, 168, 56, 255),
+            Ipv4Addr::new(192, 168, 56, 1),
+            Ipv4Addr::new(255, 255, 255, 0),
+        )
+    }
+
+    #[test]
+    fn renew_by_owner_and_other_client() {
+        let mut range = lease_range();
+        let policy = LeasePolicy::default();
+        let request = Packet::new_request(DHCPMessageType::DHCPREQUEST);
+
+        let ip = range.get_available_ip(request.xid, DEFAULT_LEASE_TIME).unwrap();
+        assert!(range.reserve_ip(&request, ip, &policy).is_ok());
+        assert!(range.renew_ip(&request, ip, &policy).is_ok());
+
+        let mut other = Packet::new_request(DHCPMessageType::DHCPREQUEST);
+        other.add_option(DHCPOption::ClientIdentifier(vec![1, 2, 3]));
+        assert!(matches!(
+            range.renew_ip(&other, ip, &policy),
+            Err(LeaseReserveError::InUse)
+        ));
+        assert!(matches!(
+            range.renew_ip(&request, Ipv4Addr::new(192, 168, 56, 100), &policy),
+            Err(LeaseReserveError::NotRequested)
+        ));
+    }
+
+    #[test]
+    fn release_offer_frees_ip() {
+        let mut range = lease_range();
+        let ip = range.get_available_ip(1, DEFAULT_LEASE_TIME).unwrap();
+        assert_eq!(range.get_available_ip(2, DEFAULT_LEASE_TIME), Some(ip));
+
+        range.release_offer(1);
+        assert!(range.data.contains_key(&ip));
+        range.release_offer(2);
+        assert!(!range.data.contains_key(&ip));
+    }
+
+    #[test]
+    fn init_reboot_on_wrong_network() {
+        let mut range = lease_range();
+        let policy = LeasePolicy::default();
+        let request = Packet::new_request(DHCPMessageType::DHCPREQUEST);
+
+        let ip = range.get_available_ip(request.xid, DEFAULT_LEASE_TIME).unwrap();
+        range.reserve_ip(&request, ip, &policy).unwrap();
+
+        assert!(range.verify_ip(&request, ip, &policy).is_ok());
+        assert!(matches!(
+            range.verify_ip(&request, Ipv4Addr::new(10, 0, 0, 5), &policy),
+            Err(LeaseReserveError::WrongNetwork)
+        ));
+    }
+
+    #[test]
+    fn select_range_by_giaddr() {
+        let mut ranges = vec![
+            lease_range(),
+            LeaseRange::new(
+                Ipv4Addr::new(10, 0, 1, 10),
+                Ipv4Addr::new(10, 0, 1, 200),
+                Ipv4Addr::new(192, 168, 56, 1),
+                Ipv4Addr::new(255, 255, 255, 0),
+            ),
+        ];
+
+        let local = LeaseRange::select(&mut ranges, Ipv4Addr::UNSPECIFIED).unwrap();
+        assert_eq!(local.start_lease, Ipv4Addr::new(192, 168, 56, 3));
+        let relayed = LeaseRange::select(&mut ranges, Ipv4Addr::new(10, 0, 1, 1)).unwrap();
+        assert_eq!(relayed.start_lease, Ipv4Addr::new(10, 0, 1, 10));
+        assert!(LeaseRange::select(&mut ranges, Ipv4Addr::new(10, 0, 2, 1)).is_none());
+    }
+
+    #[test]
+    fn lease_events() {
+        let mut range = lease_range();
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        range.lease_events = Some(sender);
+        let policy = LeasePolicy::default();
+        let request = Packet::new_request(DHCPMessageType::DHCPREQUEST);
+        let name = |hostname: &str| LeaseName {
+            hostname: hostname.into(),
+            fqdn: hostname.into(),
+            dhcid_identifier: vec![0, 0],
+        };
+
+        let ip = range.get_available_ip(request.xid, DEFAULT_LEASE_TIME).unwrap();
+        range.reserve_ip(&request, ip, &policy).unwrap();
+        range.set_hostname(ip, Some(name("laptop")));
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(LeaseEvent::Bound { ip, name: name("laptop") })
+        );
+
+        // dezelfde naam bij een renew
+        range.set_hostname(ip, Some(name("laptop")));
+        assert!(receiver.try_recv().is_err());
+
+        range.set_hostname(ip, Some(name("desktop")));
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(LeaseEvent::Released { ip, name: name("laptop") })
+        );
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(LeaseEvent::Bound { ip, name: name("desktop") })
+        );
+
+        range.release_ip(&request, ip).unwrap();
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(LeaseEvent::Released { ip, name: name("desktop") })
+        );
+        assert!(!range.data.contains_key(&ip));
+    }
+
+    #[test]
+    fn expire_leases() {
+        let mut range = lease_range();
+        let policy = LeasePolicy::default();
+        let request = Packet::new_request(DHCPMessageType::DHCPREQUEST);
+        let ip = range
+            .get_available_ip(request.xid, LeaseTime::Finite(Duration::from_secs(60)))
+            .unwrap();
+        range.reserve_ip(&request, ip, &policy).unwrap();
+
+        range.expire(Utc::now());
+        assert!(range.data.contains_key(&ip));
+        assert_eq!(range.expire(Utc::now() + chrono::Duration::seconds(61)), [ip]);
+        assert!(!range.data.contains_key(&ip));
+    }
 }
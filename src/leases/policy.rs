@@ -0,0 +1,130 @@
+use std::time::Duration;
+
+use crate::{config::LeaseTimeConfig, packet::LeaseTime};
+
+use super::DEFAULT_LEASE_TIME;
+
+/// Bepaalt hoe lang een lease mag duren.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LeasePolicy {
+    /// leasetijd als de client niets vraagt
+    pub default: LeaseTime,
+    pub min: LeaseTime,
+    pub max: LeaseTime,
+}
+
+impl Default for LeasePolicy {
+    fn default() -> Self {
+        LeasePolicy {
+            default: DEFAULT_LEASE_TIME,
+            min: LeaseTime::Finite(Duration::ZERO),
+            max: LeaseTime::Infinite,
+        }
+    }
+}
+
+impl LeasePolicy {
+    /// Neemt de ingevulde velden uit de config over, de rest blijft hetzelfde.
+    pub fn merge(&self, config: &LeaseTimeConfig) -> Self {
+        LeasePolicy {
+            default: config.default_lease_time.map_or(self.default, LeaseTime::from),
+            min: config.min_lease_time.map_or(self.min, LeaseTime::from),
+            max: config.max_lease_time.map_or(self.max, LeaseTime::from),
+        }
+    }
+
+    /// Geeft de leasetijd die de client krijgt. Als de client zelf een tijd
+    /// vraagt (option 51) wordt die gerespecteerd binnen `min` en `max`.
+    /// Met `min` boven `max` (zie [`LeasePolicy::check`]) wint `max`.
+    pub fn lease_time(&self, requested: Option<LeaseTime>) -> LeaseTime {
+        requested.unwrap_or(self.default).max(self.min).min(self.max)
+    }
+
+    /// Controleert de policy die ontstaat door `scopes` na elkaar over te
+    /// nemen: `min` mag niet boven `max` liggen en een ingevulde `default`
+    /// moet ertussen liggen.
+    pub fn check(scopes: &[&LeaseTimeConfig]) -> Result<(), String> {
+        let policy = scopes
+            .iter()
+            .fold(LeasePolicy::default(), |policy, scope| policy.merge(scope));
+        if policy.min > policy.max {
+            return Err(format!(
+                "min lease time {:?} is above max lease time {:?}",
+                policy.min, policy.max
+            ));
+        }
+        let default_set = scopes.iter().any(|scope| scope.default_lease_time.is_some());
+        if default_set && !(policy.min..=policy.max).contains(&policy.default) {
+            return Err(format!(
+                "default lease time {:?} is not between {:?} and {:?}",
+                policy.default, policy.min, policy.max
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use crate::{config::LeaseTimeConfig, packet::LeaseTime};
+
+    use super::LeasePolicy;
+
+    fn secs(secs: u64) -> LeaseTime {
+        LeaseTime::Finite(Duration::from_secs(secs))
+    }
+
+    #[test]
+    fn requested_time_is_bounded() {
+        let policy = LeasePolicy {
+            default: secs(3600),
+            min: secs(600),
+            max: secs(7200),
+        };
+
+        assert_eq!(policy.lease_time(None), secs(3600));
+        assert_eq!(policy.lease_time(Some(secs(1200))), secs(1200));
+        assert_eq!(policy.lease_time(Some(secs(60))), secs(600));
+        assert_eq!(policy.lease_time(Some(LeaseTime::Infinite)), secs(7200));
+    }
+
+    #[test]
+    fn min_above_max() {
+        let subnet = LeaseTimeConfig {
+            default_lease_time: None,
+            min_lease_time: None,
+            max_lease_time: Some(600),
+        };
+        let host = LeaseTimeConfig {
+            min_lease_time: Some(3600),
+            ..LeaseTimeConfig::default()
+        };
+        assert!(LeasePolicy::check(&[&subnet]).is_ok());
+        assert!(LeasePolicy::check(&[&subnet, &host]).is_err());
+
+        // de policy mag niet panieken, ook niet als ze niet gecontroleerd is
+        let policy = LeasePolicy::default().merge(&subnet).merge(&host);
+        assert_eq!(policy.lease_time(None), secs(600));
+
+        let default = LeaseTimeConfig {
+            default_lease_time: Some(7200),
+            ..LeaseTimeConfig::default()
+        };
+        assert!(LeasePolicy::check(&[&subnet, &default]).is_err());
+    }
+
+    #[test]
+    fn merge_keeps_unset_fields() {
+        let policy = LeasePolicy::default().merge(&LeaseTimeConfig {
+            default_lease_time: Some(600),
+            min_lease_time: None,
+            max_lease_time: Some(u32::MAX),
+        });
+
+        assert_eq!(policy.default, secs(600));
+        assert_eq!(policy.min, LeasePolicy::default().min);
+        assert_eq!(policy.max, LeaseTime::Infinite);
+    }
+}
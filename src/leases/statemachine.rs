@@ -1,11 +1,43 @@
-use std::{collections::HashSet, hash::Hash};
+use std::{collections::HashSet, hash::Hash, net::Ipv4Addr};
 
 use crate::packet::{DHCPOption, LeaseTime};
 use chrono::{DateTime, Utc};
 
-use super::DEFAULT_LEASE_TIME;
+use super::{hostname::LeaseName, LeaseReserveError, DEFAULT_LEASE_TIME};
 
+/// Veranderingen aan leases waar andere delen van de server op reageren,
+/// zoals de DNS updates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LeaseEvent {
+    /// Een lease heeft een (nieuwe) naam gekregen
+    Bound { ip: Ipv4Addr, name: LeaseName },
+    /// Een lease met een naam is vrijgegeven, verlopen of heeft een andere
+    /// naam gekregen
+    Released { ip: Ipv4Addr, name: LeaseName },
+}
+
+impl LeaseEvent {
+    pub fn ip(&self) -> Ipv4Addr {
+        match self {
+            LeaseEvent::Bound { ip, .. } | LeaseEvent::Released { ip, .. } => *ip,
+        }
+    }
+
+    pub fn name(&self) -> &LeaseName {
+        match self {
+            LeaseEvent::Bound { name, .. } | LeaseEvent::Released { name, .. } => name,
+        }
+    }
 
+    /// Of dit event een vorig event voor hetzelfde ip achterhaald maakt. Enkel
+    /// het verwijderen van een andere naam blijft nodig.
+    pub fn supersedes(&self, previous: &LeaseEvent) -> bool {
+        match previous {
+            LeaseEvent::Bound { ip, .. } => *ip == self.ip(),
+            LeaseEvent::Released { ip, name } => *ip == self.ip() && name == self.name(),
+        }
+    }
+}
 
 #[derive(Debug, Clone)]
 pub enum DHCPStates {
@@ -17,11 +49,12 @@ pub enum DHCPStates {
     ),
     /// Dit ip is in gebruik
     Used {
-        /// IP van
-        client_id: (),
+        /// client identifier (option 61) of anders het hardware adres
+        client_id: Vec<u8>,
         lease_time: LeaseTime,
         start_time: DateTime<Utc>,
         options: HashSet<DHCPOption>,
+        hostname: Option<LeaseName>,
     },
 }
 
@@ -46,14 +79,79 @@ impl Hash for DHCPOffer {
 }
 
 impl DHCPStates {
-    pub fn new_offered(xid: u32) -> Self {
+    pub fn new_offered(xid: u32, lease_time: LeaseTime) -> Self {
         let mut clients = HashSet::new();
         clients.insert(DHCPOffer {
             xid,
-            lease_time: DEFAULT_LEASE_TIME
+            lease_time
         });
         DHCPStates::Offered(clients)
     }
+
+    pub fn new_used(client_id: Vec<u8>, lease_time: LeaseTime, options: HashSet<DHCPOption>) -> Self {
+        DHCPStates::Used {
+            client_id,
+            lease_time,
+            start_time: Utc::now(),
+            options,
+            hostname: None,
+        }
+    }
+
+    /// Verlengt een lease die in gebruik is (RENEWING/REBINDING).
+    /// Enkel de client die de lease heeft mag deze verlengen, de leasetijd
+    /// begint dan opnieuw te lopen.
+    pub fn extend(&mut self, client: &[u8], new_lease_time: LeaseTime) -> Result<(), LeaseReserveError> {
+        match self {
+            DHCPStates::Used {
+                client_id,
+                lease_time,
+                start_time,
+                ..
+            } => {
+                if client_id != client {
+                    return Err(LeaseReserveError::InUse);
+                }
+                *lease_time = new_lease_time;
+                *start_time = Utc::now();
+                Ok(())
+            }
+            DHCPStates::Offered(_) => Err(LeaseReserveError::NotRequested),
+        }
+    }
+
+    /// Geeft de vorige naam terug.
+    pub fn set_hostname(&mut self, new_hostname: Option<LeaseName>) -> Option<LeaseName> {
+        match self {
+            DHCPStates::Used { hostname, .. } => std::mem::replace(hostname, new_hostname),
+            DHCPStates::Offered(_) => None,
+        }
+    }
+
+    pub fn hostname(&self) -> Option<&LeaseName> {
+        match self {
+            DHCPStates::Used { hostname, .. } => hostname.as_ref(),
+            DHCPStates::Offered(_) => None,
+        }
+    }
+
+    /// Een lease is verlopen als de leasetijd voorbij is, oneindige leases
+    /// verlopen nooit.
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        match self {
+            DHCPStates::Used {
+                lease_time: LeaseTime::Finite(duration),
+                start_time,
+                ..
+            } => chrono::Duration::from_std(*duration)
+                .is_ok_and(|duration| *start_time + duration < now),
+            _ => false,
+        }
+    }
+
+    pub fn is_owned_by(&self, client: &[u8]) -> bool {
+        matches!(self, DHCPStates::Used { client_id, .. } if client_id == client)
+    }
 }
 
 impl From<u32> for DHCPOffer {
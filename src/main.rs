@@ -1,58 +1,196 @@
 #![allow(dead_code)]
-use std::net::Ipv4Addr;
+#![allow(clippy::upper_case_acronyms)]
+use std::{net::Ipv4Addr, path::Path, time::Duration};
 
+use config::{Config, CONFIG_PATH};
+use ddns::DnsUpdater;
 use error::DHCPError;
-use leases::LeaseRange;
 use server::{Client, Server};
 use state::DHCPState;
-use tokio::task;
+use tftp::TftpServer;
+use tokio::{sync::mpsc, task};
 
 use crate::{
-    handlers::{on_dhcp_discover, on_dhcp_request},
+    auth::forcerenew::{self, ForcerenewServer},
+    boot::proxy,
+    handlers::{on_dhcp_discover, on_dhcp_release, on_dhcp_request},
     packet::DHCPMessageType,
-    standard::{BROADCAST_ADDR, SERVER_PORT},
+    standard::{BROADCAST_ADDR, PXE_PORT, SERVER_PORT},
 };
 
+mod auth;
+mod boot;
 mod buffer;
+mod classes;
+mod config;
+mod ddns;
 mod error;
 mod handlers;
 mod leases;
+mod options;
 mod packet;
 mod server;
 mod standard;
 mod state;
+mod tftp;
+mod vendor;
+
+/// Hoe vaak verlopen leases opgeruimd worden
+const EXPIRE_INTERVAL: Duration = Duration::from_secs(60);
 
 #[tokio::main]
 async fn main() -> Result<(), error::DHCPError> {
     tracing_subscriber::fmt().init();
 
-    let mut server = Server::start().await?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("options") {
+        return print_options(&args[1..]);
+    }
+    if args.first().map(String::as_str) == Some("forcerenew") {
+        return send_forcerenew(&args[1..]).await;
+    }
+
+    let config = load_config(args.first())?;
+
+    let mut server = Server::start(BROADCAST_ADDR, SERVER_PORT, &config.interfaces).await?;
+    // in ProxyDHCP mode vragen clients het bootbestand op poort 4011
+    let mut boot_server = match config.proxy {
+        Some(_) => Some(Server::start(Ipv4Addr::UNSPECIFIED, PXE_PORT, &[]).await?),
+        None => None,
+    };
+
+    for warning in config.warnings() {
+        tracing::warn!("{warning}");
+    }
+
+    if let Some(tftp) = &config.tftp {
+        task::spawn(TftpServer::bind(tftp).await?.run());
+    }
+
+    let lease_events = match &config.ddns {
+        Some(ddns) => {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            task::spawn(DnsUpdater::new(ddns)?.run(receiver));
+            tracing::info!("Sending DNS updates to {}", ddns.server);
+            Some(sender)
+        }
+        None => None,
+    };
+
+    let server_state = DHCPState::from_config(config, lease_events);
 
-    let lease_range = LeaseRange::new(
-        Ipv4Addr::new(192, 168, 56, 3),
-        Ipv4Addr::new(192, 168, 56, 255),
-        Ipv4Addr::new(192, 168, 56, 1),
-        Ipv4Addr::new(255, 255, 255, 0),
-    );
+    if let Some(socket) = &server_state.config().auth.socket {
+        task::spawn(ForcerenewServer::bind(socket, server_state.clone())?.run());
+    }
 
-    let server_state = DHCPState::from_lease(lease_range);
+    let expire_state = server_state.clone();
+    task::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRE_INTERVAL);
+        loop {
+            interval.tick().await;
+            expire_state.expire_leases(chrono::Utc::now());
+        }
+    });
     tracing::info!("Server started: {}:{}", BROADCAST_ADDR, SERVER_PORT);
 
     loop {
-        let client = server.receive().await?;
+        let client = match &mut boot_server {
+            Some(boot_server) => tokio::select! {
+                client = server.receive() => client?,
+                client = boot_server.receive() => client?,
+            },
+            None => server.receive().await?,
+        };
 
         let state = server_state.clone();
-        let _ = task::spawn(handle_request(client, state));
+        task::spawn(handle_request(client, state));
+    }
+}
+
+fn load_config(path: Option<&String>) -> Result<Config, DHCPError> {
+    let config_path = path.map_or(CONFIG_PATH, String::as_str);
+    if Path::new(config_path).exists() {
+        Config::load(config_path)
+    } else {
+        tracing::warn!("No config found at {config_path}, using defaults");
+        Ok(Config::default())
+    }
+}
+
+/// `dhcp options <mac> <ip> [config]`: toont de opties die een client in
+/// het netwerk van `ip` krijgt en uit welke scope elke optie komt.
+fn print_options(args: &[String]) -> Result<(), DHCPError> {
+    let usage = || DHCPError::Config("Usage: dhcp options <mac> <ip> [config]".into());
+    let (Some(hardware_address), Some(ip)) = (args.first(), args.get(1)) else {
+        return Err(usage());
+    };
+    let hardware_address = hardware_address.parse().map_err(|_| usage())?;
+    let ip = ip.parse().map_err(|_| usage())?;
+    let config = load_config(args.get(2))?;
+
+    let (pool, options) = options::effective_options(&config, hardware_address, ip)?;
+    println!("{hardware_address} in {pool}");
+    for scoped in options {
+        println!(
+            "{:>3}  {:<40}  {}",
+            scoped.option.get_option_id(),
+            format!("{:?}", scoped.option),
+            scoped.scope
+        );
     }
+    Ok(())
+}
+
+/// `dhcp forcerenew <ip> [config]`: laat de draaiende server een FORCERENEW
+/// naar de client met lease `ip` sturen.
+async fn send_forcerenew(args: &[String]) -> Result<(), DHCPError> {
+    let usage = || DHCPError::Config("Usage: dhcp forcerenew <ip> [config]".into());
+    let ip = args.first().ok_or_else(usage)?.parse().map_err(|_| usage())?;
+    let config = load_config(args.get(1))?;
+    let Some(socket) = &config.auth.socket else {
+        return Err(DHCPError::Config("No [auth] socket configured".into()));
+    };
+
+    forcerenew::request(socket, ip).await?;
+    println!("Sent FORCERENEW to {ip}");
+    Ok(())
 }
 
-async fn handle_request(client: Client, state: DHCPState) -> Result<(), DHCPError> {
+async fn handle_request(mut client: Client, state: DHCPState) -> Result<(), DHCPError> {
+    options::custom::decode_options(&mut client.packet, &state.config().custom_options);
+
+    if let Some(proxy) = &state.config().proxy {
+        let response = match (client.packet.dhcp_message_type, client.port) {
+            (DHCPMessageType::DHCPDISCOVER, SERVER_PORT) => {
+                proxy::on_proxy_discover(client.packet.clone(), proxy)
+            }
+            (DHCPMessageType::DHCPREQUEST, PXE_PORT) => {
+                proxy::on_boot_request(client.packet.clone(), proxy)
+            }
+            _ => None,
+        };
+        if let Some(response) = response {
+            client.send_back(response).await;
+        }
+        return Ok(());
+    }
+
     let response = match client.packet.dhcp_message_type {
-        DHCPMessageType::DHCPDISCOVER => on_dhcp_discover(client.packet.clone(), state)?,
-        DHCPMessageType::DHCPREQUEST => on_dhcp_request(client.packet.clone(), state)?,
-        _ => unimplemented!(),
+        DHCPMessageType::DHCPDISCOVER => {
+            on_dhcp_discover(client.packet.clone(), state, client.interface.as_deref())?
+        }
+        DHCPMessageType::DHCPREQUEST => {
+            on_dhcp_request(client.packet.clone(), state, client.interface.as_deref())?
+        }
+        DHCPMessageType::DHCPRELEASE => on_dhcp_release(client.packet.clone(), state)?,
+        message_type => {
+            tracing::debug!("Ignoring {message_type:?}");
+            None
+        }
     };
 
-    client.send_back(response).await;
+    if let Some(response) = response {
+        client.send_back(response).await;
+    }
     Ok(())
 }
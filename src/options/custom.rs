@@ -0,0 +1,278 @@
+//! Eigen opties uit `[[custom_option]]`: waarden uit de config omzetten naar
+//! een [`DHCPOption::Custom`] en opties van clients lezen met hun type.
+
+use std::net::Ipv4Addr;
+
+use crate::{
+    config::CustomOptionConfig,
+    packet::{
+        custom::{CustomOption, OptionType, OptionValue},
+        dns, DHCPOption, Packet,
+    },
+    vendor,
+};
+
+/// Controleert een definitie tegenover de andere definities.
+pub fn validate_definition(
+    definition: &CustomOptionConfig,
+    definitions: &[CustomOptionConfig],
+) -> Result<(), String> {
+    if DHCPOption::has_variant(definition.code) {
+        return Err(format!("option {} is already supported", definition.code));
+    }
+    let same_code = definitions
+        .iter()
+        .filter(|other| other.code == definition.code || other.name == definition.name)
+        .count();
+    if same_code > 1 {
+        return Err(format!(
+            "code {} or name is used more than once",
+            definition.code
+        ));
+    }
+    if let OptionType::Record(fields) = &definition.kind {
+        if fields.is_empty() {
+            return Err("a record needs at least one field".into());
+        }
+        if fields[..fields.len() - 1]
+            .iter()
+            .any(|field| field.fixed_len().is_none())
+        {
+            return Err("only the last field of a record can have a variable length".into());
+        }
+    }
+    Ok(())
+}
+
+/// Maakt de optie met naam `name` met een waarde uit de config.
+pub fn custom_option(
+    definitions: &[CustomOptionConfig],
+    name: &str,
+    value: &toml::Value,
+) -> Result<DHCPOption, String> {
+    let definition = definitions
+        .iter()
+        .find(|definition| definition.name == name)
+        .ok_or_else(|| format!("no [[custom_option]] named {name}"))?;
+    Ok(DHCPOption::Custom(CustomOption {
+        code: definition.code,
+        name: definition.name.clone(),
+        value: to_value(&definition.kind, value)?,
+    }))
+}
+
+fn to_value(kind: &OptionType, value: &toml::Value) -> Result<OptionValue, String> {
+    let ip = |value: &toml::Value| {
+        value
+            .as_str()
+            .and_then(|ip| ip.parse::<Ipv4Addr>().ok())
+            .ok_or_else(|| format!("expected an ip address, got {value}"))
+    };
+    let int = |max: u32| {
+        value
+            .as_integer()
+            .and_then(|int| u32::try_from(int).ok())
+            .filter(|int| *int <= max)
+            .ok_or_else(|| format!("expected an integer up to {max}, got {value}"))
+    };
+    let string = || {
+        value
+            .as_str()
+            .ok_or_else(|| format!("expected a string, got {value}"))
+    };
+    let list = || {
+        value
+            .as_array()
+            .ok_or_else(|| format!("expected a list, got {value}"))
+    };
+
+    let value = match kind {
+        OptionType::Ip => OptionValue::Ip(ip(value)?),
+        OptionType::IpList => {
+            OptionValue::IpList(list()?.iter().map(ip).collect::<Result<_, _>>()?)
+        }
+        OptionType::U8 => OptionValue::U8(int(u8::MAX as u32)? as u8),
+        OptionType::U16 => OptionValue::U16(int(u16::MAX as u32)? as u16),
+        OptionType::U32 => OptionValue::U32(int(u32::MAX)?),
+        OptionType::Bool => OptionValue::Bool(
+            value
+                .as_bool()
+                .ok_or_else(|| format!("expected a boolean, got {value}"))?,
+        ),
+        OptionType::String => OptionValue::String(string()?.to_string()),
+        OptionType::Hex => OptionValue::Hex(vendor::parse_hex(string()?)?),
+        OptionType::DomainList => {
+            let names = list()?
+                .iter()
+                .map(|name| {
+                    let name = name
+                        .as_str()
+                        .ok_or_else(|| format!("expected a domain name, got {name}"))?;
+                    dns::validate_name(name)
+                        .map_err(|error| format!("invalid domain {name}: {error:?}"))?;
+                    Ok(name.to_string())
+                })
+                .collect::<Result<_, String>>()?;
+            OptionValue::DomainList(names)
+        }
+        OptionType::Record(fields) => {
+            let values = list()?;
+            if values.len() != fields.len() {
+                return Err(format!("expected {} fields, got {value}", fields.len()));
+            }
+            OptionValue::Record(
+                fields
+                    .iter()
+                    .zip(values)
+                    .map(|(field, value)| to_value(field, value))
+                    .collect::<Result<_, _>>()?,
+            )
+        }
+    };
+    Ok(value)
+}
+
+/// Leest de opties van de client waarvoor een `[[custom_option]]` bestaat.
+/// Een optie die niet bij haar type past blijft ruwe data.
+pub fn decode_options(packet: &mut Packet, definitions: &[CustomOptionConfig]) {
+    for definition in definitions {
+        let Some(DHCPOption::Unimplemented { data, .. }) = packet.get_option(definition.code)
+        else {
+            continue;
+        };
+        match definition.kind.decode(definition.code, data) {
+            Ok(value) => {
+                packet.override_option(DHCPOption::Custom(CustomOption {
+                    code: definition.code,
+                    name: definition.name.clone(),
+                    value,
+                }));
+            }
+            Err(error) => println!("Could not read custom option {}: {error}", definition.name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use crate::{
+        config::Config,
+        packet::{
+            custom::{OptionType, OptionValue},
+            DHCPMessageType, DHCPOption, Packet,
+        },
+    };
+
+    use super::{custom_option, decode_options};
+
+    fn config(custom: &str) -> Result<Config, String> {
+        let config: Config = toml::from_str(custom).map_err(|error| error.to_string())?;
+        let config = Config {
+            custom_options: config.custom_options,
+            options: config.options,
+            ..Config::default()
+        };
+        config.validate().map_err(|error| error.to_string())?;
+        Ok(config)
+    }
+
+    #[test]
+    fn config_values() {
+        let config = config(
+            r#"
+            [[custom_option]]
+            code = 224
+            name = "provisioning"
+            type = ["u16", "ip", "string"]
+
+            [[custom_option]]
+            code = 225
+            name = "search"
+            type = "domain-list"
+
+            [options.custom]
+            provisioning = [8080, "10.0.0.1", "pbx"]
+            search = ["example.com"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.custom_options[0].kind.fixed_len(), None);
+
+        let options = config.options.to_options(&config.custom_options);
+        let Some(DHCPOption::Custom(provisioning)) =
+            options.iter().find(|option| option.get_option_id() == 224)
+        else {
+            panic!("No option 224");
+        };
+        assert_eq!(
+            provisioning.value,
+            OptionValue::Record(vec![
+                OptionValue::U16(8080),
+                OptionValue::Ip(Ipv4Addr::new(10, 0, 0, 1)),
+                OptionValue::String("pbx".into()),
+            ])
+        );
+
+        let wrong = toml::Value::Integer(70000);
+        assert!(custom_option(&config.custom_options, "provisioning", &wrong).is_err());
+        assert!(custom_option(&config.custom_options, "unknown", &wrong).is_err());
+    }
+
+    #[test]
+    fn invalid_definitions() {
+        let definition = |code: u8, kind: &str| {
+            format!("[[custom_option]]\ncode = {code}\nname = \"option\"\ntype = {kind}")
+        };
+        assert!(config(&definition(224, "\"u32\"")).is_ok());
+        // option 3 heeft een eigen variant
+        assert!(config(&definition(3, "\"ip-list\"")).is_err());
+        assert!(config(&definition(224, "\"float\"")).is_err());
+        assert!(config(&definition(224, "[\"string\", \"u8\"]")).is_err());
+        assert!(config(&definition(224, "[]")).is_err());
+        // waarde van het verkeerde type
+        let value = format!(
+            "{}\n[options.custom]\noption = true",
+            definition(224, "\"ip\"")
+        );
+        assert!(config(&value).is_err());
+    }
+
+    #[test]
+    fn decode_client_options() {
+        let config = config(
+            r#"
+            [[custom_option]]
+            code = 224
+            name = "site"
+            type = "u16"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.custom_options[0].kind, OptionType::U16);
+
+        let mut packet = Packet::new_request(DHCPMessageType::DHCPDISCOVER);
+        packet.add_option(DHCPOption::Unimplemented {
+            option_code: 224,
+            data: vec![0x01, 0x02],
+        });
+        decode_options(&mut packet, &config.custom_options);
+        assert!(matches!(
+            packet.get_option(224),
+            Some(DHCPOption::Custom(option)) if option.value == OptionValue::U16(0x0102)
+        ));
+
+        // past niet bij het type
+        let mut packet = Packet::new_request(DHCPMessageType::DHCPDISCOVER);
+        packet.add_option(DHCPOption::Unimplemented {
+            option_code: 224,
+            data: vec![1],
+        });
+        decode_options(&mut packet, &config.custom_options);
+        assert!(matches!(
+            packet.get_option(224),
+            Some(DHCPOption::Unimplemented { .. })
+        ));
+    }
+}
@@ -0,0 +1,270 @@
+//! Opties kunnen in elke scope van de config gezet worden. Per optie gaat de
+//! meest specifieke scope voor, in deze volgorde:
+//!
+//! `[options]` → `[[shared_network]]` → `[[network]]` → pool (`[[subnet]]`)
+//! → `[[class]]` → `[[host]]`
+//!
+//! Een scope vervangt een optie in zijn geheel, routers in een pool komen
+//! dus in de plaats van die van het netwerk. Bij meerdere classes gaat de
+//! laatste class in de config voor.
+
+use std::{fmt::Display, net::Ipv4Addr};
+
+pub mod custom;
+
+use crate::{
+    classes::{self, ClassContext},
+    config::{
+        Cidr, ClassConfig, Config, CustomOptionConfig, HardwareAddr, HostConfig, OptionsConfig,
+        SubnetConfig,
+    },
+    error::DHCPError,
+    leases,
+    packet::{DHCPMessageType, DHCPOption, Packet},
+};
+
+/// Waar een optie vandaan komt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Scope {
+    Global,
+    SharedNetwork(String),
+    Network(Cidr),
+    /// Een `[[subnet]]`, aangeduid met het begin van de range
+    Pool(Ipv4Addr),
+    Class(String),
+    Host(HardwareAddr),
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Scope::Global => write!(f, "global"),
+            Scope::SharedNetwork(name) => write!(f, "shared network {name}"),
+            Scope::Network(subnet) => write!(f, "network {subnet}"),
+            Scope::Pool(start) => write!(f, "pool {start}"),
+            Scope::Class(name) => write!(f, "class {name}"),
+            Scope::Host(hardware_address) => write!(f, "host {hardware_address}"),
+        }
+    }
+}
+
+/// Een optie met de scope waar ze uit komt.
+#[derive(Debug, Clone)]
+pub struct ScopedOption {
+    pub option: DHCPOption,
+    pub scope: Scope,
+}
+
+/// De scopes die voor alle clients in een pool gelden, van algemeen naar
+/// specifiek.
+pub fn pool_scopes<'a>(
+    config: &'a Config,
+    pool: &'a SubnetConfig,
+) -> Vec<(Scope, &'a OptionsConfig)> {
+    let mut scopes = vec![(Scope::Global, &config.options)];
+    if let Some(network) = config.network(pool.range_start) {
+        let shared_network = network
+            .shared_network
+            .as_deref()
+            .and_then(|name| config.shared_network(name));
+        if let Some(shared_network) = shared_network {
+            scopes.push((
+                Scope::SharedNetwork(shared_network.name.clone()),
+                &shared_network.options,
+            ));
+        }
+        scopes.push((Scope::Network(network.subnet), &network.options));
+    }
+    scopes.push((Scope::Pool(pool.range_start), &pool.options));
+    scopes
+}
+
+/// De scopes van de client zelf: zijn classes en daarna zijn `[[host]]`.
+pub fn client_scopes<'a>(
+    classes: &[&'a ClassConfig],
+    host: Option<&'a HostConfig>,
+) -> Vec<(Scope, &'a OptionsConfig)> {
+    let mut scopes: Vec<_> = classes
+        .iter()
+        .map(|class| (Scope::Class(class.name.clone()), &class.options))
+        .collect();
+    if let Some(host) = host {
+        scopes.push((Scope::Host(host.hardware_address), &host.options));
+    }
+    scopes
+}
+
+/// Voegt de opties van `scopes` samen, een latere scope vervangt een optie
+/// met dezelfde code. Het resultaat is gesorteerd op code.
+pub fn merge(
+    scopes: &[(Scope, &OptionsConfig)],
+    custom_options: &[CustomOptionConfig],
+) -> Vec<ScopedOption> {
+    let mut merged: Vec<ScopedOption> = Vec::new();
+    for (scope, options) in scopes {
+        for option in options.to_options(custom_options) {
+            let code = option.get_option_id();
+            merged.retain(|scoped| scoped.option.get_option_id() != code);
+            merged.push(ScopedOption {
+                option,
+                scope: scope.clone(),
+            });
+        }
+    }
+    merged.sort_by_key(|scoped| scoped.option.get_option_id());
+    merged
+}
+
+/// De domain name van de meest specifieke scope die er een heeft.
+pub fn domain_name(scopes: &[(Scope, &OptionsConfig)]) -> Option<String> {
+    scopes
+        .iter()
+        .rev()
+        .find_map(|(_, options)| options.domain_name.clone())
+}
+
+/// De pool waar een client met `ip` (of een relay agent in dat netwerk)
+/// uitkomt: de pool die `ip` uitdeelt of anders de eerste pool in het
+/// netwerk.
+fn pool_for(config: &Config, ip: Ipv4Addr) -> Option<&SubnetConfig> {
+    let in_network =
+        |pool: &&SubnetConfig| leases::in_network(ip, pool.range_start, pool.subnet_mask);
+    config
+        .subnets
+        .iter()
+        .filter(in_network)
+        .find(|pool| leases::in_pool(ip, pool.range_start, pool.range_end))
+        .or_else(|| config.subnets.iter().find(in_network))
+}
+
+/// De opties die een client met `hardware_address` in het netwerk van `ip`
+/// krijgt, met de scope van elke optie. Classes worden bepaald met een
+/// DISCOVER die enkel het hardware adres bevat, voorwaarden op andere
+/// velden kloppen hier dus niet.
+pub fn effective_options(
+    config: &Config,
+    hardware_address: HardwareAddr,
+    ip: Ipv4Addr,
+) -> Result<(Scope, Vec<ScopedOption>), DHCPError> {
+    let pool =
+        pool_for(config, ip).ok_or_else(|| DHCPError::Config(format!("No [[subnet]] for {ip}")))?;
+    let mut packet = Packet::new_request(DHCPMessageType::DHCPDISCOVER);
+    packet.set_chaddr(&hardware_address.0);
+    let classes = classes::classify(&config.classes, &ClassContext::new(&packet, None));
+
+    let mut scopes = pool_scopes(config, pool);
+    scopes.extend(client_scopes(&classes, config.host(&hardware_address.0)));
+    Ok((
+        Scope::Pool(pool.range_start),
+        merge(&scopes, &config.custom_options),
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use crate::{
+        config::{Config, HardwareAddr},
+        packet::DHCPOption,
+    };
+
+    use super::{effective_options, Scope};
+
+    fn config() -> Config {
+        toml::from_str(
+            r#"
+            [options]
+            domain_name = "example.com"
+            domain_search = ["example.com"]
+            routers = ["10.0.0.1"]
+
+            [[shared_network]]
+            name = "office"
+            domain_name = "office.example.com"
+
+            [[network]]
+            subnet = "192.168.56.0/24"
+            shared_network = "office"
+            routers = ["192.168.56.1"]
+
+            [[subnet]]
+            range_start = "192.168.56.10"
+            range_end = "192.168.56.100"
+            server_addr = "192.168.56.1"
+            subnet_mask = "255.255.255.0"
+
+            [[subnet]]
+            range_start = "192.168.56.200"
+            range_end = "192.168.56.250"
+            server_addr = "192.168.56.1"
+            subnet_mask = "255.255.255.0"
+            routers = ["192.168.56.254"]
+
+            [[class]]
+            name = "printers"
+            domain_search = ["printers.example.com"]
+            [class.match]
+            hardware_prefix = "00:1b:a9"
+
+            [[host]]
+            hardware_address = "00:1b:a9:00:00:01"
+            routers = ["192.168.56.2"]
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn scope_of(options: &[super::ScopedOption], code: u8) -> Scope {
+        options
+            .iter()
+            .find(|scoped| scoped.option.get_option_id() == code)
+            .map(|scoped| scoped.scope.clone())
+            .unwrap()
+    }
+
+    #[test]
+    fn inheritance() {
+        let config = config();
+        assert!(config.validate().is_ok());
+        let network = Scope::Network("192.168.56.0/24".parse().unwrap());
+
+        let client: HardwareAddr = "de:ad:c0:de:ca:fe".parse().unwrap();
+        let (pool, options) =
+            effective_options(&config, client, Ipv4Addr::new(192, 168, 56, 0)).unwrap();
+        assert_eq!(pool, Scope::Pool(Ipv4Addr::new(192, 168, 56, 10)));
+        assert_eq!(scope_of(&options, 3), network);
+        assert_eq!(
+            scope_of(&options, 15),
+            Scope::SharedNetwork("office".into())
+        );
+        assert_eq!(scope_of(&options, 119), Scope::Global);
+        assert!(options.iter().any(|scoped| matches!(
+            &scoped.option,
+            DHCPOption::DomainName(name) if name == "office.example.com"
+        )));
+
+        // de tweede pool heeft eigen routers
+        let (_, options) =
+            effective_options(&config, client, Ipv4Addr::new(192, 168, 56, 210)).unwrap();
+        assert_eq!(
+            scope_of(&options, 3),
+            Scope::Pool(Ipv4Addr::new(192, 168, 56, 200))
+        );
+
+        let printer: HardwareAddr = "00:1b:a9:00:00:01".parse().unwrap();
+        let (_, options) =
+            effective_options(&config, printer, Ipv4Addr::new(192, 168, 56, 0)).unwrap();
+        assert_eq!(scope_of(&options, 3), Scope::Host(printer));
+        assert_eq!(scope_of(&options, 119), Scope::Class("printers".into()));
+
+        assert!(effective_options(&config, client, Ipv4Addr::new(10, 0, 0, 1)).is_err());
+    }
+
+    #[test]
+    fn unknown_shared_network() {
+        let mut config = config();
+        config.networks[0].shared_network = Some("lab".into());
+        assert!(config.validate().is_err());
+    }
+}
@@ -10,6 +10,13 @@ mod time;
 use byteorder::NetworkEndian;
 use byteorder::ReadBytesExt;
 pub use option::DHCPMessageType;
+pub use option::auth;
+pub use option::custom;
+pub use option::dns;
+pub use option::ClasslessRoute;
+pub use option::ClientFqdn;
+pub use option::SipServers;
+pub use option::VendorInfo;
 pub use option::DHCPOption;
 pub use time::LeaseTime;
 pub use option::OptionParseErr;
@@ -32,15 +39,17 @@ pub struct Packet {
     /// als 1e bit 1 is moet dit terug gestuurd worden als broadcast bericht
     /// Als deze 0 is dan moet dit bericht terug gestuurd worden naar het aders in yiaddr
     flags: u16,
-    ciaddr: Ipv4Addr,
+    /// Client IP address; enkel ingevuld als de client al een ip heeft
+    /// (BOUND, RENEW of REBINDING state).
+    pub ciaddr: Ipv4Addr,
 
     /// 'your' (client) IP address.
     pub yiaddr: Ipv4Addr,
     /// IP address of next server to use in bootstrap;
     /// returned in DHCPOFFER, DHCPACK by server.
-    siaddr: Ipv4Addr,
+    pub siaddr: Ipv4Addr,
     /// Relay agent IP address, used in booting via a relay agent.
-    giaddr: Ipv4Addr,
+    pub giaddr: Ipv4Addr,
     /// Client hardware address
     chaddr: [u8; 16],
     /// Optional server host name, null terminated string.
@@ -52,6 +61,9 @@ pub struct Packet {
 
     /// Alle DHCP berichten zouden deze option moeten hebben
     pub dhcp_message_type: DHCPMessageType,
+    /// Van een ontvangen pakket met een MAC in option 90: de bytes waarover
+    /// de MAC berekend is, zie [`auth::mac_input`]
+    auth_input: Option<Vec<u8>>,
 }
 
 impl Packet {
@@ -73,9 +85,23 @@ impl Packet {
             file: [0; 128],
             options: HashSet::new(),
             dhcp_message_type,
+            auth_input: None,
         }
     }
 
+    /// Een FORCERENEW (RFC 3203) die unicast naar de client op `ciaddr`
+    /// gaat.
+    pub fn new_forcerenew(ciaddr: Ipv4Addr, chaddr: &[u8], xid: u32) -> Self {
+        let mut packet = Packet::new_request(DHCPMessageType::DHCPFORCERENEW);
+        packet.op = MessageType::BOOTREPLY;
+        packet.flags = 0;
+        packet.secs = 0;
+        packet.xid = xid;
+        packet.ciaddr = ciaddr;
+        packet.set_chaddr(chaddr);
+        packet
+    }
+
     /// voegt een optie toe, als deze nog niet bestaat
     pub fn add_option(&mut self, option: DHCPOption) -> bool {
         self.options.insert(option)
@@ -90,6 +116,73 @@ impl Packet {
         self.options.clone()
     }
 
+    /// Zoekt een optie op basis van de option code
+    pub fn get_option(&self, option_code: u8) -> Option<&DHCPOption> {
+        self.options
+            .get(&DHCPOption::Unimplemented { option_code, data: Vec::new() })
+    }
+
+    pub fn authentication(&self) -> Option<&auth::Authentication> {
+        match self.get_option(90) {
+            Some(DHCPOption::Authentication(auth)) => Some(auth),
+            _ => None,
+        }
+    }
+
+    /// De bytes waarover de MAC van option 90 berekend is, enkel voor
+    /// ontvangen pakketten
+    pub fn auth_input(&self) -> Option<&[u8]> {
+        self.auth_input.as_deref()
+    }
+
+    /// Een sub-optie van de relay agent information (82)
+    pub fn relay_suboption(&self, code: u8) -> Option<&[u8]> {
+        match self.get_option(82) {
+            Some(DHCPOption::RelayAgentInfo(suboptions)) => suboptions
+                .iter()
+                .find(|(suboption, _)| *suboption == code)
+                .map(|(_, value)| value.as_slice()),
+            _ => None,
+        }
+    }
+
+    pub fn htype(&self) -> u8 {
+        self.htype
+    }
+
+    /// Hardware adres van de client, `hlen` bytes lang
+    pub fn chaddr(&self) -> &[u8] {
+        &self.chaddr[..(self.hlen as usize).min(self.chaddr.len())]
+    }
+
+    /// Zet het hardware adres, hoogstens 16 bytes
+    pub fn set_chaddr(&mut self, chaddr: &[u8]) {
+        let len = chaddr.len().min(self.chaddr.len());
+        self.chaddr = [0; 16];
+        self.chaddr[..len].copy_from_slice(&chaddr[..len]);
+        self.hlen = len as u8;
+    }
+
+    /// Server host name, leeg als die niet ingevuld is
+    pub fn sname(&self) -> String {
+        read_cstr(&self.sname)
+    }
+
+    /// Zet de server host name, te lange namen worden afgekapt zodat er
+    /// plaats blijft voor de 0 op het einde.
+    pub fn set_sname(&mut self, sname: &str) {
+        write_cstr(&mut self.sname, sname);
+    }
+
+    /// Boot file name, leeg als die niet ingevuld is
+    pub fn file(&self) -> String {
+        read_cstr(&self.file)
+    }
+
+    pub fn set_file(&mut self, file: &str) {
+        write_cstr(&mut self.file, file);
+    }
+
     pub fn get_requested_ip(&self) -> Option<Ipv4Addr> {
         self.options
             .get(&DHCPOption::RequestedIp(Ipv4Addr::new(0, 0, 0, 0)))
@@ -100,6 +193,36 @@ impl Packet {
             .copied()
     }
 
+    pub fn get_server_id(&self) -> Option<Ipv4Addr> {
+        self.options
+            .get(&DHCPOption::ServerIdentifier(Ipv4Addr::new(0, 0, 0, 0)))
+            .map(|ip| match ip {
+                DHCPOption::ServerIdentifier(ip) => ip,
+                _ => unreachable!(),
+            })
+            .copied()
+    }
+
+    /// Identificeert de client: de client identifier (option 61) als die
+    /// meegestuurd is, anders het hardware adres.
+    pub fn client_id(&self) -> Vec<u8> {
+        match self
+            .options
+            .get(&DHCPOption::ClientIdentifier(Vec::new()))
+        {
+            Some(DHCPOption::ClientIdentifier(id)) => id.clone(),
+            _ => self.chaddr().to_vec(),
+        }
+    }
+
+    /// De opties die de client vraagt (option 55), leeg als de client er geen meestuurt
+    pub fn get_parameter_request(&self) -> Vec<u8> {
+        match self.get_option(55) {
+            Some(DHCPOption::ParameterRequest(requested)) => requested.clone(),
+            _ => Vec::new(),
+        }
+    }
+
     pub fn get_leasetime(&self) -> Option<LeaseTime> {
         self.options
             .get(&DHCPOption::IpLeasetime(LeaseTime::Infinite))
@@ -132,16 +255,30 @@ impl Packet {
         self.options.iter().for_each(|opt| println!("{opt:?}"));
     }
 
+    /// Maakt van een request een antwoord. De relay agent information (82,
+    /// RFC 3046 2.2) en subnet selection (118, RFC 3011 3) worden ongewijzigd
+    /// teruggestuurd, de andere opties vult de server in.
+    #[allow(clippy::wrong_self_convention)]
     pub fn into_response(&mut self, dhcp_message_type: DHCPMessageType) {
         self.op = MessageType::BOOTREPLY;
-        self.options.clear();
+        self.options
+            .retain(|option| matches!(option.get_option_id(), 82 | 118));
+        // deze velden worden door de server ingevuld
+        self.siaddr = Ipv4Addr::UNSPECIFIED;
+        self.sname = [0; 64];
+        self.file = [0; 128];
         self.dhcp_message_type = dhcp_message_type;
+        self.auth_input = None;
     }
 
     pub fn is_broadcast(&self) -> bool {
         (self.flags & (1 << 15)) != 0
     }
 
+    pub fn set_broadcast(&mut self) {
+        self.flags |= 1 << 15;
+    }
+
     pub fn write_to_bytes(&self, buffer: &mut [u8]) -> usize {
         buffer[0] = self.op.into();
         buffer[1] = self.htype;
@@ -164,6 +301,9 @@ impl Packet {
 
         let mut len = 243;
         len += DHCPOption::to_bytes_many(&self.options, &mut buffer[len..]);
+        if let Some(key) = self.authentication().and_then(|auth| auth.signing_key.as_ref()) {
+            auth::sign(&mut buffer[..len], key);
+        }
         len
     }
 }
@@ -172,6 +312,7 @@ impl TryFrom<&[u8]> for Packet {
     type Error = DHCPError;
 
     fn try_from(buffer: &[u8]) -> Result<Self, Self::Error> {
+        let auth_input = auth::mac_input(buffer);
         let mut buffer = Cursor::new(buffer);
 
         let op = MessageType::try_from(buffer.read_u8()?).unwrap();
@@ -238,6 +379,47 @@ impl TryFrom<&[u8]> for Packet {
             file,
             options,
             dhcp_message_type,
+            auth_input,
         })
     }
 }
+
+/// Leest een string die met een 0 eindigt, of het hele veld vult.
+fn read_cstr(field: &[u8]) -> String {
+    let len = field.iter().position(|b| *b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..len]).into_owned()
+}
+
+fn write_cstr(field: &mut [u8], value: &str) {
+    let bytes = value.as_bytes();
+    let len = bytes.len().min(field.len() - 1);
+    field.fill(0);
+    field[..len].copy_from_slice(&bytes[..len]);
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::Ipv4Addr;
+
+    use super::{DHCPMessageType, Packet};
+
+    #[test]
+    fn boot_fields() {
+        let mut packet = Packet::new_request(DHCPMessageType::DHCPDISCOVER);
+        packet.into_response(DHCPMessageType::DHCPOFFER);
+        packet.siaddr = Ipv4Addr::new(192, 168, 56, 1);
+        packet.set_sname("tftp.test");
+        packet.set_file(&"a".repeat(200));
+
+        let mut buffer = [0; 1024];
+        let len = packet.write_to_bytes(&mut buffer);
+        let parsed = Packet::try_from(&buffer[..len]).unwrap();
+        assert_eq!(parsed.siaddr, Ipv4Addr::new(192, 168, 56, 1));
+        assert_eq!(parsed.sname(), "tftp.test");
+        assert_eq!(parsed.file(), "a".repeat(127));
+
+        let mut parsed = parsed;
+        parsed.into_response(DHCPMessageType::DHCPACK);
+        assert_eq!(parsed.file(), "");
+    }
+}
@@ -0,0 +1,199 @@
+//! De authentication optie (90, RFC 3118) en de HMAC-MD5 over een pakket.
+
+use std::ops::Range;
+
+use hmac::{digest::KeyInit, Hmac, Mac};
+use md5::Md5;
+
+use super::{option_data, OptionParseErr, END_OPTION, PAD_OPTION};
+
+/// Delayed authentication (RFC 3118 5)
+pub const PROTOCOL_DELAYED: u8 = 1;
+/// Reconfigure key voor FORCERENEW (RFC 6704, zoals RFC 3315 21.5)
+pub const PROTOCOL_RECONFIGURE_KEY: u8 = 3;
+pub const ALGORITHM_HMAC_MD5: u8 = 1;
+/// De replay detection is een waarde die bij elk bericht stijgt
+pub const RDM_MONOTONIC: u8 = 0;
+/// Reconfigure key: de informatie is de sleutel zelf, in een ACK
+pub const RECONFIGURE_KEY_VALUE: u8 = 1;
+/// Reconfigure key: de informatie is een HMAC-MD5, in een FORCERENEW
+pub const RECONFIGURE_HMAC_MD5: u8 = 2;
+pub const MAC_LEN: usize = 16;
+
+/// Protocol, algoritme, RDM en replay detection
+const HEADER_LEN: usize = 11;
+
+/// Option 90. De MAC in `info` wordt pas berekend als het hele pakket
+/// geschreven is, met `signing_key`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Authentication {
+    pub protocol: u8,
+    pub algorithm: u8,
+    pub rdm: u8,
+    pub replay_detection: u64,
+    pub info: Vec<u8>,
+    /// Sleutel voor de MAC bij het schrijven, wordt niet verstuurd
+    pub signing_key: Option<Vec<u8>>,
+}
+
+impl Authentication {
+    /// Delayed authentication met de sleutel `secret_id`, de MAC wordt
+    /// ingevuld bij het schrijven.
+    pub fn delayed(replay_detection: u64, secret_id: u32, key: &[u8]) -> Self {
+        let mut info = secret_id.to_be_bytes().to_vec();
+        info.extend_from_slice(&[0; MAC_LEN]);
+        Authentication {
+            info,
+            signing_key: Some(key.to_vec()),
+            ..Authentication::new(PROTOCOL_DELAYED, replay_detection)
+        }
+    }
+
+    /// Geeft de client een reconfigure key in een ACK.
+    pub fn reconfigure_key(replay_detection: u64, key: &[u8; MAC_LEN]) -> Self {
+        let mut info = vec![RECONFIGURE_KEY_VALUE];
+        info.extend_from_slice(key);
+        Authentication {
+            info,
+            ..Authentication::new(PROTOCOL_RECONFIGURE_KEY, replay_detection)
+        }
+    }
+
+    /// Een FORCERENEW getekend met de reconfigure key van de client.
+    pub fn reconfigure_mac(replay_detection: u64, key: &[u8; MAC_LEN]) -> Self {
+        let mut info = vec![RECONFIGURE_HMAC_MD5];
+        info.extend_from_slice(&[0; MAC_LEN]);
+        Authentication {
+            info,
+            signing_key: Some(key.to_vec()),
+            ..Authentication::new(PROTOCOL_RECONFIGURE_KEY, replay_detection)
+        }
+    }
+
+    fn new(protocol: u8, replay_detection: u64) -> Self {
+        Authentication {
+            protocol,
+            algorithm: ALGORITHM_HMAC_MD5,
+            rdm: RDM_MONOTONIC,
+            replay_detection,
+            info: Vec::new(),
+            signing_key: None,
+        }
+    }
+
+    /// Het id van de gedeelde sleutel bij delayed authentication. Een
+    /// DISCOVER heeft nog geen sleutel en geen informatie.
+    pub fn secret_id(&self) -> Option<u32> {
+        match (self.protocol, self.info.get(..4)) {
+            (PROTOCOL_DELAYED, Some(id)) => Some(u32::from_be_bytes(id.try_into().unwrap())),
+            _ => None,
+        }
+    }
+
+    /// Waar de MAC in `info` staat, als er een is.
+    fn mac_range(protocol: u8, info: &[u8]) -> Option<Range<usize>> {
+        match (protocol, info) {
+            (PROTOCOL_DELAYED, _) if info.len() == 4 + MAC_LEN => Some(4..info.len()),
+            (PROTOCOL_RECONFIGURE_KEY, [RECONFIGURE_HMAC_MD5, mac @ ..])
+                if mac.len() == MAC_LEN =>
+            {
+                Some(1..info.len())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn mac(&self) -> Option<&[u8]> {
+        Authentication::mac_range(self.protocol, &self.info).map(|range| &self.info[range])
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![self.protocol, self.algorithm, self.rdm];
+        data.extend_from_slice(&self.replay_detection.to_be_bytes());
+        data.extend_from_slice(&self.info);
+        data
+    }
+
+    pub fn from_data(data: &[u8]) -> Result<Self, OptionParseErr> {
+        Ok(Authentication {
+            protocol: data[0],
+            algorithm: data[1],
+            rdm: data[2],
+            replay_detection: u64::from_be_bytes(data[3..HEADER_LEN].try_into().unwrap()),
+            info: data[HEADER_LEN..].to_vec(),
+            signing_key: None,
+        })
+    }
+}
+
+/// De bytes van een pakket waarover de MAC berekend wordt, of `None` als
+/// het pakket geen option 90 met een MAC heeft. Zoals RFC 3118 5 vraagt
+/// staan hops, giaddr en de MAC zelf op 0. De relay agent information (82)
+/// valt weg: relay agents voegen die toe na de client en halen ze weg voor
+/// het antwoord bij de client komt.
+pub fn mac_input(packet: &[u8]) -> Option<Vec<u8>> {
+    let mut input = packet.to_vec();
+    let mac = find_mac(&input)?;
+    input[mac].fill(0);
+    input[3] = 0;
+    input[24..28].fill(0);
+
+    let mut index = 240;
+    while let Some(&tag) = input.get(index) {
+        match tag {
+            END_OPTION => break,
+            PAD_OPTION => index += 1,
+            82 => {
+                let len = option_data(&input[index..]).ok()?.len();
+                input.drain(index..(index + 2 + len));
+            }
+            _ => index += 2 + option_data(&input[index..]).ok()?.len(),
+        }
+    }
+    Some(input)
+}
+
+/// Vult de MAC van option 90 in een geschreven pakket in.
+pub fn sign(packet: &mut [u8], key: &[u8]) {
+    let Some(input) = mac_input(packet) else {
+        return;
+    };
+    let mac = hmac_md5(key, &input);
+    let range = find_mac(packet).unwrap();
+    packet[range].copy_from_slice(&mac);
+}
+
+/// Controleert de MAC van een ontvangen pakket, `input` komt van
+/// [`mac_input`].
+pub fn verify(input: &[u8], mac: &[u8], key: &[u8]) -> bool {
+    let mut hmac = <Hmac<Md5> as KeyInit>::new_from_slice(key).expect("HMAC takes any key length");
+    hmac.update(input);
+    hmac.verify_slice(mac).is_ok()
+}
+
+fn hmac_md5(key: &[u8], input: &[u8]) -> [u8; MAC_LEN] {
+    let mut hmac = <Hmac<Md5> as KeyInit>::new_from_slice(key).expect("HMAC takes any key length");
+    hmac.update(input);
+    hmac.finalize().into_bytes().into()
+}
+
+/// De plaats van de MAC van option 90 in een geschreven pakket.
+fn find_mac(packet: &[u8]) -> Option<Range<usize>> {
+    let mut index = 240;
+    while let Some(&tag) = packet.get(index) {
+        match tag {
+            END_OPTION => return None,
+            PAD_OPTION => index += 1,
+            tag => {
+                let data = option_data(&packet[index..]).ok()?;
+                if tag == 90 && data.len() >= HEADER_LEN {
+                    let info = index + 2 + HEADER_LEN;
+                    let mac = Authentication::mac_range(data[0], &data[HEADER_LEN..])?;
+                    return Some((info + mac.start)..(info + mac.end));
+                }
+                index += 2 + data.len();
+            }
+        }
+    }
+    None
+}
@@ -2,12 +2,40 @@ use std::net::Ipv4Addr;
 
 use crate::{buffer::ByteWriter, packet::time::LeaseTime};
 
-use super::{DHCPMessageType, OptionOverload};
+use super::{
+    dns, ClasslessRoute, ClientFqdn, DHCPMessageType, NetBiosNodeType, NetworkInterfaceId,
+    OptionOverload,
+};
 
 pub trait OptionToByte {
     fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize;
 }
 
+/// Schrijft data die langer kan zijn dan 255 bytes als meerdere opties met
+/// dezelfde tag (RFC 3396). Geeft het totaal aantal geschreven bytes terug.
+pub fn write_long_option(tag: u8, data: &[u8], buffer: &mut [u8]) -> usize {
+    let mut index = 0;
+    for chunk in data.chunks(u8::MAX as usize) {
+        buffer[index..].write_tag(tag);
+        buffer[index..].write_len(chunk.len() as u8);
+        buffer.write_slice(index + 2, chunk);
+        index += chunk.len() + 2;
+    }
+    index
+}
+
+/// Schrijft data van een [`OptionToByte`] die langer kan zijn dan 255 bytes
+/// met [`write_long_option`]. Zoals de trait verwacht telt de tag en lengte
+/// van de eerste optie niet mee. Lege data wordt een optie met lengte 0.
+fn write_long_data(tag: u8, data: &[u8], buffer: &mut [u8]) -> usize {
+    if data.is_empty() {
+        buffer.write_tag(tag);
+        buffer.write_len(0);
+        return 0;
+    }
+    write_long_option(tag, data, buffer) - 2
+}
+
 impl OptionToByte for Ipv4Addr {
     fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
         buffer.write_tag(tag);
@@ -20,23 +48,14 @@ impl OptionToByte for Ipv4Addr {
 
 impl OptionToByte for Vec<Ipv4Addr> {
     fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
-        buffer.write_tag(tag);
-        buffer.write_len(self.len() as u8 * 4);
-        self
-            .iter()
-            .enumerate()
-            .for_each(|(i, ipv4)| buffer.write_slice(i * 4 + 2, &ipv4.octets()));
-        self.len() * 4
+        let data: Vec<u8> = self.iter().flat_map(|ipv4| ipv4.octets()).collect();
+        write_long_data(tag, &data, buffer)
     }
 }
 
 impl OptionToByte for String {
     fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
-        buffer.write_tag(tag);
-        let bytes = self.as_bytes();
-        buffer.write_len(bytes.len() as u8);
-        buffer.write_slice(2, &bytes);
-        bytes.len()
+        write_long_data(tag, self.as_bytes(), buffer)
     }
 }
 
@@ -69,10 +88,7 @@ impl OptionToByte for DHCPMessageType {
 
 impl OptionToByte for Vec<u8> {
     fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
-        buffer.write_tag(tag);
-        buffer.write_len(self.len() as u8);
-        buffer.write_slice(2, &self);
-        self.len()
+        write_long_data(tag, self, buffer)
     }
 }
 
@@ -84,3 +100,100 @@ impl OptionToByte for u16 {
         2
     }
 }
+
+impl OptionToByte for u32 {
+    fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
+        buffer.write_tag(tag);
+        buffer.write_len(4);
+        buffer.write_u32(2, *self);
+        4
+    }
+}
+
+impl OptionToByte for i32 {
+    fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
+        buffer.write_tag(tag);
+        buffer.write_len(4);
+        buffer.write_u32(2, *self as u32);
+        4
+    }
+}
+
+impl OptionToByte for Vec<(Ipv4Addr, Ipv4Addr)> {
+    fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
+        let data: Vec<u8> = self
+            .iter()
+            .flat_map(|(destination, router)| [destination.octets(), router.octets()])
+            .flatten()
+            .collect();
+        write_long_data(tag, &data, buffer)
+    }
+}
+
+impl OptionToByte for NetBiosNodeType {
+    fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
+        buffer.write_tag(tag);
+        buffer.write_len(1);
+        buffer[2] = (*self).into();
+        1
+    }
+}
+
+impl OptionToByte for Vec<ClasslessRoute> {
+    fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
+        let mut data = Vec::with_capacity(self.iter().map(ClasslessRoute::encoded_len).sum());
+        for route in self {
+            data.push(route.prefix_len);
+            data.extend_from_slice(&route.destination.octets()[..route.significant_octets()]);
+            data.extend_from_slice(&route.router.octets());
+        }
+        // een route kan over twee opties verdeeld worden, RFC 3396 plakt
+        // die weer aan elkaar
+        write_long_data(tag, &data, buffer)
+    }
+}
+
+impl OptionToByte for ClientFqdn {
+    fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
+        // RCODE1 en RCODE2 zijn altijd 255 in een antwoord van de server
+        let mut data = vec![self.flags.into(), 255, 255];
+        if self.flags.encoded {
+            dns::encode_name(&self.name, &mut data);
+            // gedeeltelijke naam, zonder 0 label
+            if !self.name.ends_with('.') {
+                data.pop();
+            }
+        } else {
+            data.extend_from_slice(self.name.as_bytes());
+        }
+        // hostname en domain_name samen kunnen langer zijn dan 252 bytes
+        write_long_data(tag, &data, buffer)
+    }
+}
+
+impl OptionToByte for Vec<u16> {
+    fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
+        let data: Vec<u8> = self.iter().flat_map(|value| value.to_be_bytes()).collect();
+        write_long_data(tag, &data, buffer)
+    }
+}
+
+impl OptionToByte for NetworkInterfaceId {
+    fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
+        buffer.write_tag(tag);
+        buffer.write_len(3);
+        buffer.write_slice(2, &[self.interface_type, self.major, self.minor]);
+        3
+    }
+}
+
+impl OptionToByte for [u8; 16] {
+    fn write_option_bytes(&self, tag: u8, buffer: &mut [u8]) -> usize {
+        buffer.write_tag(tag);
+        buffer.write_len(17);
+        // type 0: UUID
+        buffer[2] = 0;
+        buffer.write_slice(3, self);
+        17
+    }
+}
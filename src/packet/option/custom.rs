@@ -0,0 +1,157 @@
+//! Opties zonder eigen variant in [`DHCPOption`](super::DHCPOption), met een
+//! type uit de config (`[[custom_option]]`).
+
+use std::{net::Ipv4Addr, str::FromStr};
+
+use super::{dns, OptionParseErr};
+
+/// Het type van een eigen optie. Een record is een reeks velden achter
+/// elkaar, enkel het laatste veld mag een variabele lengte hebben.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionType {
+    Ip,
+    IpList,
+    U8,
+    U16,
+    U32,
+    Bool,
+    String,
+    Hex,
+    /// Domeinnamen zoals in option 119 (RFC 1035 met compressie)
+    DomainList,
+    Record(Vec<OptionType>),
+}
+
+/// De waarde van een eigen optie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OptionValue {
+    Ip(Ipv4Addr),
+    IpList(Vec<Ipv4Addr>),
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    Bool(bool),
+    String(String),
+    Hex(Vec<u8>),
+    DomainList(Vec<String>),
+    Record(Vec<OptionValue>),
+}
+
+/// Een eigen optie met de naam uit de config, de naam wordt niet verstuurd.
+#[derive(Debug, Clone)]
+pub struct CustomOption {
+    pub code: u8,
+    pub name: String,
+    pub value: OptionValue,
+}
+
+impl FromStr for OptionType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "ip" => OptionType::Ip,
+            "ip-list" => OptionType::IpList,
+            "u8" => OptionType::U8,
+            "u16" => OptionType::U16,
+            "u32" => OptionType::U32,
+            "bool" => OptionType::Bool,
+            "string" => OptionType::String,
+            "hex" => OptionType::Hex,
+            "domain-list" => OptionType::DomainList,
+            _ => return Err(format!("Unknown option type: {s}")),
+        })
+    }
+}
+
+impl OptionType {
+    /// Het aantal bytes van een waarde van dit type, `None` als dat
+    /// afhangt van de waarde.
+    pub fn fixed_len(&self) -> Option<usize> {
+        match self {
+            OptionType::Ip | OptionType::U32 => Some(4),
+            OptionType::U16 => Some(2),
+            OptionType::U8 | OptionType::Bool => Some(1),
+            OptionType::Record(fields) => fields.iter().map(OptionType::fixed_len).sum(),
+            OptionType::IpList | OptionType::String | OptionType::Hex | OptionType::DomainList => {
+                None
+            }
+        }
+    }
+
+    /// Leest een waarde van dit type uit de data van optie `code`.
+    pub fn decode(&self, code: u8, data: &[u8]) -> Result<OptionValue, OptionParseErr> {
+        let invalid_len = || OptionParseErr::InvalidLength {
+            option_code: code,
+            len: data.len(),
+        };
+        if self.fixed_len().is_some_and(|len| len != data.len()) {
+            return Err(invalid_len());
+        }
+
+        let value = match self {
+            OptionType::Ip => OptionValue::Ip(Ipv4Addr::new(data[0], data[1], data[2], data[3])),
+            OptionType::IpList => {
+                if data.is_empty() || !data.len().is_multiple_of(4) {
+                    return Err(invalid_len());
+                }
+                OptionValue::IpList(
+                    data.chunks(4)
+                        .map(|ip| Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3]))
+                        .collect(),
+                )
+            }
+            OptionType::U8 => OptionValue::U8(data[0]),
+            OptionType::U16 => OptionValue::U16(u16::from_be_bytes([data[0], data[1]])),
+            OptionType::U32 => {
+                OptionValue::U32(u32::from_be_bytes([data[0], data[1], data[2], data[3]]))
+            }
+            OptionType::Bool => match data[0] {
+                0 => OptionValue::Bool(false),
+                1 => OptionValue::Bool(true),
+                _ => return Err(OptionParseErr::InvalidValue(code)),
+            },
+            OptionType::String => OptionValue::String(std::str::from_utf8(data)?.to_string()),
+            OptionType::Hex => OptionValue::Hex(data.to_vec()),
+            OptionType::DomainList => OptionValue::DomainList(
+                dns::decode_names(data).map_err(|_| OptionParseErr::InvalidValue(code))?,
+            ),
+            OptionType::Record(fields) => {
+                let mut values = Vec::with_capacity(fields.len());
+                let mut rest = data;
+                for (i, field) in fields.iter().enumerate() {
+                    // het laatste veld krijgt de rest van de data
+                    let len = match field.fixed_len() {
+                        Some(len) if i + 1 < fields.len() => len,
+                        _ => rest.len(),
+                    };
+                    if len > rest.len() {
+                        return Err(OptionParseErr::Truncated { option_code: code });
+                    }
+                    let (field_data, next) = rest.split_at(len);
+                    values.push(field.decode(code, field_data)?);
+                    rest = next;
+                }
+                OptionValue::Record(values)
+            }
+        };
+        Ok(value)
+    }
+}
+
+impl OptionValue {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            OptionValue::Ip(ip) => ip.octets().to_vec(),
+            OptionValue::IpList(ips) => ips.iter().flat_map(|ip| ip.octets()).collect(),
+            OptionValue::U8(value) => vec![*value],
+            OptionValue::U16(value) => value.to_be_bytes().to_vec(),
+            OptionValue::U32(value) => value.to_be_bytes().to_vec(),
+            OptionValue::Bool(value) => vec![*value as u8],
+            OptionValue::String(value) => value.as_bytes().to_vec(),
+            OptionValue::Hex(data) => data.clone(),
+            OptionValue::DomainList(names) => dns::encode_names(names),
+            OptionValue::Record(fields) => fields.iter().flat_map(OptionValue::to_bytes).collect(),
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Domeinnamen in DNS wire format (RFC 1035 3.1), met compressie pointers
+//! (RFC 1035 4.1.4) zoals gebruikt in de domain search option (RFC 3397).
+
+use std::collections::HashMap;
+
+/// Maximale lengte van een label
+pub const MAX_LABEL_LEN: usize = 63;
+/// Maximale lengte van een naam in wire format
+pub const MAX_NAME_LEN: usize = 255;
+
+const POINTER: u8 = 0b1100_0000;
+/// Pointers zijn 14 bits, verder terug kan niet verwezen worden
+const MAX_POINTER_OFFSET: usize = 0x3fff;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsNameErr {
+    /// Een label is leeg of langer dan 63 bytes
+    InvalidLabel,
+    /// De naam is langer dan 255 bytes
+    NameTooLong,
+    /// De data stopt midden in een naam
+    Truncated,
+    /// Een pointer wijst niet naar een eerdere naam in de data
+    InvalidPointer,
+}
+
+/// Kijkt of `name` in wire format gezet kan worden.
+pub fn validate_name(name: &str) -> Result<(), DnsNameErr> {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    if name.is_empty() {
+        return Ok(());
+    }
+    if name
+        .split('.')
+        .any(|label| label.is_empty() || label.len() > MAX_LABEL_LEN)
+    {
+        return Err(DnsNameErr::InvalidLabel);
+    }
+    // elk label heeft een lengte byte, plus de 0 op het einde
+    if name.len() + 2 > MAX_NAME_LEN {
+        return Err(DnsNameErr::NameTooLong);
+    }
+    Ok(())
+}
+
+/// Schrijft een naam zonder compressie.
+pub fn encode_name(name: &str, buffer: &mut Vec<u8>) {
+    for label in labels(name) {
+        buffer.push(label.len() as u8);
+        buffer.extend_from_slice(label.as_bytes());
+    }
+    buffer.push(0);
+}
+
+/// Schrijft een lijst van namen achter elkaar. Een einde van een naam dat al
+/// eerder voorkwam wordt vervangen door een pointer naar die plaats.
+/// Offsets zijn relatief tegenover het begin van de data.
+pub fn encode_names(names: &[String]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    let mut suffixes: HashMap<String, usize> = HashMap::new();
+
+    for name in names {
+        let labels: Vec<&str> = labels(name).collect();
+        let mut pointer = None;
+        for i in 0..labels.len() {
+            let suffix = labels[i..].join(".").to_ascii_lowercase();
+            if let Some(&offset) = suffixes.get(&suffix) {
+                pointer = Some(offset);
+                break;
+            }
+            if buffer.len() <= MAX_POINTER_OFFSET {
+                suffixes.insert(suffix, buffer.len());
+            }
+            buffer.push(labels[i].len() as u8);
+            buffer.extend_from_slice(labels[i].as_bytes());
+        }
+        match pointer {
+            Some(offset) => {
+                buffer.push(POINTER | (offset >> 8) as u8);
+                buffer.push(offset as u8);
+            }
+            None => buffer.push(0),
+        }
+    }
+    buffer
+}
+
+/// Leest alle namen uit `data`.
+pub fn decode_names(data: &[u8]) -> Result<Vec<String>, DnsNameErr> {
+    let mut names = Vec::new();
+    let mut offset = 0;
+    while offset < data.len() {
+        let (name, next) = decode_name(data, offset)?;
+        names.push(name);
+        offset = next;
+    }
+    Ok(names)
+}
+
+/// Leest een naam die begint bij `offset` en geeft de naam en de offset
+/// na de naam terug. Pointers moeten naar voor in de data wijzen, zo kan een
+/// lus van pointers niet voorkomen.
+pub fn decode_name(data: &[u8], offset: usize) -> Result<(String, usize), DnsNameErr> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut position = offset;
+    let mut next = None;
+    let mut len = 1;
+
+    loop {
+        let &label_len = data.get(position).ok_or(DnsNameErr::Truncated)?;
+        match label_len {
+            0 => {
+                next.get_or_insert(position + 1);
+                break;
+            }
+            len_byte if len_byte & POINTER == POINTER => {
+                let &low = data.get(position + 1).ok_or(DnsNameErr::Truncated)?;
+                let target = (((len_byte & !POINTER) as usize) << 8) | low as usize;
+                if target >= position {
+                    return Err(DnsNameErr::InvalidPointer);
+                }
+                next.get_or_insert(position + 2);
+                position = target;
+            }
+            len_byte if len_byte as usize > MAX_LABEL_LEN => {
+                return Err(DnsNameErr::InvalidLabel);
+            }
+            len_byte => {
+                let start = position + 1;
+                let label = data
+                    .get(start..(start + len_byte as usize))
+                    .ok_or(DnsNameErr::Truncated)?;
+                len += label.len() + 1;
+                if len > MAX_NAME_LEN {
+                    return Err(DnsNameErr::NameTooLong);
+                }
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                position = start + label.len();
+            }
+        }
+    }
+
+    Ok((labels.join("."), next.unwrap_or(position + 1)))
+}
+
+fn labels(name: &str) -> impl Iterator<Item = &str> {
+    name.split('.')
+        .filter(|label| !label.is_empty())
+        .map(|label| &label[..label.len().min(MAX_LABEL_LEN)])
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_name, decode_names, encode_names, validate_name, DnsNameErr};
+
+    #[test]
+    fn compression() {
+        // voorbeeld uit RFC 3397 p. 3
+        let names = vec!["eng.apple.com".to_string(), "marketing.apple.com".to_string()];
+        let data = encode_names(&names);
+        let mut expected = vec![3, b'e', b'n', b'g', 5, b'a', b'p', b'p', b'l', b'e', 3, b'c', b'o', b'm', 0];
+        expected.push(9);
+        expected.extend_from_slice(b"marketing");
+        expected.extend_from_slice(&[0xc0, 0x04]);
+        assert_eq!(data, expected);
+        assert_eq!(decode_names(&data).unwrap(), names);
+    }
+
+    #[test]
+    fn pointer_loop() {
+        // pointer naar zichzelf
+        assert_eq!(decode_name(&[0xc0, 0x00], 0), Err(DnsNameErr::InvalidPointer));
+        // pointer naar achter
+        assert_eq!(
+            decode_names(&[1, b'a', 0xc0, 0x04, 0]),
+            Err(DnsNameErr::InvalidPointer)
+        );
+    }
+
+    #[test]
+    fn out_of_range() {
+        assert_eq!(decode_names(&[3, b'c', b'o']), Err(DnsNameErr::Truncated));
+        assert_eq!(decode_names(&[3, b'c', b'o', b'm']), Err(DnsNameErr::Truncated));
+        assert_eq!(decode_names(&[1, b'a', 0, 0xc0]), Err(DnsNameErr::Truncated));
+        assert_eq!(decode_names(&[0x40, b'a']), Err(DnsNameErr::InvalidLabel));
+    }
+
+    #[test]
+    fn validate() {
+        assert!(validate_name("example.com.").is_ok());
+        assert_eq!(validate_name("example..com"), Err(DnsNameErr::InvalidLabel));
+        assert_eq!(validate_name(&"a".repeat(64)), Err(DnsNameErr::InvalidLabel));
+        let long = vec!["a".repeat(63); 4].join(".");
+        assert_eq!(validate_name(&long), Err(DnsNameErr::NameTooLong));
+    }
+}
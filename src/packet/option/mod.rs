@@ -1,14 +1,19 @@
 use thiserror::Error;
 
-use crate::buffer::ByteReader;
+use crate::buffer::{ByteReader, ByteWriter};
 use std::collections::HashSet;
 use std::hash::Hash;
 use std::net::Ipv4Addr;
 use std::str;
-use self::bytes::OptionToByte;
+use self::bytes::{write_long_option, OptionToByte};
+use self::auth::Authentication;
+use self::custom::CustomOption;
 use super::time::LeaseTime;
 
+pub mod auth;
 mod bytes;
+pub mod custom;
+pub mod dns;
 
 const END_OPTION: u8 = 255;
 const PAD_OPTION: u8 = 0;
@@ -16,20 +21,46 @@ const PAD_OPTION: u8 = 0;
 /// https://datatracker.ietf.org/doc/html/rfc1533
 #[derive(Debug, Clone)]
 pub enum DHCPOption {
+    /// Een optie zonder eigen variant, met de ruwe data zodat ze later met
+    /// een `[[custom_option]]` gelezen kan worden. Wordt niet verstuurd.
     Unimplemented {
         option_code: u8,
-        len: u8,
+        data: Vec<u8>,
     },
+    /// Een optie met een type uit de config
+    Custom(CustomOption),
     /// option code 1
     Subnet(Ipv4Addr),
+    /// option code 2, offset van UTC in seconden
+    TimeOffset(i32),
     /// option code 3
     Router(Vec<Ipv4Addr>),
     /// option code 6
     DomainNameServer(Vec<Ipv4Addr>),
+    /// option code 7
+    LogServer(Vec<Ipv4Addr>),
     /// option 12
     HostName(String),
     /// option 15
     DomainName(String),
+    /// option 26, minimum 68
+    InterfaceMtu(u16),
+    /// option 28
+    BroadcastAddress(Ipv4Addr),
+    /// option 33, lijst van (bestemming, router)
+    StaticRoute(Vec<(Ipv4Addr, Ipv4Addr)>),
+    /// option 40
+    NisDomain(String),
+    /// option 41
+    NisServers(Vec<Ipv4Addr>),
+    /// option 42
+    NtpServers(Vec<Ipv4Addr>),
+    /// option 43, vendor specifieke opties als ruwe data
+    VendorSpecific(Vec<u8>),
+    /// option 44
+    NetBiosNameServers(Vec<Ipv4Addr>),
+    /// option 46
+    NetBiosNodeType(NetBiosNodeType),
     /// option 50
     RequestedIp(Ipv4Addr),
     /// option 51
@@ -54,6 +85,53 @@ pub enum DHCPOption {
     ClassIdentifier(Vec<u8>),
     /// option 61
     ClientIdentifier(Vec<u8>),
+    /// option 66, naam van de TFTP server
+    TftpServerName(String),
+    /// option 67
+    BootfileName(String),
+    /// option 77, RFC 3004, een lijst van user classes
+    UserClass(Vec<Vec<u8>>),
+    /// option 80, RFC 4039
+    RapidCommit,
+    /// option 81, RFC 4702
+    ClientFqdn(ClientFqdn),
+    /// option 82, RFC 3046, sub-opties van de relay agent
+    RelayAgentInfo(Vec<(u8, Vec<u8>)>),
+    /// option 90, RFC 3118
+    Authentication(Authentication),
+    /// option 93, RFC 4578, de architecturen die de client ondersteunt
+    ClientArchitecture(Vec<u16>),
+    /// option 94, RFC 4578
+    ClientNetworkInterface(NetworkInterfaceId),
+    /// option 97, RFC 4578, UUID/GUID van de client
+    ClientMachineId([u8; 16]),
+    /// option 108, RFC 8925, V6ONLY_WAIT in seconden
+    Ipv6OnlyPreferred(u32),
+    /// option 114, RFC 8910, URI van de captive portal API
+    CaptivePortal(String),
+    /// option 118, RFC 3011, het subnet waarin de client een adres wil
+    SubnetSelection(Ipv4Addr),
+    /// option 119, RFC 3397
+    DomainSearch(Vec<String>),
+    /// option 120, RFC 3361
+    SipServers(SipServers),
+    /// option 121, RFC 3442
+    ClasslessStaticRoute(Vec<ClasslessRoute>),
+    /// option 124, RFC 3925, vendor classes per enterprise number
+    VendorIdentifyingClass(Vec<VendorClass>),
+    /// option 125, RFC 3925, sub-opties per enterprise number
+    VendorIdentifyingInfo(Vec<VendorInfo>),
+    /// option 145, RFC 6704, de algoritmes voor een reconfigure key die de
+    /// client ondersteunt
+    ForcerenewNonceCapable(Vec<u8>),
+    /// option 150, RFC 5859, adressen van TFTP servers
+    TftpServerAddresses(Vec<Ipv4Addr>),
+    /// option 175, iPXE encapsulated options, enkel de ruwe data
+    IpxeEncapsulated(Vec<u8>),
+    /// option 249, Microsoft variant van option 121 met dezelfde encoding
+    MsClasslessStaticRoute(Vec<ClasslessRoute>),
+    /// option 252, URL van het proxy auto-config bestand (WPAD)
+    WebProxyAutoDiscovery(String),
 }
 
 impl PartialEq for DHCPOption {
@@ -74,10 +152,21 @@ impl DHCPOption {
     pub fn get_option_id(&self) -> u8 {
         match self {
             DHCPOption::Subnet(_) => 1,
+            DHCPOption::TimeOffset(_) => 2,
             DHCPOption::Router(_) => 3,
             DHCPOption::DomainNameServer(_) => 6,
+            DHCPOption::LogServer(_) => 7,
             DHCPOption::HostName(_) => 12,
             DHCPOption::DomainName(_) => 15,
+            DHCPOption::InterfaceMtu(_) => 26,
+            DHCPOption::BroadcastAddress(_) => 28,
+            DHCPOption::StaticRoute(_) => 33,
+            DHCPOption::NisDomain(_) => 40,
+            DHCPOption::NisServers(_) => 41,
+            DHCPOption::NtpServers(_) => 42,
+            DHCPOption::VendorSpecific(_) => 43,
+            DHCPOption::NetBiosNameServers(_) => 44,
+            DHCPOption::NetBiosNodeType(_) => 46,
             DHCPOption::RequestedIp(_) => 50,
             DHCPOption::IpLeasetime(_) => 51,
             DHCPOption::OptionOverload(_) => 52,
@@ -90,51 +179,105 @@ impl DHCPOption {
             DHCPOption::RebindingTime(_) => 59,
             DHCPOption::ClassIdentifier(_) => 60,
             DHCPOption::ClientIdentifier(_) => 61,
+            DHCPOption::TftpServerName(_) => 66,
+            DHCPOption::BootfileName(_) => 67,
+            DHCPOption::UserClass(_) => 77,
+            DHCPOption::RapidCommit => 80,
+            DHCPOption::ClientFqdn(_) => 81,
+            DHCPOption::RelayAgentInfo(_) => 82,
+            DHCPOption::Authentication(_) => 90,
+            DHCPOption::ClientArchitecture(_) => 93,
+            DHCPOption::ClientNetworkInterface(_) => 94,
+            DHCPOption::ClientMachineId(_) => 97,
+            DHCPOption::Ipv6OnlyPreferred(_) => 108,
+            DHCPOption::CaptivePortal(_) => 114,
+            DHCPOption::SubnetSelection(_) => 118,
+            DHCPOption::DomainSearch(_) => 119,
+            DHCPOption::SipServers(_) => 120,
+            DHCPOption::ClasslessStaticRoute(_) => 121,
+            DHCPOption::VendorIdentifyingClass(_) => 124,
+            DHCPOption::VendorIdentifyingInfo(_) => 125,
+            DHCPOption::ForcerenewNonceCapable(_) => 145,
+            DHCPOption::TftpServerAddresses(_) => 150,
+            DHCPOption::IpxeEncapsulated(_) => 175,
+            DHCPOption::MsClasslessStaticRoute(_) => 249,
+            DHCPOption::WebProxyAutoDiscovery(_) => 252,
+            DHCPOption::Custom(option) => option.code,
             DHCPOption::Unimplemented {
                 option_code,
-                len: _,
+                data: _,
             } => *option_code,
         }
     }
 
     pub fn from_bytes_many(buffer: &[u8]) -> Result<HashSet<Self>, OptionParseErr> {
         let mut start_index_option = 0;
-        let mut options: HashSet<DHCPOption> = HashSet::new();
+        // RFC 3396: opties met dezelfde code worden achter elkaar geplakt
+        // voor ze gelezen worden
+        let mut raw_options: Vec<(u8, Vec<u8>)> = Vec::new();
 
-        loop {
-            let result = DHCPOption::from_bytes(&buffer[start_index_option..])?;
-
-            match result {
-                OptionsParseResult::End => break,
-                OptionsParseResult::Padding => start_index_option += 1,
-                OptionsParseResult::Done(option, len) => {
-                    options.insert(option);
-                    start_index_option += 2 + len;
+        // sommige clients sturen geen end option
+        while start_index_option < buffer.len() {
+            let bytes = &buffer[start_index_option..];
+            match bytes[0] {
+                END_OPTION => break,
+                PAD_OPTION => start_index_option += 1,
+                tag => {
+                    let data = option_data(bytes)?;
+                    match raw_options.iter_mut().find(|(code, _)| *code == tag) {
+                        Some((_, existing)) => existing.extend_from_slice(data),
+                        None => raw_options.push((tag, data.to_vec())),
+                    }
+                    start_index_option += 2 + data.len();
                 }
             }
         }
 
+        // een optie met een ongeldige waarde wordt overgeslagen, zodat een
+        // client met bv. een kapotte vendor optie toch een antwoord krijgt
+        let options = raw_options
+            .into_iter()
+            .filter_map(|(tag, data)| match DHCPOption::from_data(tag, &data) {
+                Ok(option) => Some(option),
+                Err(error) => {
+                    println!("Skipping option {tag}: {error}");
+                    None
+                }
+            })
+            .collect();
         Ok(options)
     }
 
     pub fn to_bytes_many(options: &HashSet<DHCPOption>, buffer: &mut [u8]) -> usize {
         let mut index = 0;
         for option in options {
-            index += option.write_bytes(&mut buffer[index..]) + 2; // len van data + tag + lengte
-                                                                   // zelf
+            index += option.write_bytes(&mut buffer[index..]);
         }
         buffer[index] = END_OPTION;
         index += 1;
         index
     }
 
+    /// Schrijft de optie weg en geeft het aantal geschreven bytes terug,
+    /// tag en lengte inbegrepen.
     fn write_bytes(&self, buffer: &mut [u8]) -> usize {
-        match self {
+        let len = match self {
             DHCPOption::Subnet(subnet) => subnet.write_option_bytes(1, buffer),
+            DHCPOption::TimeOffset(offset) => offset.write_option_bytes(2, buffer),
             DHCPOption::Router(routers) => routers.write_option_bytes(3, buffer),
             DHCPOption::DomainNameServer(dns_servers) => dns_servers.write_option_bytes(6, buffer),
+            DHCPOption::LogServer(servers) => servers.write_option_bytes(7, buffer),
             DHCPOption::HostName(hostname) => hostname.write_option_bytes(12, buffer),
             DHCPOption::DomainName(domainname) => domainname.write_option_bytes(15, buffer),
+            DHCPOption::InterfaceMtu(mtu) => mtu.write_option_bytes(26, buffer),
+            DHCPOption::BroadcastAddress(ip) => ip.write_option_bytes(28, buffer),
+            DHCPOption::StaticRoute(routes) => routes.write_option_bytes(33, buffer),
+            DHCPOption::NisDomain(domain) => domain.write_option_bytes(40, buffer),
+            DHCPOption::NisServers(servers) => servers.write_option_bytes(41, buffer),
+            DHCPOption::NtpServers(servers) => servers.write_option_bytes(42, buffer),
+            DHCPOption::VendorSpecific(data) => write_long_option(43, data, buffer),
+            DHCPOption::NetBiosNameServers(servers) => servers.write_option_bytes(44, buffer),
+            DHCPOption::NetBiosNodeType(node_type) => node_type.write_option_bytes(46, buffer),
             DHCPOption::RequestedIp(ip) => ip.write_option_bytes(50, buffer),
             DHCPOption::IpLeasetime(secs) => secs.write_option_bytes(51, buffer),
             DHCPOption::OptionOverload(overload) => overload.write_option_bytes(52, buffer),
@@ -149,13 +292,71 @@ impl DHCPOption {
             DHCPOption::DHCPMessageSize(size) => size.write_option_bytes(57, buffer),
             DHCPOption::RenewalTime(secs) => secs.write_option_bytes(58, buffer),
             DHCPOption::RebindingTime(secs) => secs.write_option_bytes(59, buffer),
-            DHCPOption::ClientIdentifier(id) => id.write_option_bytes(60, buffer),
-            DHCPOption::ClassIdentifier(id) => id.write_option_bytes(61, buffer),
+            DHCPOption::ClassIdentifier(class) => class.write_option_bytes(60, buffer),
+            DHCPOption::ClientIdentifier(id) => id.write_option_bytes(61, buffer),
+            DHCPOption::TftpServerName(name) => name.write_option_bytes(66, buffer),
+            DHCPOption::BootfileName(file) => file.write_option_bytes(67, buffer),
+            DHCPOption::UserClass(classes) => {
+                let mut data = Vec::new();
+                for class in classes {
+                    data.push(class.len() as u8);
+                    data.extend_from_slice(class);
+                }
+                data.write_option_bytes(77, buffer)
+            }
+            DHCPOption::RapidCommit => {
+                buffer.write_tag(80);
+                buffer.write_len(0);
+                0
+            }
+            DHCPOption::ClientFqdn(fqdn) => fqdn.write_option_bytes(81, buffer),
+            DHCPOption::RelayAgentInfo(suboptions) => {
+                let mut data = Vec::new();
+                for (code, value) in suboptions {
+                    data.push(*code);
+                    data.push(value.len() as u8);
+                    data.extend_from_slice(value);
+                }
+                data.write_option_bytes(82, buffer)
+            }
+            DHCPOption::Authentication(auth) => {
+                return write_long_option(90, &auth.to_bytes(), buffer)
+            }
+            DHCPOption::ClientArchitecture(types) => types.write_option_bytes(93, buffer),
+            DHCPOption::ClientNetworkInterface(id) => id.write_option_bytes(94, buffer),
+            DHCPOption::ClientMachineId(uuid) => uuid.write_option_bytes(97, buffer),
+            DHCPOption::Ipv6OnlyPreferred(wait) => wait.write_option_bytes(108, buffer),
+            DHCPOption::CaptivePortal(uri) => uri.write_option_bytes(114, buffer),
+            DHCPOption::SubnetSelection(subnet) => subnet.write_option_bytes(118, buffer),
+            DHCPOption::DomainSearch(domains) => {
+                return write_long_option(119, &dns::encode_names(domains), buffer)
+            }
+            DHCPOption::SipServers(servers) => {
+                return write_long_option(120, &servers.to_bytes(), buffer)
+            }
+            DHCPOption::ClasslessStaticRoute(routes) => routes.write_option_bytes(121, buffer),
+            DHCPOption::VendorIdentifyingClass(classes) => {
+                return write_long_option(124, &VendorClass::to_bytes_many(classes), buffer)
+            }
+            DHCPOption::VendorIdentifyingInfo(infos) => {
+                return write_long_option(125, &VendorInfo::to_bytes_many(infos), buffer)
+            }
+            DHCPOption::ForcerenewNonceCapable(algorithms) => {
+                algorithms.write_option_bytes(145, buffer)
+            }
+            DHCPOption::TftpServerAddresses(servers) => servers.write_option_bytes(150, buffer),
+            DHCPOption::IpxeEncapsulated(data) => data.write_option_bytes(175, buffer),
+            DHCPOption::MsClasslessStaticRoute(routes) => routes.write_option_bytes(249, buffer),
+            DHCPOption::WebProxyAutoDiscovery(url) => url.write_option_bytes(252, buffer),
+            DHCPOption::Custom(option) => {
+                return write_long_option(option.code, &option.value.to_bytes(), buffer)
+            }
             DHCPOption::Unimplemented {
                 option_code: _,
-                len: _,
-            } => 0,
-        }
+                data: _,
+            } => return 0,
+        };
+        len + 2 // len van data + tag + lengte zelf
     }
 }
 
@@ -174,6 +375,12 @@ pub enum OptionParseErr {
     DHCPMessageType,
     #[error("StringErr")]
     StringErr,
+    #[error("Option {option_code} is truncated")]
+    Truncated { option_code: u8 },
+    #[error("Option {option_code} has invalid length {len}")]
+    InvalidLength { option_code: u8, len: usize },
+    #[error("Option {0} has an invalid value")]
+    InvalidValue(u8),
 }
 
 impl From<std::str::Utf8Error> for OptionParseErr {
@@ -192,46 +399,205 @@ impl DHCPOption {
             return Ok(OptionsParseResult::Padding);
         }
 
-        let len = bytes[1] as usize;
+        let data = option_data(bytes)?;
+        Ok(OptionsParseResult::Done(DHCPOption::from_data(tag, data)?, data.len()))
+    }
+
+    /// Of `code` een eigen variant heeft, zo'n optie kan geen
+    /// `[[custom_option]]` zijn.
+    pub fn has_variant(code: u8) -> bool {
+        code == PAD_OPTION
+            || code == END_OPTION
+            || !matches!(
+                DHCPOption::from_data(code, &[]),
+                Ok(DHCPOption::Unimplemented { .. })
+            )
+    }
+
+    /// Leest een optie uit de data zonder tag en lengte. De data kan langer
+    /// zijn dan 255 bytes als de optie over meerdere stukken verdeeld was.
+    fn from_data(tag: u8, data: &[u8]) -> Result<DHCPOption, OptionParseErr> {
+        validate_len(tag, data.len())?;
 
         let option = match tag {
-            1 => DHCPOption::Subnet(Ipv4Addr::from(bytes.read_u32(2))),
-            3 => DHCPOption::Router(
-                bytes
-                    .read_u32_many(2, len / 4)
-                    .map(Ipv4Addr::from)
+            1 => DHCPOption::Subnet(Ipv4Addr::from(data.read_u32(0))),
+            2 => DHCPOption::TimeOffset(data.read_u32(0) as i32),
+            3 => DHCPOption::Router(read_ip_list(data)),
+            6 => DHCPOption::DomainNameServer(read_ip_list(data)),
+            7 => DHCPOption::LogServer(read_ip_list(data)),
+            12 => DHCPOption::HostName(str::from_utf8(data)?.to_string()),
+            15 => DHCPOption::DomainName(str::from_utf8(data)?.to_string()),
+            26 => match data.read_u16(0) {
+                mtu if mtu >= MIN_MTU => DHCPOption::InterfaceMtu(mtu),
+                _ => return Err(OptionParseErr::InvalidValue(tag)),
+            },
+            28 => DHCPOption::BroadcastAddress(Ipv4Addr::from(data.read_u32(0))),
+            33 => DHCPOption::StaticRoute(
+                read_ip_list(data)
+                    .chunks_exact(2)
+                    .map(|route| (route[0], route[1]))
                     .collect(),
             ),
-            6 => DHCPOption::DomainNameServer(
-                bytes
-                    .read_u32_many(2, len / 4)
-                    .map(Ipv4Addr::from)
-                    .collect(),
+            40 => DHCPOption::NisDomain(str::from_utf8(data)?.to_string()),
+            41 => DHCPOption::NisServers(read_ip_list(data)),
+            42 => DHCPOption::NtpServers(read_ip_list(data)),
+            43 => DHCPOption::VendorSpecific(data.to_vec()),
+            44 => DHCPOption::NetBiosNameServers(read_ip_list(data)),
+            46 => DHCPOption::NetBiosNodeType(
+                NetBiosNodeType::try_from(data[0]).map_err(|_| OptionParseErr::InvalidValue(tag))?,
             ),
-            12 => DHCPOption::HostName(str::from_utf8(&bytes[2..(2 + len)])?.to_string()),
-            15 => DHCPOption::DomainName(str::from_utf8(&bytes[2..(2 + len)])?.to_string()),
-            50 => DHCPOption::RequestedIp(Ipv4Addr::from(bytes.read_u32(2))),
-            51 => DHCPOption::IpLeasetime(bytes.read_u32(2).into()),
+            50 => DHCPOption::RequestedIp(Ipv4Addr::from(data.read_u32(0))),
+            51 => DHCPOption::IpLeasetime(data.read_u32(0).into()),
             52 => DHCPOption::OptionOverload(
-                OptionOverload::try_from(bytes[2]).map_err(|_| OptionParseErr::OptionOverLoad)?,
+                OptionOverload::try_from(data[0]).map_err(|_| OptionParseErr::OptionOverLoad)?,
             ),
             53 => DHCPOption::DHCPMessageType(
-                DHCPMessageType::try_from(bytes[2]).map_err(|_| OptionParseErr::DHCPMessageType)?,
+                DHCPMessageType::try_from(data[0]).map_err(|_| OptionParseErr::DHCPMessageType)?,
+            ),
+            54 => DHCPOption::ServerIdentifier(Ipv4Addr::from(data.read_u32(0))),
+            55 => DHCPOption::ParameterRequest(data.to_vec()),
+            56 => DHCPOption::Message(str::from_utf8(data)?.to_string()),
+            57 => DHCPOption::DHCPMessageSize(data.read_u16(0)),
+            58 => DHCPOption::RenewalTime(data.read_u32(0).into()),
+            59 => DHCPOption::RebindingTime(data.read_u32(0).into()),
+            60 => DHCPOption::ClassIdentifier(data.to_vec()),
+            61 => DHCPOption::ClientIdentifier(data.to_vec()),
+            66 => DHCPOption::TftpServerName(str::from_utf8(data)?.to_string()),
+            67 => DHCPOption::BootfileName(str::from_utf8(data)?.to_string()),
+            77 => DHCPOption::UserClass(read_user_classes(data)),
+            80 => DHCPOption::RapidCommit,
+            81 => DHCPOption::ClientFqdn(ClientFqdn::from_data(data)?),
+            82 => DHCPOption::RelayAgentInfo(read_relay_suboptions(data)?),
+            90 => DHCPOption::Authentication(Authentication::from_data(data)?),
+            93 => DHCPOption::ClientArchitecture(
+                data.chunks(2).map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]])).collect(),
             ),
-            54 => DHCPOption::ServerIdentifier(Ipv4Addr::from(bytes.read_u32(2))),
-            55 => DHCPOption::ParameterRequest(Vec::from(&bytes[2..(2 + len)])),
-            56 => DHCPOption::Message(str::from_utf8(&bytes[2..(2 + len)])?.to_string()),
-            57 => DHCPOption::DHCPMessageSize(bytes.read_u16(2)),
-            58 => DHCPOption::RenewalTime(bytes.read_u32(2).into()),
-            59 => DHCPOption::RebindingTime(bytes.read_u32(2).into()),
-            60 => DHCPOption::ClassIdentifier(bytes[2..(2 + len)].to_vec()),
-            61 => DHCPOption::ClientIdentifier(bytes[2..(2 + len)].to_vec()),
+            94 => DHCPOption::ClientNetworkInterface(NetworkInterfaceId {
+                interface_type: data[0],
+                major: data[1],
+                minor: data[2],
+            }),
+            // type 0 is het enige type, gevolgd door de UUID
+            97 => match data {
+                [0, uuid @ ..] => DHCPOption::ClientMachineId(uuid.try_into().unwrap()),
+                _ => return Err(OptionParseErr::InvalidValue(tag)),
+            },
+            108 => DHCPOption::Ipv6OnlyPreferred(data.read_u32(0)),
+            114 => DHCPOption::CaptivePortal(str::from_utf8(data)?.to_string()),
+            118 => DHCPOption::SubnetSelection(Ipv4Addr::from(data.read_u32(0))),
+            119 => DHCPOption::DomainSearch(
+                dns::decode_names(data).map_err(|_| OptionParseErr::InvalidValue(tag))?,
+            ),
+            120 => DHCPOption::SipServers(SipServers::from_data(data)?),
+            121 => DHCPOption::ClasslessStaticRoute(ClasslessRoute::from_bytes_many(tag, data)?),
+            124 => DHCPOption::VendorIdentifyingClass(VendorClass::from_bytes_many(data)?),
+            125 => DHCPOption::VendorIdentifyingInfo(VendorInfo::from_bytes_many(data)?),
+            145 => DHCPOption::ForcerenewNonceCapable(data.to_vec()),
+            150 => DHCPOption::TftpServerAddresses(read_ip_list(data)),
+            175 => DHCPOption::IpxeEncapsulated(data.to_vec()),
+            249 => DHCPOption::MsClasslessStaticRoute(ClasslessRoute::from_bytes_many(tag, data)?),
+            252 => DHCPOption::WebProxyAutoDiscovery(str::from_utf8(data)?.to_string()),
             option_code => DHCPOption::Unimplemented {
                 option_code,
-                len: len as u8,
+                data: data.to_vec(),
             },
         };
-        Ok(OptionsParseResult::Done(option, len))
+        Ok(option)
+    }
+}
+
+/// Geeft de data van de optie die begint bij `bytes[0]`.
+fn option_data(bytes: &[u8]) -> Result<&[u8], OptionParseErr> {
+    let tag = bytes[0];
+    let Some(&len) = bytes.get(1) else {
+        return Err(OptionParseErr::Truncated { option_code: tag });
+    };
+    bytes
+        .get(2..(2 + len as usize))
+        .ok_or(OptionParseErr::Truncated { option_code: tag })
+}
+
+/// Kleinste MTU die een client moet aanvaarden (RFC 2132 5.1)
+const MIN_MTU: u16 = 68;
+
+fn read_ip_list(data: &[u8]) -> Vec<Ipv4Addr> {
+    data
+        .read_u32_many(0, data.len() / 4)
+        .map(Ipv4Addr::from)
+        .collect()
+}
+
+/// Leest de user classes (RFC 3004): elke class is een lengte byte gevolgd
+/// door de data. iPXE en sommige Windows versies sturen gewoon een string
+/// zonder lengte, die wordt dan als één class gelezen.
+fn read_user_classes(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut classes = Vec::new();
+    let mut rest = data;
+    while let [len, tail @ ..] = rest {
+        match tail.get(..*len as usize) {
+            Some(class) if *len > 0 => {
+                classes.push(class.to_vec());
+                rest = &tail[class.len()..];
+            }
+            _ => return vec![data.to_vec()],
+        }
+    }
+    classes
+}
+
+/// Leest de sub-opties van option 82 (RFC 3046 2.0): code, lengte en data.
+fn read_relay_suboptions(mut data: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, OptionParseErr> {
+    let mut suboptions = Vec::new();
+    while let [code, len, rest @ ..] = data {
+        let value = rest
+            .get(..*len as usize)
+            .ok_or(OptionParseErr::InvalidValue(82))?;
+        suboptions.push((*code, value.to_vec()));
+        data = &rest[value.len()..];
+    }
+    if !data.is_empty() {
+        return Err(OptionParseErr::InvalidValue(82));
+    }
+    Ok(suboptions)
+}
+
+/// Controleert de lengte van de opties die we kennen, volgens RFC 2132.
+fn validate_len(option_code: u8, len: usize) -> Result<(), OptionParseErr> {
+    let valid = match option_code {
+        // vaste lengte
+        1 | 2 | 28 | 50 | 51 | 54 | 58 | 59 | 108 | 118 => len == 4,
+        26 | 57 => len == 2,
+        46 | 52 | 53 => len == 1,
+        80 => len == 0,
+        // flags, rcode1 en rcode2, de naam mag leeg zijn
+        81 => len >= 3,
+        // minstens één sub-optie
+        82 => len >= 2,
+        // lijst van u16
+        93 => len >= 2 && len.is_multiple_of(2),
+        // type, major en minor
+        94 => len == 3,
+        // type en UUID
+        97 => len == 17,
+        // kleinste route is 0.0.0.0/0: 1 byte prefix + 4 bytes router
+        121 | 249 => len >= 5,
+        // enterprise number en data-len
+        124 | 125 => len >= 5,
+        // lijst van ip adressen
+        3 | 6 | 7 | 41 | 42 | 44 | 150 => len >= 4 && len.is_multiple_of(4),
+        // encoding en minstens een naam of adres
+        120 => len >= 2,
+        // protocol, algoritme, RDM en replay detection
+        90 => len >= 11,
+        // lijst van ip paren
+        33 => len >= 8 && len.is_multiple_of(8),
+        12 | 15 | 40 | 43 | 55 | 56 | 60 | 61 | 66 | 67 | 77 | 114 | 119 | 145 | 252 => len >= 1,
+        _ => true,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(OptionParseErr::InvalidLength { option_code, len })
     }
 }
 
@@ -244,6 +610,8 @@ pub enum DHCPMessageType {
     DHCPACK = 5,
     DHCPNAK = 6,
     DHCPRELEASE = 7,
+    /// RFC 3203
+    DHCPFORCERENEW = 9,
 }
 
 impl TryFrom<u8> for DHCPMessageType {
@@ -257,6 +625,7 @@ impl TryFrom<u8> for DHCPMessageType {
             5 => DHCPMessageType::DHCPACK,
             6 => DHCPMessageType::DHCPNAK,
             7 => DHCPMessageType::DHCPRELEASE,
+            9 => DHCPMessageType::DHCPFORCERENEW,
             _ => return Err(()),
         })
     }
@@ -268,6 +637,355 @@ impl From<DHCPMessageType> for u8 {
     }
 }
 
+/// Client FQDN option (81), RFC 4702
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientFqdn {
+    pub flags: FqdnFlags,
+    /// Zonder punt op het einde is het een gedeeltelijke naam
+    pub name: String,
+}
+
+/// Flags van de Client FQDN option (RFC 4702 2.1)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FqdnFlags {
+    /// S: de server moet het A record updaten
+    pub server_update: bool,
+    /// O: de server heeft de voorkeur van de client (S) overschreven
+    pub server_override: bool,
+    /// E: de naam staat in DNS wire format
+    pub encoded: bool,
+    /// N: de server doet geen DNS updates
+    pub no_update: bool,
+}
+
+impl From<u8> for FqdnFlags {
+    fn from(value: u8) -> Self {
+        FqdnFlags {
+            server_update: value & 0x01 != 0,
+            server_override: value & 0x02 != 0,
+            encoded: value & 0x04 != 0,
+            no_update: value & 0x08 != 0,
+        }
+    }
+}
+
+impl From<FqdnFlags> for u8 {
+    fn from(flags: FqdnFlags) -> Self {
+        flags.server_update as u8
+            | (flags.server_override as u8) << 1
+            | (flags.encoded as u8) << 2
+            | (flags.no_update as u8) << 3
+    }
+}
+
+impl ClientFqdn {
+    /// Het antwoord van de server op de FQDN van de client (RFC 4702 4).
+    /// `server_updates` geeft aan of de server zelf het A record bijwerkt.
+    pub fn reply(client: FqdnFlags, server_updates: bool, name: String) -> Self {
+        ClientFqdn {
+            flags: FqdnFlags {
+                server_update: server_updates,
+                server_override: client.server_update != server_updates,
+                encoded: client.encoded,
+                no_update: !server_updates,
+            },
+            name,
+        }
+    }
+
+    /// Het eerste label van de naam
+    pub fn hostname(&self) -> Option<&str> {
+        self.name.split('.').next().filter(|label| !label.is_empty())
+    }
+
+    fn from_data(data: &[u8]) -> Result<Self, OptionParseErr> {
+        let flags = FqdnFlags::from(data[0]);
+        // data[1] en data[2] zijn RCODE1 en RCODE2, die worden niet meer gebruikt
+        let name = &data[3..];
+        let name = if flags.encoded {
+            // een gedeeltelijke naam heeft geen 0 label op het einde
+            let mut wire = name.to_vec();
+            let partial = wire.last() != Some(&0);
+            if partial {
+                wire.push(0);
+            }
+            let (mut name, _) =
+                dns::decode_name(&wire, 0).map_err(|_| OptionParseErr::InvalidValue(81))?;
+            if !partial && !name.is_empty() {
+                name.push('.');
+            }
+            name
+        } else {
+            str::from_utf8(name)?.to_string()
+        };
+        Ok(ClientFqdn { flags, name })
+    }
+}
+
+/// SIP servers uit option 120 (RFC 3361). De eerste byte geeft aan of er
+/// namen of adressen volgen, beide samen kan niet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SipServers {
+    /// Encoding 0: domeinnamen zoals in option 119
+    Domains(Vec<String>),
+    /// Encoding 1: IPv4 adressen
+    Addresses(Vec<Ipv4Addr>),
+}
+
+impl SipServers {
+    const DOMAINS: u8 = 0;
+    const ADDRESSES: u8 = 1;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            SipServers::Domains(domains) => {
+                let mut data = vec![SipServers::DOMAINS];
+                data.extend(dns::encode_names(domains));
+                data
+            }
+            SipServers::Addresses(addresses) => std::iter::once(SipServers::ADDRESSES)
+                .chain(addresses.iter().flat_map(|address| address.octets()))
+                .collect(),
+        }
+    }
+
+    fn from_data(data: &[u8]) -> Result<Self, OptionParseErr> {
+        let servers = &data[1..];
+        match data[0] {
+            SipServers::DOMAINS => Ok(SipServers::Domains(
+                dns::decode_names(servers).map_err(|_| OptionParseErr::InvalidValue(120))?,
+            )),
+            SipServers::ADDRESSES if servers.len().is_multiple_of(4) => {
+                Ok(SipServers::Addresses(read_ip_list(servers)))
+            }
+            SipServers::ADDRESSES => Err(OptionParseErr::InvalidLength {
+                option_code: 120,
+                len: data.len(),
+            }),
+            _ => Err(OptionParseErr::InvalidValue(120)),
+        }
+    }
+}
+
+/// Een route uit option 121 (RFC 3442)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClasslessRoute {
+    pub destination: Ipv4Addr,
+    pub prefix_len: u8,
+    pub router: Ipv4Addr,
+}
+
+impl ClasslessRoute {
+    /// Maakt een route, de bits van `destination` buiten de prefix worden op 0 gezet.
+    pub fn new(destination: Ipv4Addr, prefix_len: u8, router: Ipv4Addr) -> Self {
+        let prefix_len = prefix_len.min(32);
+        ClasslessRoute {
+            destination: Ipv4Addr::from(u32::from(destination) & prefix_mask(prefix_len)),
+            prefix_len,
+            router,
+        }
+    }
+
+    /// Aantal bytes van de bestemming die in de optie gezet worden,
+    /// enkel de significante octetten (RFC 3442 p. 4)
+    pub fn significant_octets(&self) -> usize {
+        (self.prefix_len as usize).div_ceil(8)
+    }
+
+    /// Lengte van de route in de optie: prefix, bestemming en router
+    pub fn encoded_len(&self) -> usize {
+        1 + self.significant_octets() + 4
+    }
+
+    fn from_bytes_many(option_code: u8, mut bytes: &[u8]) -> Result<Vec<Self>, OptionParseErr> {
+        let mut routes = Vec::new();
+        while let Some(&prefix_len) = bytes.first() {
+            if prefix_len > 32 {
+                return Err(OptionParseErr::InvalidValue(option_code));
+            }
+            let octets = (prefix_len as usize).div_ceil(8);
+            if bytes.len() < 1 + octets + 4 {
+                return Err(OptionParseErr::InvalidLength {
+                    option_code,
+                    len: bytes.len(),
+                });
+            }
+            let mut destination = [0; 4];
+            destination[..octets].copy_from_slice(&bytes[1..(1 + octets)]);
+            routes.push(ClasslessRoute::new(
+                Ipv4Addr::from(destination),
+                prefix_len,
+                Ipv4Addr::from(bytes.read_u32(1 + octets)),
+            ));
+            bytes = &bytes[(1 + octets + 4)..];
+        }
+        Ok(routes)
+    }
+}
+
+fn prefix_mask(prefix_len: u8) -> u32 {
+    u32::MAX.checked_shl(32 - prefix_len as u32).unwrap_or(0)
+}
+
+/// Client Network Interface Identifier (RFC 4578 2.2)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NetworkInterfaceId {
+    /// 1 is UNDI, het enige type dat gedefinieerd is
+    pub interface_type: u8,
+    pub major: u8,
+    pub minor: u8,
+}
+
+/// Een blok uit option 124 (RFC 3925 3): de vendor classes van één
+/// enterprise number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VendorClass {
+    /// IANA private enterprise number
+    pub enterprise: u32,
+    pub data: Vec<Vec<u8>>,
+}
+
+/// Een blok uit option 125 (RFC 3925 4): de sub-opties van één enterprise
+/// number.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VendorInfo {
+    /// IANA private enterprise number
+    pub enterprise: u32,
+    pub suboptions: Vec<(u8, Vec<u8>)>,
+}
+
+/// Leest de blokken van option 124 of 125: enterprise number, data-len en
+/// de data zelf.
+fn read_enterprise_blocks(
+    option_code: u8,
+    mut data: &[u8],
+) -> Result<Vec<(u32, &[u8])>, OptionParseErr> {
+    let mut blocks = Vec::new();
+    while !data.is_empty() {
+        let invalid = OptionParseErr::InvalidLength {
+            option_code,
+            len: data.len(),
+        };
+        let [a, b, c, d, len, rest @ ..] = data else {
+            return Err(invalid);
+        };
+        let Some(block) = rest.get(..*len as usize) else {
+            return Err(invalid);
+        };
+        blocks.push((u32::from_be_bytes([*a, *b, *c, *d]), block));
+        data = &rest[block.len()..];
+    }
+    Ok(blocks)
+}
+
+fn write_enterprise_block(enterprise: u32, block: &[u8], data: &mut Vec<u8>) {
+    data.extend_from_slice(&enterprise.to_be_bytes());
+    data.push(block.len() as u8);
+    data.extend_from_slice(block);
+}
+
+impl VendorClass {
+    fn from_bytes_many(data: &[u8]) -> Result<Vec<Self>, OptionParseErr> {
+        read_enterprise_blocks(124, data)?
+            .into_iter()
+            .map(|(enterprise, mut block)| {
+                let mut classes = Vec::new();
+                while let [len, rest @ ..] = block {
+                    let class = rest
+                        .get(..*len as usize)
+                        .ok_or(OptionParseErr::InvalidValue(124))?;
+                    classes.push(class.to_vec());
+                    block = &rest[class.len()..];
+                }
+                Ok(VendorClass {
+                    enterprise,
+                    data: classes,
+                })
+            })
+            .collect()
+    }
+
+    fn to_bytes_many(classes: &[Self]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for class in classes {
+            let mut block = Vec::new();
+            for item in &class.data {
+                block.push(item.len() as u8);
+                block.extend_from_slice(item);
+            }
+            write_enterprise_block(class.enterprise, &block, &mut data);
+        }
+        data
+    }
+}
+
+impl VendorInfo {
+    fn from_bytes_many(data: &[u8]) -> Result<Vec<Self>, OptionParseErr> {
+        read_enterprise_blocks(125, data)?
+            .into_iter()
+            .map(|(enterprise, mut block)| {
+                let mut suboptions = Vec::new();
+                while let [code, len, rest @ ..] = block {
+                    let value = rest
+                        .get(..*len as usize)
+                        .ok_or(OptionParseErr::InvalidValue(125))?;
+                    suboptions.push((*code, value.to_vec()));
+                    block = &rest[value.len()..];
+                }
+                if !block.is_empty() {
+                    return Err(OptionParseErr::InvalidValue(125));
+                }
+                Ok(VendorInfo {
+                    enterprise,
+                    suboptions,
+                })
+            })
+            .collect()
+    }
+
+    fn to_bytes_many(infos: &[Self]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for info in infos {
+            let mut block = Vec::new();
+            for (code, value) in &info.suboptions {
+                block.push(*code);
+                block.push(value.len() as u8);
+                block.extend_from_slice(value);
+            }
+            write_enterprise_block(info.enterprise, &block, &mut data);
+        }
+        data
+    }
+}
+
+/// NetBIOS over TCP/IP node type (option 46): B-node, P-node, M-node of H-node
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum NetBiosNodeType {
+    B = 0x1,
+    P = 0x2,
+    M = 0x4,
+    H = 0x8,
+}
+
+impl TryFrom<u8> for NetBiosNodeType {
+    type Error = ();
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0x1 => NetBiosNodeType::B,
+            0x2 => NetBiosNodeType::P,
+            0x4 => NetBiosNodeType::M,
+            0x8 => NetBiosNodeType::H,
+            _ => return Err(()),
+        })
+    }
+}
+
+impl From<NetBiosNodeType> for u8 {
+    fn from(value: NetBiosNodeType) -> Self {
+        value as u8
+    }
+}
+
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
 pub enum OptionOverload {
     File = 1,
@@ -297,7 +1015,31 @@ impl From<OptionOverload> for u8 {
 mod test {
     use std::{collections::HashSet, net::Ipv4Addr};
 
-    use super::DHCPOption;
+    use super::{
+        auth::{Authentication, PROTOCOL_DELAYED},
+        custom::{CustomOption, OptionType, OptionValue},
+        ClasslessRoute, ClientFqdn, DHCPOption, FqdnFlags, NetBiosNodeType, NetworkInterfaceId,
+        OptionParseErr, OptionsParseResult, SipServers, VendorClass, VendorInfo,
+    };
+
+    /// Schrijft de optie weg, vergelijkt met `bytes` en leest ze terug in.
+    fn roundtrip(option: DHCPOption, bytes: &[u8]) -> DHCPOption {
+        let mut buffer = [0; 512];
+        let len = option.write_bytes(&mut buffer);
+        assert_eq!(&buffer[..len], bytes);
+
+        match DHCPOption::from_bytes(bytes).unwrap() {
+            OptionsParseResult::Done(parsed, parsed_len) => {
+                assert_eq!(parsed_len + 2, bytes.len());
+                parsed
+            }
+            result => panic!("Unexpected parse result {result:?}"),
+        }
+    }
+
+    fn parse_err(bytes: &[u8]) -> OptionParseErr {
+        DHCPOption::from_bytes(bytes).unwrap_err()
+    }
 
     #[test]
     fn testing() {
@@ -308,11 +1050,636 @@ mod test {
         let opt2 = DHCPOption::ServerIdentifier(ip2);
 
         let mut set: HashSet<DHCPOption> = HashSet::new();
-        set.insert(DHCPOption::ServerIdentifier(ip1));
+        set.insert(opt1);
 
-        assert!(!set.insert(DHCPOption::ServerIdentifier(ip2)));
+        assert!(!set.insert(opt2));
 
         assert!(set.insert(DHCPOption::Subnet(ip1)));
         assert!(!set.insert(DHCPOption::Subnet(ip2)));
     }
+
+    #[test]
+    fn rapid_commit() {
+        let options = DHCPOption::from_bytes_many(&[80, 0, 255]).unwrap();
+        assert!(options.contains(&DHCPOption::RapidCommit));
+
+        let mut buffer = [0; 8];
+        let len = DHCPOption::to_bytes_many(&options, &mut buffer);
+        assert_eq!(&buffer[..len], &[80, 0, 255]);
+    }
+
+    #[test]
+    fn time_offset() {
+        let parsed = roundtrip(DHCPOption::TimeOffset(-3600), &[2, 4, 0xff, 0xff, 0xf1, 0xf0]);
+        assert!(matches!(parsed, DHCPOption::TimeOffset(-3600)));
+        assert!(matches!(
+            parse_err(&[2, 2, 0, 0]),
+            OptionParseErr::InvalidLength { option_code: 2, len: 2 }
+        ));
+    }
+
+    #[test]
+    fn ip_lists() {
+        let servers = vec![Ipv4Addr::new(10, 0, 0, 1), Ipv4Addr::new(10, 0, 0, 2)];
+        let bytes = [10, 0, 0, 1, 10, 0, 0, 2];
+
+        for (option, code) in [
+            (DHCPOption::LogServer(servers.clone()), 7),
+            (DHCPOption::NisServers(servers.clone()), 41),
+            (DHCPOption::NtpServers(servers.clone()), 42),
+            (DHCPOption::NetBiosNameServers(servers.clone()), 44),
+        ] {
+            let mut expected = vec![code, 8];
+            expected.extend_from_slice(&bytes);
+            let parsed = roundtrip(option.clone(), &expected);
+            assert_eq!(format!("{parsed:?}"), format!("{option:?}"));
+
+            assert!(matches!(
+                parse_err(&[code, 6, 10, 0, 0, 1, 10, 0]),
+                OptionParseErr::InvalidLength { len: 6, .. }
+            ));
+            assert!(matches!(
+                parse_err(&[code, 0]),
+                OptionParseErr::InvalidLength { len: 0, .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn interface_mtu() {
+        let parsed = roundtrip(DHCPOption::InterfaceMtu(1500), &[26, 2, 0x05, 0xdc]);
+        assert!(matches!(parsed, DHCPOption::InterfaceMtu(1500)));
+        assert!(matches!(parse_err(&[26, 2, 0, 67]), OptionParseErr::InvalidValue(26)));
+        assert!(matches!(
+            parse_err(&[26, 4, 0, 0, 5, 220]),
+            OptionParseErr::InvalidLength { option_code: 26, len: 4 }
+        ));
+    }
+
+    #[test]
+    fn broadcast_address() {
+        let ip = Ipv4Addr::new(192, 168, 56, 255);
+        let parsed = roundtrip(DHCPOption::BroadcastAddress(ip), &[28, 4, 192, 168, 56, 255]);
+        assert!(matches!(parsed, DHCPOption::BroadcastAddress(parsed) if parsed == ip));
+        assert!(matches!(
+            parse_err(&[28, 3, 192, 168, 56]),
+            OptionParseErr::InvalidLength { option_code: 28, len: 3 }
+        ));
+    }
+
+    #[test]
+    fn static_route() {
+        let routes = vec![
+            (Ipv4Addr::new(10, 1, 0, 0), Ipv4Addr::new(192, 168, 56, 1)),
+            (Ipv4Addr::new(10, 2, 0, 0), Ipv4Addr::new(192, 168, 56, 2)),
+        ];
+        let parsed = roundtrip(
+            DHCPOption::StaticRoute(routes.clone()),
+            &[33, 16, 10, 1, 0, 0, 192, 168, 56, 1, 10, 2, 0, 0, 192, 168, 56, 2],
+        );
+        assert!(matches!(parsed, DHCPOption::StaticRoute(parsed) if parsed == routes));
+        assert!(matches!(
+            parse_err(&[33, 4, 10, 1, 0, 0]),
+            OptionParseErr::InvalidLength { option_code: 33, len: 4 }
+        ));
+    }
+
+    #[test]
+    fn nis_domain() {
+        let parsed = roundtrip(DHCPOption::NisDomain("nis".into()), &[40, 3, b'n', b'i', b's']);
+        assert!(matches!(parsed, DHCPOption::NisDomain(domain) if domain == "nis"));
+        assert!(matches!(
+            parse_err(&[40, 0]),
+            OptionParseErr::InvalidLength { option_code: 40, len: 0 }
+        ));
+    }
+
+    #[test]
+    fn netbios_node_type() {
+        let parsed = roundtrip(DHCPOption::NetBiosNodeType(NetBiosNodeType::H), &[46, 1, 8]);
+        assert!(matches!(parsed, DHCPOption::NetBiosNodeType(NetBiosNodeType::H)));
+        assert!(matches!(parse_err(&[46, 1, 3]), OptionParseErr::InvalidValue(46)));
+    }
+
+    #[test]
+    fn truncated_option() {
+        assert!(matches!(
+            parse_err(&[42, 8, 10, 0, 0, 1]),
+            OptionParseErr::Truncated { option_code: 42 }
+        ));
+        assert!(matches!(parse_err(&[42]), OptionParseErr::Truncated { option_code: 42 }));
+    }
+
+    /// Schrijft de opties weg en leest ze terug in.
+    fn roundtrip_many(option: DHCPOption) -> DHCPOption {
+        let mut buffer = [0; 2048];
+        let len = DHCPOption::to_bytes_many(&[option.clone()].into(), &mut buffer);
+        let parsed = DHCPOption::from_bytes_many(&buffer[..len]).unwrap();
+        parsed.get(&option).unwrap().clone()
+    }
+
+    #[test]
+    fn long_lists() {
+        let routers: Vec<Ipv4Addr> = (0..70).map(|i| Ipv4Addr::new(10, 0, 0, i)).collect();
+        let parsed = roundtrip_many(DHCPOption::Router(routers.clone()));
+        assert!(matches!(parsed, DHCPOption::Router(parsed) if parsed == routers));
+
+        let routes: Vec<(Ipv4Addr, Ipv4Addr)> = (0..40)
+            .map(|i| (Ipv4Addr::new(10, i, 0, 0), Ipv4Addr::new(192, 168, 56, 1)))
+            .collect();
+        let parsed = roundtrip_many(DHCPOption::StaticRoute(routes.clone()));
+        assert!(matches!(parsed, DHCPOption::StaticRoute(parsed) if parsed == routes));
+
+        let architectures: Vec<u16> = (0..130).collect();
+        let parsed = roundtrip_many(DHCPOption::ClientArchitecture(architectures.clone()));
+        assert!(matches!(parsed, DHCPOption::ClientArchitecture(parsed) if parsed == architectures));
+    }
+
+    #[test]
+    fn invalid_option_is_skipped() {
+        // subnet mask van 3 bytes
+        let options = DHCPOption::from_bytes_many(&[1, 3, 255, 255, 255, 80, 0, 255]).unwrap();
+        assert_eq!(options.len(), 1);
+        assert!(options.contains(&DHCPOption::RapidCommit));
+        // een afgebroken optie blijft een fout
+        assert!(DHCPOption::from_bytes_many(&[80, 0, 3, 8, 1]).is_err());
+    }
+
+    #[test]
+    fn classless_static_route() {
+        let router = Ipv4Addr::new(10, 0, 0, 1);
+        let routes = vec![
+            ClasslessRoute::new(Ipv4Addr::new(0, 0, 0, 0), 0, router),
+            ClasslessRoute::new(Ipv4Addr::new(10, 0, 0, 0), 8, router),
+            ClasslessRoute::new(Ipv4Addr::new(10, 17, 0, 0), 16, router),
+            ClasslessRoute::new(Ipv4Addr::new(10, 27, 129, 0), 24, router),
+            ClasslessRoute::new(Ipv4Addr::new(10, 229, 0, 128), 25, router),
+            ClasslessRoute::new(Ipv4Addr::new(10, 198, 122, 47), 32, router),
+        ];
+        // voorbeelden uit RFC 3442 p. 4
+        let bytes = [
+            121, 44,
+            0, 10, 0, 0, 1,
+            8, 10, 10, 0, 0, 1,
+            16, 10, 17, 10, 0, 0, 1,
+            24, 10, 27, 129, 10, 0, 0, 1,
+            25, 10, 229, 0, 128, 10, 0, 0, 1,
+            32, 10, 198, 122, 47, 10, 0, 0, 1,
+        ];
+        let parsed = roundtrip(DHCPOption::ClasslessStaticRoute(routes.clone()), &bytes);
+        assert!(matches!(parsed, DHCPOption::ClasslessStaticRoute(parsed) if parsed == routes));
+
+        let mut ms_bytes = bytes;
+        ms_bytes[0] = 249;
+        let parsed = roundtrip(DHCPOption::MsClasslessStaticRoute(routes.clone()), &ms_bytes);
+        assert!(matches!(parsed, DHCPOption::MsClasslessStaticRoute(parsed) if parsed == routes));
+    }
+
+    #[test]
+    fn classless_static_route_masks_destination() {
+        let route = ClasslessRoute::new(Ipv4Addr::new(10, 1, 2, 3), 16, Ipv4Addr::new(10, 0, 0, 1));
+        assert_eq!(route.destination, Ipv4Addr::new(10, 1, 0, 0));
+        assert_eq!(route.encoded_len(), 7);
+    }
+
+    #[test]
+    fn classless_static_route_invalid() {
+        // prefix groter dan 32
+        assert!(matches!(
+            parse_err(&[121, 9, 33, 10, 0, 0, 0, 10, 0, 0, 1]),
+            OptionParseErr::InvalidValue(121)
+        ));
+        // router onvolledig
+        assert!(matches!(
+            parse_err(&[121, 9, 24, 10, 0, 0, 10, 0, 0, 1, 8]),
+            OptionParseErr::InvalidLength { option_code: 121, .. }
+        ));
+        assert!(matches!(
+            parse_err(&[121, 4, 0, 10, 0, 0]),
+            OptionParseErr::InvalidLength { option_code: 121, len: 4 }
+        ));
+    }
+
+    #[test]
+    fn long_classless_static_routes() {
+        // 40 routes van 7 bytes: meer dan 255 bytes
+        let routes: Vec<ClasslessRoute> = (0..40)
+            .map(|i| ClasslessRoute {
+                destination: Ipv4Addr::new(10, i, 0, 0),
+                prefix_len: 16,
+                router: Ipv4Addr::new(192, 168, 56, 1),
+            })
+            .collect();
+        let mut buffer = [0; 1024];
+        let len = DHCPOption::ClasslessStaticRoute(routes.clone()).write_bytes(&mut buffer);
+        assert_eq!(len, 40 * 7 + 4);
+        assert_eq!(&buffer[..2], &[121, 255]);
+        // 280 bytes: 255 in de eerste optie, 25 in de tweede
+        assert_eq!(&buffer[257..259], &[121, 25]);
+
+        let parsed = roundtrip_many(DHCPOption::ClasslessStaticRoute(routes.clone()));
+        assert!(matches!(parsed, DHCPOption::ClasslessStaticRoute(parsed) if parsed == routes));
+    }
+
+    #[test]
+    fn domain_search() {
+        let domains = vec!["eng.apple.com".to_string(), "marketing.apple.com".to_string()];
+        let mut bytes = vec![119, 27];
+        bytes.extend_from_slice(b"\x03eng\x05apple\x03com\x00\x09marketing\xc0\x04");
+        let parsed = roundtrip(DHCPOption::DomainSearch(domains.clone()), &bytes);
+        assert!(matches!(parsed, DHCPOption::DomainSearch(parsed) if parsed == domains));
+
+        // pointer naar zichzelf
+        assert!(matches!(parse_err(&[119, 2, 0xc0, 0x00]), OptionParseErr::InvalidValue(119)));
+    }
+
+    #[test]
+    fn domain_search_split() {
+        let domains: Vec<String> = (0..10)
+            .map(|i| format!("{}{i}.example.com", "a".repeat(40)))
+            .collect();
+        let options: HashSet<DHCPOption> = [DHCPOption::DomainSearch(domains.clone())].into();
+
+        let mut buffer = [0; 1024];
+        let len = DHCPOption::to_bytes_many(&options, &mut buffer);
+        // 10 * 42 bytes labels + pointers/einde, meer dan 255 bytes
+        assert_eq!(buffer[0], 119);
+        assert_eq!(buffer[1], 255);
+        assert_eq!(buffer[257], 119);
+        assert_eq!(buffer[len - 1], 255);
+
+        let parsed = DHCPOption::from_bytes_many(&buffer[..len]).unwrap();
+        assert!(matches!(
+            parsed.get(&DHCPOption::DomainSearch(Vec::new())),
+            Some(DHCPOption::DomainSearch(parsed)) if *parsed == domains
+        ));
+    }
+
+    #[test]
+    fn client_fqdn_ascii() {
+        let fqdn = ClientFqdn {
+            flags: FqdnFlags {
+                server_update: true,
+                ..FqdnFlags::default()
+            },
+            name: "laptop".into(),
+        };
+        let parsed = roundtrip(
+            DHCPOption::ClientFqdn(fqdn.clone()),
+            &[81, 9, 0x01, 255, 255, b'l', b'a', b'p', b't', b'o', b'p'],
+        );
+        assert!(matches!(parsed, DHCPOption::ClientFqdn(parsed) if parsed == fqdn));
+    }
+
+    #[test]
+    fn client_fqdn_wire_format() {
+        let fqdn = ClientFqdn {
+            flags: FqdnFlags {
+                encoded: true,
+                no_update: true,
+                ..FqdnFlags::default()
+            },
+            name: "laptop.example.com.".into(),
+        };
+        let mut bytes = vec![81, 23, 0x0c, 255, 255];
+        bytes.extend_from_slice(b"\x06laptop\x07example\x03com\x00");
+        let parsed = roundtrip(DHCPOption::ClientFqdn(fqdn.clone()), &bytes);
+        assert!(matches!(parsed, DHCPOption::ClientFqdn(parsed) if parsed == fqdn));
+
+        // gedeeltelijke naam zonder 0 label
+        let partial = [81, 10, 0x04, 0, 0, 6, b'l', b'a', b'p', b't', b'o', b'p'];
+        let parsed = roundtrip(
+            DHCPOption::ClientFqdn(ClientFqdn {
+                flags: FqdnFlags { encoded: true, ..FqdnFlags::default() },
+                name: "laptop".into(),
+            }),
+            &[81, 10, 0x04, 255, 255, 6, b'l', b'a', b'p', b't', b'o', b'p'],
+        );
+        assert!(matches!(&parsed, DHCPOption::ClientFqdn(parsed) if parsed.name == "laptop"));
+        assert!(matches!(
+            DHCPOption::from_bytes(&partial).unwrap(),
+            OptionsParseResult::Done(DHCPOption::ClientFqdn(parsed), 10) if parsed.hostname() == Some("laptop")
+        ));
+    }
+
+    #[test]
+    fn client_fqdn_reply_flags() {
+        let client = FqdnFlags {
+            server_update: true,
+            encoded: true,
+            ..FqdnFlags::default()
+        };
+        let reply = ClientFqdn::reply(client, false, "laptop".into());
+        assert_eq!(u8::from(reply.flags), 0x0e);
+
+        let reply = ClientFqdn::reply(FqdnFlags::default(), true, "laptop".into());
+        assert_eq!(u8::from(reply.flags), 0x03);
+    }
+
+    #[test]
+    fn long_client_fqdn() {
+        let name = format!("{}.{}", "a".repeat(63), "b".repeat(200));
+        let fqdn = ClientFqdn {
+            flags: FqdnFlags::default(),
+            name: name.clone(),
+        };
+        let mut buffer = [0; 1024];
+        let len = DHCPOption::ClientFqdn(fqdn.clone()).write_bytes(&mut buffer);
+        assert_eq!(len, 3 + name.len() + 4);
+        assert_eq!(&buffer[..2], &[81, 255]);
+
+        let parsed = roundtrip_many(DHCPOption::ClientFqdn(fqdn));
+        assert!(matches!(parsed, DHCPOption::ClientFqdn(parsed) if parsed.name == name));
+    }
+
+    #[test]
+    fn boot_file() {
+        let mut bytes = vec![66, 9];
+        bytes.extend_from_slice(b"tftp.test");
+        let parsed = roundtrip(DHCPOption::TftpServerName("tftp.test".into()), &bytes);
+        assert!(matches!(parsed, DHCPOption::TftpServerName(name) if name == "tftp.test"));
+
+        let mut bytes = vec![67, 10];
+        bytes.extend_from_slice(b"pxelinux.0");
+        let parsed = roundtrip(DHCPOption::BootfileName("pxelinux.0".into()), &bytes);
+        assert!(matches!(parsed, DHCPOption::BootfileName(file) if file == "pxelinux.0"));
+    }
+
+    #[test]
+    fn pxe_client_options() {
+        let parsed = roundtrip(DHCPOption::ClientArchitecture(vec![0, 7]), &[93, 4, 0, 0, 0, 7]);
+        assert!(matches!(parsed, DHCPOption::ClientArchitecture(types) if types == [0, 7]));
+        assert!(matches!(
+            parse_err(&[93, 3, 0, 0, 7]),
+            OptionParseErr::InvalidLength { option_code: 93, len: 3 }
+        ));
+
+        let id = NetworkInterfaceId {
+            interface_type: 1,
+            major: 2,
+            minor: 1,
+        };
+        let parsed = roundtrip(DHCPOption::ClientNetworkInterface(id), &[94, 3, 1, 2, 1]);
+        assert!(matches!(parsed, DHCPOption::ClientNetworkInterface(parsed) if parsed == id));
+
+        let uuid: [u8; 16] = std::array::from_fn(|i| i as u8);
+        let mut bytes = vec![97, 17, 0];
+        bytes.extend_from_slice(&uuid);
+        let parsed = roundtrip(DHCPOption::ClientMachineId(uuid), &bytes);
+        assert!(matches!(parsed, DHCPOption::ClientMachineId(parsed) if parsed == uuid));
+
+        bytes[2] = 1;
+        assert!(matches!(parse_err(&bytes), OptionParseErr::InvalidValue(97)));
+    }
+
+    #[test]
+    fn user_class() {
+        let classes = vec![b"iPXE".to_vec(), b"lab".to_vec()];
+        let parsed = roundtrip(
+            DHCPOption::UserClass(classes.clone()),
+            &[77, 9, 4, b'i', b'P', b'X', b'E', 3, b'l', b'a', b'b'],
+        );
+        assert!(matches!(parsed, DHCPOption::UserClass(parsed) if parsed == classes));
+
+        // iPXE stuurt de class zonder lengte byte
+        let options = DHCPOption::from_bytes_many(&[77, 4, b'i', b'P', b'X', b'E', 255]).unwrap();
+        assert!(options
+            .iter()
+            .any(|option| matches!(option, DHCPOption::UserClass(classes) if classes == &[b"iPXE".to_vec()])));
+    }
+
+    #[test]
+    fn class_and_client_identifier() {
+        let parsed = roundtrip(
+            DHCPOption::ClassIdentifier(b"PXEClient".to_vec()),
+            &[60, 9, b'P', b'X', b'E', b'C', b'l', b'i', b'e', b'n', b't'],
+        );
+        assert!(matches!(parsed, DHCPOption::ClassIdentifier(class) if class == b"PXEClient"));
+
+        let parsed = roundtrip(DHCPOption::ClientIdentifier(vec![1, 2, 3]), &[61, 3, 1, 2, 3]);
+        assert!(matches!(parsed, DHCPOption::ClientIdentifier(id) if id == [1, 2, 3]));
+    }
+
+    #[test]
+    fn vendor_identifying_options() {
+        let classes = vec![VendorClass {
+            enterprise: 3561,
+            data: vec![b"CPE".to_vec(), b"v2".to_vec()],
+        }];
+        let parsed = roundtrip(
+            DHCPOption::VendorIdentifyingClass(classes.clone()),
+            &[124, 12, 0, 0, 0x0d, 0xe9, 7, 3, b'C', b'P', b'E', 2, b'v', b'2'],
+        );
+        assert!(matches!(parsed, DHCPOption::VendorIdentifyingClass(parsed) if parsed == classes));
+
+        let infos = vec![
+            VendorInfo {
+                enterprise: 3561,
+                suboptions: vec![(1, b"ab".to_vec())],
+            },
+            VendorInfo {
+                enterprise: 9,
+                suboptions: vec![],
+            },
+        ];
+        let parsed = roundtrip(
+            DHCPOption::VendorIdentifyingInfo(infos.clone()),
+            &[125, 14, 0, 0, 0x0d, 0xe9, 4, 1, 2, b'a', b'b', 0, 0, 0, 9, 0],
+        );
+        assert!(matches!(parsed, DHCPOption::VendorIdentifyingInfo(parsed) if parsed == infos));
+
+        // data-len groter dan de optie
+        assert!(matches!(
+            parse_err(&[125, 6, 0, 0, 0x0d, 0xe9, 4, 1]),
+            OptionParseErr::InvalidLength { option_code: 125, .. }
+        ));
+        // afgekapte sub-optie in het blok
+        assert!(matches!(
+            parse_err(&[125, 8, 0, 0, 0x0d, 0xe9, 3, 1, 5, b'a']),
+            OptionParseErr::InvalidValue(125)
+        ));
+    }
+
+    #[test]
+    fn relay_agent_info() {
+        let suboptions = vec![(1, b"eth0".to_vec()), (2, vec![0xde, 0xad])];
+        let parsed = roundtrip(
+            DHCPOption::RelayAgentInfo(suboptions.clone()),
+            &[82, 10, 1, 4, b'e', b't', b'h', b'0', 2, 2, 0xde, 0xad],
+        );
+        assert!(matches!(parsed, DHCPOption::RelayAgentInfo(parsed) if parsed == suboptions));
+        assert!(matches!(
+            parse_err(&[82, 3, 1, 4, b'e']),
+            OptionParseErr::InvalidValue(82)
+        ));
+    }
+
+    #[test]
+    fn custom_options() {
+        let kind = OptionType::Record(vec![OptionType::U16, OptionType::Ip, OptionType::String]);
+        let value = OptionValue::Record(vec![
+            OptionValue::U16(8080),
+            OptionValue::Ip(Ipv4Addr::new(10, 0, 0, 1)),
+            OptionValue::String("pbx".into()),
+        ]);
+        let option = DHCPOption::Custom(CustomOption {
+            code: 224,
+            name: "provisioning".into(),
+            value: value.clone(),
+        });
+        let bytes = [224, 9, 0x1f, 0x90, 10, 0, 0, 1, b'p', b'b', b'x'];
+        let DHCPOption::Unimplemented { option_code, data } = roundtrip(option, &bytes) else {
+            panic!("Option 224 has no variant");
+        };
+        assert_eq!(kind.decode(option_code, &data).unwrap(), value);
+        assert!(matches!(
+            kind.decode(224, &data[..5]),
+            Err(OptionParseErr::Truncated { option_code: 224 })
+        ));
+
+        let domains = OptionType::DomainList;
+        let names = OptionValue::DomainList(vec!["a.example.com".into(), "example.com".into()]);
+        assert_eq!(domains.decode(1, &names.to_bytes()).unwrap(), names);
+        assert!(OptionType::IpList.decode(1, &[10, 0, 0]).is_err());
+        assert!(OptionType::Bool.decode(1, &[2]).is_err());
+    }
+
+    #[test]
+    fn has_variant() {
+        assert!(DHCPOption::has_variant(3));
+        assert!(DHCPOption::has_variant(80));
+        assert!(DHCPOption::has_variant(255));
+        assert!(!DHCPOption::has_variant(224));
+        // geen enkele optie mag panieken op lege data
+        let variants = (0..=u8::MAX).filter(|code| DHCPOption::has_variant(*code)).count();
+        assert!(variants > 40);
+    }
+
+    #[test]
+    fn url_options() {
+        let uri = "https://portal.example.com/api";
+        let mut bytes = vec![114, uri.len() as u8];
+        bytes.extend_from_slice(uri.as_bytes());
+        let parsed = roundtrip(DHCPOption::CaptivePortal(uri.into()), &bytes);
+        assert!(matches!(parsed, DHCPOption::CaptivePortal(parsed) if parsed == uri));
+
+        let url = "http://wpad.example.com/wpad.dat";
+        let mut bytes = vec![252, url.len() as u8];
+        bytes.extend_from_slice(url.as_bytes());
+        let parsed = roundtrip(DHCPOption::WebProxyAutoDiscovery(url.into()), &bytes);
+        assert!(matches!(parsed, DHCPOption::WebProxyAutoDiscovery(parsed) if parsed == url));
+
+        assert!(matches!(
+            parse_err(&[114, 0]),
+            OptionParseErr::InvalidLength { option_code: 114, len: 0 }
+        ));
+        assert!(matches!(parse_err(&[252, 1, 0xff]), OptionParseErr::StringErr));
+    }
+
+    #[test]
+    fn ipv6_only_preferred() {
+        let parsed = roundtrip(DHCPOption::Ipv6OnlyPreferred(1800), &[108, 4, 0, 0, 0x07, 0x08]);
+        assert!(matches!(parsed, DHCPOption::Ipv6OnlyPreferred(1800)));
+        assert!(matches!(
+            parse_err(&[108, 2, 0, 0]),
+            OptionParseErr::InvalidLength { option_code: 108, len: 2 }
+        ));
+    }
+
+    #[test]
+    fn sip_servers() {
+        let domains = SipServers::Domains(vec!["sip.example.com".into(), "sip2.example.com".into()]);
+        // de tweede naam wijst naar example.com in de eerste
+        let bytes = [
+            120, 25, 0, 3, b's', b'i', b'p', 7, b'e', b'x', b'a', b'm', b'p', b'l', b'e', 3, b'c',
+            b'o', b'm', 0, 4, b's', b'i', b'p', b'2', 0xc0, 4,
+        ];
+        let parsed = roundtrip(DHCPOption::SipServers(domains.clone()), &bytes);
+        assert!(matches!(parsed, DHCPOption::SipServers(parsed) if parsed == domains));
+
+        let addresses = SipServers::Addresses(vec![
+            Ipv4Addr::new(10, 0, 0, 1),
+            Ipv4Addr::new(10, 0, 0, 2),
+        ]);
+        let parsed = roundtrip(
+            DHCPOption::SipServers(addresses.clone()),
+            &[120, 9, 1, 10, 0, 0, 1, 10, 0, 0, 2],
+        );
+        assert!(matches!(parsed, DHCPOption::SipServers(parsed) if parsed == addresses));
+
+        // onbekende encoding en een afgekapt adres
+        assert!(matches!(
+            parse_err(&[120, 5, 2, 10, 0, 0, 1]),
+            OptionParseErr::InvalidValue(120)
+        ));
+        assert!(matches!(
+            parse_err(&[120, 4, 1, 10, 0, 0]),
+            OptionParseErr::InvalidLength { option_code: 120, .. }
+        ));
+    }
+
+    #[test]
+    fn tftp_server_addresses() {
+        let servers = vec![Ipv4Addr::new(10, 0, 0, 5), Ipv4Addr::new(10, 0, 0, 6)];
+        let parsed = roundtrip(
+            DHCPOption::TftpServerAddresses(servers.clone()),
+            &[150, 8, 10, 0, 0, 5, 10, 0, 0, 6],
+        );
+        assert!(matches!(parsed, DHCPOption::TftpServerAddresses(parsed) if parsed == servers));
+        assert!(matches!(
+            parse_err(&[150, 3, 10, 0, 0]),
+            OptionParseErr::InvalidLength { option_code: 150, len: 3 }
+        ));
+    }
+
+    #[test]
+    fn subnet_selection() {
+        let subnet = Ipv4Addr::new(10, 0, 2, 0);
+        let parsed = roundtrip(DHCPOption::SubnetSelection(subnet), &[118, 4, 10, 0, 2, 0]);
+        assert!(matches!(parsed, DHCPOption::SubnetSelection(parsed) if parsed == subnet));
+        assert!(matches!(
+            parse_err(&[118, 3, 10, 0, 2]),
+            OptionParseErr::InvalidLength { option_code: 118, len: 3 }
+        ));
+    }
+
+    #[test]
+    fn authentication() {
+        // een DISCOVER vraagt om delayed authentication zonder informatie
+        let mut bytes = vec![90, 11, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0, 7];
+        let parsed = roundtrip(
+            DHCPOption::Authentication(Authentication {
+                info: Vec::new(),
+                ..Authentication::delayed(7, 0, b"key")
+            }),
+            &bytes,
+        );
+        assert!(matches!(
+            &parsed,
+            DHCPOption::Authentication(auth)
+                if auth.protocol == PROTOCOL_DELAYED && auth.replay_detection == 7
+                    && auth.secret_id().is_none()
+        ));
+
+        // secret id en MAC, die bij het schrijven van het pakket ingevuld wordt
+        bytes[1] = 31;
+        bytes.extend_from_slice(&[0, 0, 0, 42]);
+        bytes.extend_from_slice(&[0; 16]);
+        let parsed = roundtrip(
+            DHCPOption::Authentication(Authentication::delayed(7, 42, b"key")),
+            &bytes,
+        );
+        assert!(matches!(
+            &parsed,
+            DHCPOption::Authentication(auth)
+                if auth.secret_id() == Some(42) && auth.mac() == Some(&[0; 16][..])
+        ));
+        assert!(matches!(
+            parse_err(&[90, 10, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0]),
+            OptionParseErr::InvalidLength { option_code: 90, len: 10 }
+        ));
+
+        let parsed = roundtrip(DHCPOption::ForcerenewNonceCapable(vec![1]), &[145, 1, 1]);
+        assert!(matches!(parsed, DHCPOption::ForcerenewNonceCapable(algorithms) if algorithms == [1]));
+    }
 }
@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{cmp::Ordering, time::Duration};
 
 
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
@@ -22,12 +22,48 @@ impl From<Duration> for LeaseTime {
     }
 }
 
+impl PartialOrd for LeaseTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Een oneindige lease is langer dan elke eindige lease.
+impl Ord for LeaseTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (LeaseTime::Infinite, LeaseTime::Infinite) => Ordering::Equal,
+            (LeaseTime::Infinite, LeaseTime::Finite(_)) => Ordering::Greater,
+            (LeaseTime::Finite(_), LeaseTime::Infinite) => Ordering::Less,
+            (LeaseTime::Finite(a), LeaseTime::Finite(b)) => a.cmp(b),
+        }
+    }
+}
+
 impl LeaseTime {
-    pub fn to_bytes(&self) -> u32 {
+    pub fn to_bytes(self) -> u32 {
         match self {
             LeaseTime::Infinite => 0xffffffff,
             LeaseTime::Finite(duration) => duration.as_secs() as u32,
         }
     }
-}
 
+    /// T1, standaard 50% van de leasetijd (RFC 2131 4.4.5)
+    pub fn renewal_time(self) -> LeaseTime {
+        self.fraction(1, 2)
+    }
+
+    /// T2, standaard 87.5% van de leasetijd (RFC 2131 4.4.5)
+    pub fn rebinding_time(self) -> LeaseTime {
+        self.fraction(7, 8)
+    }
+
+    fn fraction(self, numerator: u32, denominator: u32) -> LeaseTime {
+        match self {
+            LeaseTime::Infinite => LeaseTime::Infinite,
+            LeaseTime::Finite(duration) => {
+                LeaseTime::Finite(duration * numerator / denominator)
+            }
+        }
+    }
+}
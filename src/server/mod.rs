@@ -1,5 +1,6 @@
-use std::net::{IpAddr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
+use socket2::{Domain, Protocol, Socket, Type};
 use tokio::{
     net::UdpSocket,
     sync::mpsc::{Receiver, Sender},
@@ -13,24 +14,51 @@ use crate::{
 };
 
 pub struct Server {
-    sender: Sender<Client>,
     receiver: Receiver<Client>,
 }
 
 impl Server {
-    pub async fn start() -> Result<Self, DHCPError> {
-        // send client responses back
-        let (sender1, mut receiver1) = tokio::sync::mpsc::channel(10);
-
+    /// Luistert op `addr` en `port`. Op de DHCP server poort (67) gaan
+    /// antwoorden naar de client poort (68), op andere poorten (bv. de PXE
+    /// boot server poort 4011) rechtstreeks terug naar de afzender.
+    /// Met `interfaces` komt er een socket per interface, zodat we weten op
+    /// welke interface een pakket binnenkwam.
+    pub async fn start(addr: Ipv4Addr, port: u16, interfaces: &[String]) -> Result<Self, DHCPError> {
         // receive client packets
         let (sender2, receiver2) = tokio::sync::mpsc::channel(10);
 
-        let server = UdpSocket::bind((BROADCAST_ADDR, SERVER_PORT)).await?;
-        server.set_broadcast(true)?;
+        if interfaces.is_empty() {
+            let server = UdpSocket::bind((addr, port)).await?;
+            server.set_broadcast(true)?;
+            Server::spawn_socket(server, port, None, sender2.clone());
+        }
+        for interface in interfaces {
+            let server = Server::bind_interface(addr, port, interface)?;
+            Server::spawn_socket(server, port, Some(interface.clone()), sender2.clone());
+        }
+
+        Ok(Server {
+            receiver: receiver2,
+        })
+    }
+
+    /// Een socket die enkel pakketten van `interface` krijgt (SO_BINDTODEVICE).
+    fn bind_interface(addr: Ipv4Addr, port: u16, interface: &str) -> Result<UdpSocket, DHCPError> {
+        let socket = Socket::new(Domain::IPV4, Type::DGRAM, Some(Protocol::UDP))?;
+        socket.set_reuse_address(true)?;
+        socket.set_broadcast(true)?;
+        socket.bind_device(Some(interface.as_bytes()))?;
+        socket.set_nonblocking(true)?;
+        socket.bind(&SocketAddr::from((addr, port)).into())?;
+        Ok(UdpSocket::from_std(socket.into())?)
+    }
+
+    fn spawn_socket(server: UdpSocket, port: u16, interface: Option<String>, sender2: Sender<Client>) {
+        // send client responses back
+        let (sender1, mut receiver1) = tokio::sync::mpsc::channel(10);
 
         let mut buff = [0; 4096];
 
-        let _sender1 = sender1.clone();
         task::spawn(async move {
             loop {
                 tokio::select! {
@@ -40,7 +68,7 @@ impl Server {
                             tracing::error!("Server closed");
                             panic!();
                         };
-                        if let Err(error) = Server::server_send_back(packet, &server).await {
+                        if let Err(error) = Server::server_send_back(packet, &server, port).await {
                             tracing::error!("Could not send packet: {error}");
                         };
                     },
@@ -48,7 +76,7 @@ impl Server {
                     client = server.recv_from(&mut buff) => {
                         match client {
                             Ok((len, src)) => {
-                                if let Err(error) = Server::server_receive(len, src, &buff, &sender2, _sender1.clone()).await {
+                                if let Err(error) = Server::server_receive(len, src, port, interface.clone(), &buff, &sender2, sender1.clone()).await {
                                     tracing::error!("Could receive packet: {error}");
                                 };
                             },
@@ -62,11 +90,6 @@ impl Server {
                 }
             }
         });
-
-        Ok(Server {
-            sender: sender1,
-            receiver: receiver2,
-        })
     }
 
     pub async fn receive(&mut self) -> Result<Client, DHCPError> {
@@ -76,6 +99,8 @@ impl Server {
     async fn server_receive(
         len: usize,
         src: SocketAddr,
+        port: u16,
+        interface: Option<String>,
         buff: &[u8],
         sender2: &Sender<Client>,
         _sender1: Sender<Client>
@@ -90,6 +115,8 @@ impl Server {
             .send(Client {
                 packet,
                 src,
+                port,
+                interface,
                 sender: _sender1,
             })
             .await.is_err() {
@@ -99,28 +126,43 @@ impl Server {
         Ok(())
     }
 
-    async fn server_send_back(client: Client, server: &UdpSocket) -> Result<(), DHCPError> {
+    async fn server_send_back(client: Client, server: &UdpSocket, port: u16) -> Result<(), DHCPError> {
         tracing::info!("Sending stuff back");
         let mut buff = [0; 4096];
         let len = client.packet.write_to_bytes(&mut buff);
 
-        let mut response_addr = client.src.ip();
-        if client.packet.is_broadcast() {
-            response_addr = IpAddr::from(BROADCAST_ADDR);
-        }
+        let response_addr = Server::response_addr(&client.packet, client.src, port);
         println!("sent: {response_addr:?}");
-        let sent_len = server
-            .send_to(&buff[0..len], (response_addr, CLIENT_PORT))
-            .await?;
+        let sent_len = server.send_to(&buff[0..len], response_addr).await?;
 
         assert!(len == sent_len);
         Ok(())
     }
+
+    /// Waar het antwoord op een pakket van `src` heen gaat. Een antwoord via
+    /// een relay agent gaat naar poort 67 van giaddr (RFC 2131 4.1), de
+    /// relay agent stuurt het door naar de client.
+    fn response_addr(response: &Packet, src: SocketAddr, port: u16) -> SocketAddr {
+        if port != SERVER_PORT {
+            src
+        } else if !response.giaddr.is_unspecified() {
+            SocketAddr::new(IpAddr::from(response.giaddr), SERVER_PORT)
+        } else if response.is_broadcast() {
+            SocketAddr::new(IpAddr::from(BROADCAST_ADDR), CLIENT_PORT)
+        } else {
+            SocketAddr::new(src.ip(), CLIENT_PORT)
+        }
+    }
 }
 
 pub struct Client {
     pub packet: Packet,
     pub src: SocketAddr,
+    /// De poort waarop de server het pakket ontvangen heeft
+    pub port: u16,
+    /// De interface waarop het pakket binnenkwam, als de server per
+    /// interface luistert
+    pub interface: Option<String>,
     sender: Sender<Client>,
 }
 
@@ -130,3 +172,38 @@ impl Client {
         self.sender.clone().send(self).await.expect("Channel closed");
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::net::{Ipv4Addr, SocketAddr};
+
+    use crate::{
+        packet::{DHCPMessageType, Packet},
+        standard::{BROADCAST_ADDR, CLIENT_PORT, PXE_PORT, SERVER_PORT},
+    };
+
+    use super::Server;
+
+    #[test]
+    fn response_addr() {
+        let client = SocketAddr::from((Ipv4Addr::new(192, 168, 56, 10), CLIENT_PORT));
+        let relay = SocketAddr::from((Ipv4Addr::new(10, 0, 1, 1), SERVER_PORT));
+        let mut response = Packet::new_request(DHCPMessageType::DHCPOFFER);
+        assert!(response.is_broadcast());
+        assert_eq!(
+            Server::response_addr(&response, client, SERVER_PORT),
+            SocketAddr::from((BROADCAST_ADDR, CLIENT_PORT))
+        );
+
+        // via een relay agent, ook met de broadcast flag
+        response.giaddr = Ipv4Addr::new(10, 0, 1, 1);
+        assert_eq!(Server::response_addr(&response, relay, SERVER_PORT), relay);
+
+        // de PXE boot server antwoordt aan de afzender
+        let pxe_client = SocketAddr::from((Ipv4Addr::new(192, 168, 56, 10), PXE_PORT));
+        assert_eq!(
+            Server::response_addr(&response, pxe_client, PXE_PORT),
+            pxe_client
+        );
+    }
+}
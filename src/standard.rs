@@ -2,7 +2,11 @@ use std::net::Ipv4Addr;
 
 pub const CLIENT_PORT: u16 = 68;
 pub const SERVER_PORT: u16 = 67;
+/// PXE boot server discovery (PXE spec 2.2)
+pub const PXE_PORT: u16 = 4011;
 
 pub const BROADCAST_ADDR: Ipv4Addr = Ipv4Addr::new(255, 255, 255, 255);
 // pub const BROADCAST_ADDR: Ipv4Addr = Ipv4Addr::new(0, 0, 0, 0);
 pub const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+/// Kleinste V6ONLY_WAIT in seconden (RFC 8925 3.4)
+pub const MIN_V6ONLY_WAIT: u32 = 300;
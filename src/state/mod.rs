@@ -1,6 +1,14 @@
 use std::sync::{Arc, Mutex, MutexGuard};
 
-use crate::leases::LeaseRange;
+use chrono::{DateTime, Utc};
+use tokio::sync::mpsc::UnboundedSender;
+
+use crate::{
+    auth::{self, AuthState},
+    config::{ClassConfig, Config},
+    leases::{LeaseEvent, LeasePolicy, LeaseRange},
+    options,
+};
 
 #[derive(Debug, Clone)]
 pub struct DHCPState {
@@ -9,17 +17,79 @@ pub struct DHCPState {
 
 #[derive(Debug)]
 pub struct DHCPStateInner {
-    lease_range: Mutex<LeaseRange>,
+    lease_ranges: Mutex<Vec<LeaseRange>>,
+    /// Wordt na `lease_ranges` gelockt als beide nodig zijn
+    auth: Mutex<AuthState>,
+    config: Config,
 }
 
 impl DHCPState {
-    pub fn from_lease(lease_range: LeaseRange) -> Self {
+    /// `lease_events` krijgt de veranderingen aan de namen van leases, voor
+    /// de DNS updates.
+    pub fn from_config(config: Config, lease_events: Option<UnboundedSender<LeaseEvent>>) -> Self {
+        let lease_ranges: Vec<LeaseRange> = config
+            .subnets
+            .iter()
+            .map(|subnet| {
+                let scopes = options::pool_scopes(&config, subnet);
+                LeaseRange {
+                    options: options::merge(&scopes, &config.custom_options)
+                        .into_iter()
+                        .map(|scoped| scoped.option)
+                        .collect(),
+                    domain_name: options::domain_name(&scopes),
+                    lease_events: lease_events.clone(),
+                    ..LeaseRange::from_config(subnet)
+                }
+            })
+            .collect();
         DHCPState {
-            inner: Arc::new(DHCPStateInner { lease_range: lease_range.into() }),
+            inner: Arc::new(DHCPStateInner {
+                lease_ranges: lease_ranges.into(),
+                auth: Mutex::default(),
+                config,
+            }),
         }
     }
 
-    pub fn lock<'a>(&'a self) -> MutexGuard<'a, LeaseRange> {
-        self.inner.lease_range.lock().unwrap()
+    pub fn lock(&self) -> MutexGuard<'_, Vec<LeaseRange>> {
+        self.inner.lease_ranges.lock().unwrap()
+    }
+
+    pub fn auth(&self) -> MutexGuard<'_, AuthState> {
+        self.inner.auth.lock().unwrap()
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.inner.config
+    }
+
+    /// Geeft de leases in alle ranges vrij die op `now` verlopen zijn.
+    pub fn expire_leases(&self, now: DateTime<Utc>) {
+        let expired: Vec<_> = self
+            .lock()
+            .iter_mut()
+            .flat_map(|lease_range| lease_range.expire(now))
+            .collect();
+        for ip in expired {
+            auth::forget_lease(self, ip);
+        }
+    }
+
+    /// De lease policy van de range, aangevuld met die van de classes van
+    /// de client en daarna die van de host als de client een vaste host is.
+    pub fn lease_policy(
+        &self,
+        lease_range: &LeaseRange,
+        hardware_address: &[u8],
+        classes: &[&ClassConfig],
+    ) -> LeasePolicy {
+        let policy = classes
+            .iter()
+            .fold(lease_range.policy, |policy, class| policy.merge(&class.lease_time));
+        match self.config().host(hardware_address) {
+            Some(host) => policy.merge(&host.lease_time),
+            None => policy,
+        }
     }
 }
@@ -0,0 +1,542 @@
+//! Read-only TFTP server (RFC 1350) voor bootbestanden, met de blksize,
+//! timeout en tsize opties (RFC 2347, 2348 en 2349). Elke transfer krijgt
+//! een eigen socket (transfer ID) en task.
+
+use std::{
+    net::SocketAddr,
+    path::{Component, Path, PathBuf},
+    time::Duration,
+};
+
+use tokio::{
+    fs::File,
+    io::AsyncReadExt,
+    net::UdpSocket,
+    task,
+    time::{timeout_at, Instant},
+};
+use tracing::Instrument;
+
+use crate::{config::TftpConfig, error::DHCPError};
+
+use self::packet::{ErrorCode, TftpPacket};
+
+pub mod packet;
+
+/// Blokgrootte zonder blksize optie (RFC 1350)
+const DEFAULT_BLOCK_SIZE: usize = 512;
+/// Grenzen van de blksize optie (RFC 2348)
+const MIN_BLOCK_SIZE: usize = 8;
+const MAX_BLOCK_SIZE: usize = 65464;
+/// Maximale grootte van een request
+const REQUEST_BUFFER_LEN: usize = 1024;
+
+#[derive(Debug)]
+enum TransferErr {
+    Io(std::io::Error),
+    /// Geen antwoord na alle pogingen
+    Timeout,
+    /// De client heeft de transfer afgebroken
+    Aborted(ErrorCode, String),
+    /// De client stuurde een bericht dat we niet verwachtten
+    IllegalOperation,
+}
+
+impl From<std::io::Error> for TransferErr {
+    fn from(value: std::io::Error) -> Self {
+        TransferErr::Io(value)
+    }
+}
+
+/// De instellingen van een transfer na het afspreken van de opties.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct TransferOptions {
+    block_size: usize,
+    timeout: Duration,
+}
+
+pub struct TftpServer {
+    socket: UdpSocket,
+    config: TftpConfig,
+}
+
+impl TftpServer {
+    pub async fn bind(config: &TftpConfig) -> Result<Self, DHCPError> {
+        if !config.root.is_dir() {
+            return Err(DHCPError::Config(format!(
+                "TFTP root {} is not a directory",
+                config.root.display()
+            )));
+        }
+        let socket = UdpSocket::bind(config.listen).await?;
+        Ok(TftpServer {
+            socket,
+            config: config.clone(),
+        })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr, DHCPError> {
+        Ok(self.socket.local_addr()?)
+    }
+
+    pub async fn run(self) {
+        tracing::info!(
+            "TFTP server serving {} on {}",
+            self.config.root.display(),
+            self.config.listen
+        );
+        let mut buffer = [0; REQUEST_BUFFER_LEN];
+        loop {
+            let (len, client) = match self.socket.recv_from(&mut buffer).await {
+                Ok(received) => received,
+                Err(error) => {
+                    tracing::error!("TFTP socket error: {error}");
+                    continue;
+                }
+            };
+            match TftpPacket::from_bytes(&buffer[..len]) {
+                Ok(TftpPacket::ReadRequest {
+                    filename,
+                    mode,
+                    options,
+                }) => {
+                    let span = tracing::info_span!("tftp", %client, file = %filename);
+                    let config = self.config.clone();
+                    task::spawn(
+                        async move {
+                            if let Err(error) =
+                                transfer(config, client, &filename, &mode, &options).await
+                            {
+                                tracing::warn!("Transfer failed: {error:?}");
+                            }
+                        }
+                        .instrument(span),
+                    );
+                }
+                Ok(TftpPacket::WriteRequest { filename, .. }) => {
+                    tracing::warn!("Refusing write of {filename} from {client}");
+                    self.send_error(client, ErrorCode::AccessViolation, "Server is read-only")
+                        .await;
+                }
+                Ok(packet) => {
+                    tracing::debug!("Unexpected {packet:?} from {client}");
+                    self.send_error(client, ErrorCode::UnknownTransferId, "Unknown transfer ID")
+                        .await;
+                }
+                Err(error) => tracing::debug!("Invalid TFTP packet from {client}: {error:?}"),
+            }
+        }
+    }
+
+    async fn send_error(&self, client: SocketAddr, code: ErrorCode, message: &str) {
+        let packet = TftpPacket::Error {
+            code,
+            message: message.into(),
+        };
+        if let Err(error) = self.socket.send_to(&packet.to_bytes(), client).await {
+            tracing::error!("Could not send TFTP error to {client}: {error}");
+        }
+    }
+}
+
+/// Zet de bestandsnaam uit een request om naar een pad onder `root`.
+/// Namen met `..` of die via een symlink buiten de root wijzen worden
+/// geweigerd. Sommige PXE clients gebruiken `\` als scheidingsteken.
+pub fn resolve_path(root: &Path, filename: &str) -> Result<PathBuf, ErrorCode> {
+    let filename = filename.replace('\\', "/");
+    let mut path = root.to_path_buf();
+    for component in Path::new(&filename).components() {
+        match component {
+            Component::Normal(part) => path.push(part),
+            Component::CurDir | Component::RootDir => {}
+            Component::ParentDir | Component::Prefix(_) => return Err(ErrorCode::AccessViolation),
+        }
+    }
+
+    let root = root.canonicalize().map_err(|_| ErrorCode::FileNotFound)?;
+    let path = path.canonicalize().map_err(|_| ErrorCode::FileNotFound)?;
+    if !path.starts_with(&root) {
+        return Err(ErrorCode::AccessViolation);
+    }
+    if !path.is_file() {
+        return Err(ErrorCode::FileNotFound);
+    }
+    Ok(path)
+}
+
+/// Stuurt een bestand naar `client` vanaf een nieuwe socket.
+async fn transfer(
+    config: TftpConfig,
+    client: SocketAddr,
+    filename: &str,
+    mode: &str,
+    options: &[(String, String)],
+) -> Result<(), TransferErr> {
+    let bind_addr = SocketAddr::new(config.listen.ip(), 0);
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(client).await?;
+
+    // netascii wordt zonder conversie verstuurd, bootbestanden zijn octet
+    if mode != "octet" && mode != "netascii" {
+        send_error(&socket, ErrorCode::IllegalOperation, "Unsupported mode").await?;
+        return Err(TransferErr::IllegalOperation);
+    }
+    let path = match resolve_path(&config.root, filename) {
+        Ok(path) => path,
+        Err(code) => {
+            tracing::warn!("Refusing {filename}: {code:?}");
+            send_error(&socket, code, "Cannot read file").await?;
+            return Ok(());
+        }
+    };
+    let mut file = File::open(&path).await?;
+    let size = file.metadata().await?.len();
+
+    let (transfer_options, acknowledged) = negotiate(&config, options, size);
+    let start = Instant::now();
+    tracing::info!(
+        "Sending {} ({size} bytes, block size {})",
+        path.display(),
+        transfer_options.block_size
+    );
+
+    let mut sender = Sender {
+        socket: &socket,
+        options: transfer_options,
+        retries: config.retries,
+    };
+    if !acknowledged.is_empty() {
+        sender
+            .send_and_wait(&TftpPacket::OptionAck(acknowledged).to_bytes(), 0)
+            .await?;
+    }
+
+    let mut block: u16 = 0;
+    let mut data = vec![0; transfer_options.block_size];
+    loop {
+        let len = read_block(&mut file, &mut data).await?;
+        block = block.wrapping_add(1);
+        let packet = TftpPacket::Data {
+            block,
+            data: data[..len].to_vec(),
+        };
+        sender.send_and_wait(&packet.to_bytes(), block).await?;
+        // een blok dat kleiner is dan de blokgrootte is het laatste
+        if len < transfer_options.block_size {
+            break;
+        }
+    }
+
+    tracing::info!("Sent {size} bytes in {:?}", start.elapsed());
+    Ok(())
+}
+
+/// Bepaalt de opties voor de transfer en welke opties in de OACK komen.
+/// Opties die we niet kennen of met een ongeldige waarde worden genegeerd.
+fn negotiate(
+    config: &TftpConfig,
+    requested: &[(String, String)],
+    size: u64,
+) -> (TransferOptions, Vec<(String, String)>) {
+    let mut options = TransferOptions {
+        block_size: DEFAULT_BLOCK_SIZE,
+        timeout: Duration::from_secs(config.timeout),
+    };
+    let mut acknowledged = Vec::new();
+
+    for (name, value) in requested {
+        match name.as_str() {
+            "blksize" => {
+                let Ok(block_size) = value.parse::<usize>() else {
+                    continue;
+                };
+                if block_size < MIN_BLOCK_SIZE {
+                    continue;
+                }
+                // de server mag een kleinere blokgrootte kiezen
+                options.block_size = block_size
+                    .min(MAX_BLOCK_SIZE)
+                    .min(config.max_block_size as usize);
+                acknowledged.push((name.clone(), options.block_size.to_string()));
+            }
+            "timeout" => {
+                let Ok(timeout @ 1..=255) = value.parse::<u64>() else {
+                    continue;
+                };
+                options.timeout = Duration::from_secs(timeout);
+                acknowledged.push((name.clone(), value.clone()));
+            }
+            // bij een read request vraagt de client de grootte met tsize 0
+            "tsize" => acknowledged.push((name.clone(), size.to_string())),
+            _ => {}
+        }
+    }
+    (options, acknowledged)
+}
+
+/// Leest tot `buffer` vol is of het bestand op is.
+async fn read_block(file: &mut File, buffer: &mut [u8]) -> Result<usize, std::io::Error> {
+    let mut len = 0;
+    while len < buffer.len() {
+        match file.read(&mut buffer[len..]).await? {
+            0 => break,
+            read => len += read,
+        }
+    }
+    Ok(len)
+}
+
+async fn send_error(socket: &UdpSocket, code: ErrorCode, message: &str) -> Result<(), TransferErr> {
+    let packet = TftpPacket::Error {
+        code,
+        message: message.into(),
+    };
+    socket.send(&packet.to_bytes()).await?;
+    Ok(())
+}
+
+struct Sender<'a> {
+    socket: &'a UdpSocket,
+    options: TransferOptions,
+    retries: u32,
+}
+
+impl Sender<'_> {
+    /// Stuurt `bytes` en wacht op de ACK van `block`, na een timeout wordt
+    /// opnieuw gestuurd. Oude ACKs worden genegeerd zonder opnieuw te
+    /// sturen, anders verdubbelt elk blok (Sorcerer's Apprentice, RFC 1123).
+    async fn send_and_wait(&mut self, bytes: &[u8], block: u16) -> Result<(), TransferErr> {
+        let mut buffer = [0; REQUEST_BUFFER_LEN];
+        for _ in 0..=self.retries {
+            self.socket.send(bytes).await?;
+            let deadline = Instant::now() + self.options.timeout;
+            loop {
+                let Ok(received) = timeout_at(deadline, self.socket.recv(&mut buffer)).await else {
+                    break;
+                };
+                match TftpPacket::from_bytes(&buffer[..received?]) {
+                    Ok(TftpPacket::Ack(ack)) if ack == block => return Ok(()),
+                    Ok(TftpPacket::Ack(_)) => continue,
+                    Ok(TftpPacket::Error { code, message }) => {
+                        return Err(TransferErr::Aborted(code, message))
+                    }
+                    _ => {
+                        send_error(self.socket, ErrorCode::IllegalOperation, "Expected ACK")
+                            .await?;
+                        return Err(TransferErr::IllegalOperation);
+                    }
+                }
+            }
+            tracing::debug!("Timeout waiting for ACK {block}, retransmitting");
+        }
+        Err(TransferErr::Timeout)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        net::{Ipv4Addr, SocketAddr},
+        path::PathBuf,
+        time::Duration,
+    };
+
+    use tokio::{net::UdpSocket, time::timeout};
+
+    use crate::config::TftpConfig;
+
+    use super::{
+        negotiate,
+        packet::{ErrorCode, TftpPacket},
+        resolve_path, TftpServer,
+    };
+
+    /// Een lege map voor een test, met `pxelinux.0` en een map `boot`.
+    fn tftp_root(name: &str) -> PathBuf {
+        let root = std::env::temp_dir().join(format!("dhcp-tftp-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(root.join("boot")).unwrap();
+        std::fs::write(root.join("boot/pxelinux.0"), vec![0xab; 1500]).unwrap();
+        root
+    }
+
+    fn config(root: PathBuf) -> TftpConfig {
+        TftpConfig {
+            root,
+            listen: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            timeout: 1,
+            retries: 2,
+            max_block_size: 1024,
+        }
+    }
+
+    #[test]
+    fn path_traversal() {
+        let root = tftp_root("traversal");
+        assert!(resolve_path(&root, "boot/pxelinux.0").is_ok());
+        assert!(resolve_path(&root, "/boot/pxelinux.0").is_ok());
+        assert!(resolve_path(&root, "\\boot\\pxelinux.0").is_ok());
+        assert_eq!(
+            resolve_path(&root, "../etc/passwd"),
+            Err(ErrorCode::AccessViolation)
+        );
+        assert_eq!(
+            resolve_path(&root, "boot/../../etc/passwd"),
+            Err(ErrorCode::AccessViolation)
+        );
+        assert_eq!(resolve_path(&root, "missing"), Err(ErrorCode::FileNotFound));
+        assert_eq!(resolve_path(&root, "boot"), Err(ErrorCode::FileNotFound));
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/etc", root.join("etc")).unwrap();
+            assert_eq!(
+                resolve_path(&root, "etc/hostname"),
+                Err(ErrorCode::AccessViolation)
+            );
+        }
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn option_negotiation() {
+        let config = config(PathBuf::new());
+        let requested = [
+            ("blksize".to_string(), "1468".to_string()),
+            ("tsize".to_string(), "0".to_string()),
+            ("timeout".to_string(), "0".to_string()),
+            ("windowsize".to_string(), "4".to_string()),
+        ];
+        let (options, acknowledged) = negotiate(&config, &requested, 1500);
+        assert_eq!(options.block_size, 1024);
+        assert_eq!(options.timeout, Duration::from_secs(1));
+        assert_eq!(
+            acknowledged,
+            vec![
+                ("blksize".to_string(), "1024".to_string()),
+                ("tsize".to_string(), "1500".to_string())
+            ]
+        );
+    }
+
+    async fn receive(socket: &UdpSocket) -> (TftpPacket, SocketAddr) {
+        let mut buffer = [0; 2048];
+        let (len, src) = timeout(Duration::from_secs(5), socket.recv_from(&mut buffer))
+            .await
+            .unwrap()
+            .unwrap();
+        (TftpPacket::from_bytes(&buffer[..len]).unwrap(), src)
+    }
+
+    #[tokio::test]
+    async fn read_file_with_options() {
+        let root = tftp_root("read");
+        let server = TftpServer::bind(&config(root.clone())).await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+
+        let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+        let request = TftpPacket::ReadRequest {
+            filename: "boot/pxelinux.0".into(),
+            mode: "octet".into(),
+            options: vec![
+                ("blksize".into(), "1024".into()),
+                ("tsize".into(), "0".into()),
+            ],
+        };
+        client
+            .send_to(&request.to_bytes(), server_addr)
+            .await
+            .unwrap();
+
+        let (oack, transfer) = receive(&client).await;
+        assert_ne!(transfer, server_addr);
+        assert_eq!(
+            oack,
+            TftpPacket::OptionAck(vec![
+                ("blksize".into(), "1024".into()),
+                ("tsize".into(), "1500".into())
+            ])
+        );
+
+        let mut received = Vec::new();
+        let mut ack = 0;
+        loop {
+            client
+                .send_to(&TftpPacket::Ack(ack).to_bytes(), transfer)
+                .await
+                .unwrap();
+            let (TftpPacket::Data { block, data }, _) = receive(&client).await else {
+                panic!("Expected data");
+            };
+            assert_eq!(block, ack + 1);
+            ack = block;
+            received.extend_from_slice(&data);
+            if data.len() < 1024 {
+                break;
+            }
+        }
+        client
+            .send_to(&TftpPacket::Ack(ack).to_bytes(), transfer)
+            .await
+            .unwrap();
+        assert_eq!(received, vec![0xab; 1500]);
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn refuse_write_and_missing_file() {
+        let root = tftp_root("errors");
+        let server = TftpServer::bind(&config(root.clone())).await.unwrap();
+        let server_addr = server.local_addr().unwrap();
+        tokio::spawn(server.run());
+        let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+
+        let write = TftpPacket::WriteRequest {
+            filename: "boot/pxelinux.0".into(),
+            mode: "octet".into(),
+        };
+        client
+            .send_to(&write.to_bytes(), server_addr)
+            .await
+            .unwrap();
+        assert!(matches!(
+            receive(&client).await.0,
+            TftpPacket::Error {
+                code: ErrorCode::AccessViolation,
+                ..
+            }
+        ));
+
+        let read = |filename: &str| TftpPacket::ReadRequest {
+            filename: filename.into(),
+            mode: "octet".into(),
+            options: Vec::new(),
+        };
+        client
+            .send_to(&read("boot/missing.0").to_bytes(), server_addr)
+            .await
+            .unwrap();
+        assert!(matches!(
+            receive(&client).await.0,
+            TftpPacket::Error {
+                code: ErrorCode::FileNotFound,
+                ..
+            }
+        ));
+
+        // buiten de root
+        client
+            .send_to(&read("../../etc/passwd").to_bytes(), server_addr)
+            .await
+            .unwrap();
+        assert!(matches!(
+            receive(&client).await.0,
+            TftpPacket::Error {
+                code: ErrorCode::AccessViolation,
+                ..
+            }
+        ));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
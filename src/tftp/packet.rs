@@ -0,0 +1,260 @@
+//! TFTP berichten (RFC 1350) met option extension (RFC 2347).
+
+const OPCODE_RRQ: u16 = 1;
+const OPCODE_WRQ: u16 = 2;
+const OPCODE_DATA: u16 = 3;
+const OPCODE_ACK: u16 = 4;
+const OPCODE_ERROR: u16 = 5;
+const OPCODE_OACK: u16 = 6;
+
+/// Error codes (RFC 1350 en RFC 2347)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    NotDefined,
+    FileNotFound,
+    AccessViolation,
+    DiskFull,
+    IllegalOperation,
+    UnknownTransferId,
+    FileExists,
+    NoSuchUser,
+    /// De client weigert de opties uit de OACK
+    OptionNegotiation,
+}
+
+impl From<u16> for ErrorCode {
+    fn from(value: u16) -> Self {
+        match value {
+            1 => ErrorCode::FileNotFound,
+            2 => ErrorCode::AccessViolation,
+            3 => ErrorCode::DiskFull,
+            4 => ErrorCode::IllegalOperation,
+            5 => ErrorCode::UnknownTransferId,
+            6 => ErrorCode::FileExists,
+            7 => ErrorCode::NoSuchUser,
+            8 => ErrorCode::OptionNegotiation,
+            _ => ErrorCode::NotDefined,
+        }
+    }
+}
+
+impl From<ErrorCode> for u16 {
+    fn from(value: ErrorCode) -> Self {
+        match value {
+            ErrorCode::NotDefined => 0,
+            ErrorCode::FileNotFound => 1,
+            ErrorCode::AccessViolation => 2,
+            ErrorCode::DiskFull => 3,
+            ErrorCode::IllegalOperation => 4,
+            ErrorCode::UnknownTransferId => 5,
+            ErrorCode::FileExists => 6,
+            ErrorCode::NoSuchUser => 7,
+            ErrorCode::OptionNegotiation => 8,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TftpPacket {
+    /// Namen van opties zijn in kleine letters
+    ReadRequest {
+        filename: String,
+        mode: String,
+        options: Vec<(String, String)>,
+    },
+    WriteRequest {
+        filename: String,
+        mode: String,
+    },
+    Data {
+        block: u16,
+        data: Vec<u8>,
+    },
+    Ack(u16),
+    Error {
+        code: ErrorCode,
+        message: String,
+    },
+    OptionAck(Vec<(String, String)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TftpParseErr {
+    Truncated,
+    UnknownOpcode(u16),
+    /// Een string zonder 0 op het einde of geen geldige UTF-8
+    InvalidString,
+}
+
+impl TftpPacket {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TftpParseErr> {
+        let [high, low, rest @ ..] = bytes else {
+            return Err(TftpParseErr::Truncated);
+        };
+        let read_u16 = |data: &[u8]| match data {
+            [high, low, ..] => Ok(u16::from_be_bytes([*high, *low])),
+            _ => Err(TftpParseErr::Truncated),
+        };
+
+        let packet = match u16::from_be_bytes([*high, *low]) {
+            OPCODE_RRQ => {
+                let mut strings = read_strings(rest)?.into_iter();
+                let filename = strings.next().ok_or(TftpParseErr::Truncated)?;
+                let mode = strings.next().ok_or(TftpParseErr::Truncated)?;
+                let mut options = Vec::new();
+                while let (Some(name), Some(value)) = (strings.next(), strings.next()) {
+                    options.push((name.to_ascii_lowercase(), value));
+                }
+                TftpPacket::ReadRequest {
+                    filename,
+                    mode: mode.to_ascii_lowercase(),
+                    options,
+                }
+            }
+            OPCODE_WRQ => {
+                let mut strings = read_strings(rest)?.into_iter();
+                TftpPacket::WriteRequest {
+                    filename: strings.next().ok_or(TftpParseErr::Truncated)?,
+                    mode: strings.next().ok_or(TftpParseErr::Truncated)?,
+                }
+            }
+            OPCODE_DATA => TftpPacket::Data {
+                block: read_u16(rest)?,
+                data: rest[2..].to_vec(),
+            },
+            OPCODE_ACK => TftpPacket::Ack(read_u16(rest)?),
+            OPCODE_ERROR => TftpPacket::Error {
+                code: read_u16(rest)?.into(),
+                message: read_strings(&rest[2..])?
+                    .into_iter()
+                    .next()
+                    .unwrap_or_default(),
+            },
+            OPCODE_OACK => {
+                let mut strings = read_strings(rest)?.into_iter();
+                let mut options = Vec::new();
+                while let (Some(name), Some(value)) = (strings.next(), strings.next()) {
+                    options.push((name.to_ascii_lowercase(), value));
+                }
+                TftpPacket::OptionAck(options)
+            }
+            opcode => return Err(TftpParseErr::UnknownOpcode(opcode)),
+        };
+        Ok(packet)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buffer = Vec::new();
+        match self {
+            TftpPacket::ReadRequest {
+                filename,
+                mode,
+                options,
+            } => {
+                buffer.extend_from_slice(&OPCODE_RRQ.to_be_bytes());
+                write_string(&mut buffer, filename);
+                write_string(&mut buffer, mode);
+                for (name, value) in options {
+                    write_string(&mut buffer, name);
+                    write_string(&mut buffer, value);
+                }
+            }
+            TftpPacket::WriteRequest { filename, mode } => {
+                buffer.extend_from_slice(&OPCODE_WRQ.to_be_bytes());
+                write_string(&mut buffer, filename);
+                write_string(&mut buffer, mode);
+            }
+            TftpPacket::Data { block, data } => {
+                buffer.extend_from_slice(&OPCODE_DATA.to_be_bytes());
+                buffer.extend_from_slice(&block.to_be_bytes());
+                buffer.extend_from_slice(data);
+            }
+            TftpPacket::Ack(block) => {
+                buffer.extend_from_slice(&OPCODE_ACK.to_be_bytes());
+                buffer.extend_from_slice(&block.to_be_bytes());
+            }
+            TftpPacket::Error { code, message } => {
+                buffer.extend_from_slice(&OPCODE_ERROR.to_be_bytes());
+                buffer.extend_from_slice(&u16::from(*code).to_be_bytes());
+                write_string(&mut buffer, message);
+            }
+            TftpPacket::OptionAck(options) => {
+                buffer.extend_from_slice(&OPCODE_OACK.to_be_bytes());
+                for (name, value) in options {
+                    write_string(&mut buffer, name);
+                    write_string(&mut buffer, value);
+                }
+            }
+        }
+        buffer
+    }
+}
+
+/// Leest strings die elk met een 0 eindigen.
+fn read_strings(data: &[u8]) -> Result<Vec<String>, TftpParseErr> {
+    if data.is_empty() {
+        return Ok(Vec::new());
+    }
+    let Some((_, strings)) = data.split_last().filter(|(last, _)| **last == 0) else {
+        return Err(TftpParseErr::InvalidString);
+    };
+    strings
+        .split(|b| *b == 0)
+        .map(|string| String::from_utf8(string.to_vec()).map_err(|_| TftpParseErr::InvalidString))
+        .collect()
+}
+
+fn write_string(buffer: &mut Vec<u8>, value: &str) {
+    buffer.extend_from_slice(value.as_bytes());
+    buffer.push(0);
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ErrorCode, TftpPacket, TftpParseErr};
+
+    #[test]
+    fn read_request() {
+        let bytes = b"\x00\x01pxelinux.0\x00octet\x00BLKSIZE\x001468\x00tsize\x000\x00";
+        let packet = TftpPacket::from_bytes(bytes).unwrap();
+        assert_eq!(
+            packet,
+            TftpPacket::ReadRequest {
+                filename: "pxelinux.0".into(),
+                mode: "octet".into(),
+                options: vec![
+                    ("blksize".into(), "1468".into()),
+                    ("tsize".into(), "0".into())
+                ],
+            }
+        );
+
+        assert_eq!(
+            TftpPacket::from_bytes(b"\x00\x01pxelinux.0"),
+            Err(TftpParseErr::InvalidString)
+        );
+        assert_eq!(
+            TftpPacket::from_bytes(b"\x00\x09"),
+            Err(TftpParseErr::UnknownOpcode(9))
+        );
+    }
+
+    #[test]
+    fn roundtrip() {
+        for packet in [
+            TftpPacket::Data {
+                block: 3,
+                data: vec![1, 2, 3],
+            },
+            TftpPacket::Ack(65535),
+            TftpPacket::Error {
+                code: ErrorCode::FileNotFound,
+                message: "File not found".into(),
+            },
+            TftpPacket::OptionAck(vec![("blksize".into(), "1024".into())]),
+        ] {
+            assert_eq!(TftpPacket::from_bytes(&packet.to_bytes()).unwrap(), packet);
+        }
+        assert_eq!(TftpPacket::Ack(1).to_bytes(), [0, 4, 0, 1]);
+    }
+}